@@ -1,54 +1,11 @@
 extern crate parsimony;
 
-use parsimony::tm::unmodifiable::turing::Machine;
-use parsimony::tm::unmodifiable::tape::TapeBuilder;
-use parsimony::tm::transition::{Transitions, TransitionKey, TransitionValue};
-use parsimony::tm::movement::Movement;
+use parsimony::tm::modifiable::turing::Machine;
 
 fn main() {
-    let mut machine = Some(Machine::new(
-        0,
-        TapeBuilder::with_blank("_").build(),
-        Transitions::new()
-            .insert(
-                TransitionKey::new(0, "_"),
-                TransitionValue::new(1, "I", Movement::Left))
-            .insert(
-                TransitionKey::new(0, "I"),
-                TransitionValue::new(2, "I", Movement::Right))
-            .insert(
-                TransitionKey::new(1, "_"),
-                TransitionValue::new(2, "I", Movement::Left))
-            .insert(
-                TransitionKey::new(1, "I"),
-                TransitionValue::new(1, "I", Movement::Left))
-            .insert(
-                TransitionKey::new(2, "_"),
-                TransitionValue::new(3, "I", Movement::Left))
-            .insert(
-                TransitionKey::new(2, "I"),
-                TransitionValue::new(4, "_", Movement::Right))
-            .insert(
-                TransitionKey::new(3, "_"),
-                TransitionValue::new(0, "I", Movement::Right))
-            .insert(
-                TransitionKey::new(3, "I"),
-                TransitionValue::new(3, "I", Movement::Right))
-            .insert(
-                TransitionKey::new(4, "_"),
-                TransitionValue::new(-1, "I", Movement::Left))
-            .insert(
-                TransitionKey::new(4, "I"),
-                TransitionValue::new(0, "_", Movement::Right))
-    ));
+    let mut machine = Machine::from_standard("1LB1RC_1LC1LB_1LD0RE_1RA1RD_1LZ0RA")
+        .expect("a machine in standard TM format");
 
-    loop {
-        machine = match machine {
-            Some(m) => {
-                println!("{:?}", m);
-                m.step()
-            },
-            None => break,
-        }
-    }
+    let outcome = machine.run(100_000_000);
+    println!("{:?}", outcome);
 }
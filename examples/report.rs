@@ -0,0 +1,53 @@
+extern crate parsimony;
+
+use std::env;
+use std::fs;
+use std::process;
+use parsimony::laconic;
+use parsimony::laconic::report::report;
+use parsimony::tmd;
+
+/// Compile a Laconic program and show where the states of the machine come
+/// from, as a table or, with `--json`, as JSON.
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    let json = arguments.iter().any(|argument| argument == "--json");
+    let path = match arguments.iter().find(|argument| *argument != "--json") {
+        Some(path) => path,
+
+        None => {
+            eprintln!("usage: report [--json] <program.lac>");
+            process::exit(2);
+        }
+    };
+    let source = fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    });
+
+    let mut program = laconic::parser::parse(&source).unwrap_or_else(|errors| {
+        for error in errors {
+            eprintln!("{}: {}", path, error);
+        }
+        process::exit(1);
+    });
+    let diagnostics = laconic::check::check(&mut program);
+    if !diagnostics.is_empty() {
+        for diagnostic in diagnostics {
+            eprintln!("{}: {}", path, diagnostic);
+        }
+        process::exit(1);
+    }
+    let program = tmd::parser::parse(&laconic::codegen::generate(&program)).expect("generated TMD to parse");
+    let compilation = tmd::compile::compile(&program).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+    });
+
+    let report = report(&compilation);
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+}
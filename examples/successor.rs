@@ -6,7 +6,7 @@ use parsimony::tm::transition::{Transitions, TransitionKey, TransitionValue};
 use parsimony::tm::movement::Movement;
 
 fn main() {
-    let mut machine = Some(Machine::new(
+    let machine = Machine::new(
         0,
         TapeBuilder::with_blank("_")
             .with_current("I")
@@ -24,16 +24,10 @@ fn main() {
                 TransitionValue::new(1, "I", Movement::Left))
             .insert(
                 TransitionKey::new(1, "_"),
-                TransitionValue::new(-1, "_", Movement::Right))
-    ));
+                TransitionValue::halt("_", Movement::Right))
+    );
 
-    loop {
-        machine = match machine {
-            Some(m) => {
-                println!("{:?}", m);
-                m.step()
-            },
-            None => break,
-        }
-    }
+    let (machine, outcome) = machine.run(1000);
+    println!("{:?}", machine);
+    println!("{:?}", outcome);
 }
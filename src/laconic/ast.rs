@@ -0,0 +1,261 @@
+//! The abstract syntax tree of a Laconic program.
+//!
+//! Every node carries the span of the source it was parsed from. Expressions
+//! also have a slot for their type, which is filled in by the `check` pass.
+
+use std::fmt;
+use super::lexer::Span;
+
+/// The types of Laconic values.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Type {
+    /// An unbounded natural number.
+    Int,
+    /// A list of `Int`.
+    List,
+    /// A list of `List`.
+    List2,
+}
+
+impl Type {
+    /// The type of the elements of a list type, if this is one.
+    pub fn element(&self) -> Option<Type> {
+        match *self {
+            Type::Int => None,
+
+            Type::List => Some(Type::Int),
+
+            Type::List2 => Some(Type::List),
+        }
+    }
+
+    /// The type of lists of this type, if there is one.
+    pub fn list_of(&self) -> Option<Type> {
+        match *self {
+            Type::Int => Some(Type::List),
+
+            Type::List => Some(Type::List2),
+
+            Type::List2 => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Int => write!(f, "int"),
+
+            Type::List => write!(f, "list"),
+
+            Type::List2 => write!(f, "list2"),
+        }
+    }
+}
+
+/// A complete program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program {
+    /// The functions in order of definition.
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    /// Find a function by its name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name.name == name)
+    }
+}
+
+/// A name together with where it was written.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Identifier {
+    /// The name.
+    pub name: String,
+    /// Where the name was written.
+    pub span: Span,
+}
+
+/// A function definition: `func name(int a, list b) int { ... }`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Function {
+    /// The name of the function.
+    pub name: Identifier,
+    /// The parameters of the function.
+    pub parameters: Vec<Parameter>,
+    /// The type of the returned value, if the function returns one.
+    pub returns: Option<Type>,
+    /// The statements of the function.
+    pub body: Block,
+    /// The whole definition.
+    pub span: Span,
+}
+
+/// A parameter of a function.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Parameter {
+    /// The type of the parameter.
+    pub ty: Type,
+    /// The name of the parameter.
+    pub name: Identifier,
+}
+
+/// Statements between braces.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Block {
+    /// The statements in order.
+    pub statements: Vec<Statement>,
+    /// The block, including braces.
+    pub span: Span,
+}
+
+/// A statement together with where it was written.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Statement {
+    /// What kind of statement this is.
+    pub kind: StatementKind,
+    /// The whole statement.
+    pub span: Span,
+}
+
+/// The kinds of statements.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StatementKind {
+    /// `int x;` or `int x = e;`
+    Declaration {
+        /// The type of the variable.
+        ty: Type,
+        /// The name of the variable.
+        name: Identifier,
+        /// The initial value, if given.
+        value: Option<Expression>,
+    },
+    /// `x = e;` or, with indices, `x[i][j] = e;`
+    Assignment {
+        /// The variable assigned to.
+        target: Identifier,
+        /// The indices selecting the element assigned to, outermost first.
+        indices: Vec<Expression>,
+        /// The assigned value.
+        value: Expression,
+    },
+    /// `while (c) { ... }`
+    While {
+        /// The condition checked before every iteration.
+        condition: Expression,
+        /// The statements repeated.
+        body: Block,
+    },
+    /// `if (c) { ... } else { ... }`; `else if` is an `else` block holding a
+    /// single `if`.
+    If {
+        /// The condition.
+        condition: Expression,
+        /// The statements executed when the condition holds.
+        then: Block,
+        /// The statements executed otherwise.
+        otherwise: Option<Block>,
+    },
+    /// `return;` or `return e;`
+    Return(Option<Expression>),
+    /// `print(e);`
+    Print(Expression),
+    /// An expression evaluated for its effects, i.e. a call: `f(x);`
+    Expression(Expression),
+}
+
+/// An expression together with where it was written and its type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Expression {
+    /// What kind of expression this is.
+    pub kind: ExpressionKind,
+    /// The whole expression.
+    pub span: Span,
+    /// The type of the expression, once resolved by `check`.
+    pub ty: Option<Type>,
+}
+
+impl Expression {
+    /// Create an expression of which the type is not yet known.
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression {
+        Expression { kind, span, ty: None }
+    }
+}
+
+/// The kinds of expressions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExpressionKind {
+    /// A literal natural number, as written in decimal.
+    Integer(String),
+    /// The value of a variable.
+    Variable(String),
+    /// A literal list: `[a, b, c]`.
+    List(Vec<Expression>),
+    /// An operator applied to two operands.
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    /// An operator applied to one operand.
+    Unary(UnaryOperator, Box<Expression>),
+    /// A call of a function or builtin such as `len`.
+    Call(Identifier, Vec<Expression>),
+    /// An element of a list: `l[i]`.
+    Index(Box<Expression>, Box<Expression>),
+}
+
+/// Operators with two operands, from loosest to tightest binding.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BinaryOperator {
+    /// `||`
+    Or,
+    /// `&&`
+    And,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `%`
+    Remainder,
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            BinaryOperator::Or => "||",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Operators with one operand.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UnaryOperator {
+    /// `!`
+    Not,
+}
@@ -0,0 +1,740 @@
+//! Check finds the mistakes in a parsed Laconic program that the grammar can
+//! not express, and resolves the type of every expression.
+//!
+//! The rules checked are:
+//!
+//! * variables and functions are declared before they are used;
+//! * operands, arguments, conditions and assigned values have the right type;
+//! * functions are called with the right number of arguments;
+//! * functions with a return type return a value on every path;
+//! * a name is not declared again while it is visible;
+//! * functions do not call themselves, directly or indirectly;
+//! * `main` exists, takes no parameters and returns nothing.
+//!
+//! The type of the empty list `[]` comes from where it is used. Without any
+//! hint it is a `list`.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use super::ast::{BinaryOperator, Block, Expression, ExpressionKind, Function, Identifier, Program, Statement, StatementKind, Type};
+use super::lexer::Span;
+
+/// Mistake found while checking a program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    /// Where the mistake is.
+    pub span: Span,
+    /// What the mistake is.
+    pub kind: DiagnosticKind,
+}
+
+/// The kinds of mistakes found while checking a program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DiagnosticKind {
+    /// A variable is used but not declared.
+    UndeclaredVariable(String),
+    /// A function is called but not defined.
+    UndeclaredFunction(String),
+    /// A variable or parameter is declared while the name is visible.
+    Redeclared(String),
+    /// A function is defined twice, or has the name of a builtin.
+    DuplicateFunction(String),
+    /// A value has a different type than required.
+    TypeMismatch {
+        /// The required type.
+        expected: Type,
+        /// The type of the value.
+        found: Type,
+    },
+    /// An `int` is indexed or passed to `len`.
+    NotAList(Type),
+    /// A list literal has elements of a type that can not be put in a list.
+    NoListOf(Type),
+    /// A function is called with the wrong number of arguments.
+    Arity {
+        /// The called function.
+        function: String,
+        /// The number of parameters.
+        expected: usize,
+        /// The number of arguments.
+        found: usize,
+    },
+    /// A function without a return type is used as a value.
+    NoValue(String),
+    /// An expression other than a call is used as a statement.
+    NotAStatement,
+    /// A function with a return type can finish without returning.
+    MissingReturn(String),
+    /// `return;` in a function with a return type.
+    MissingReturnValue(Type),
+    /// `return e;` in a function without a return type.
+    UnexpectedReturnValue,
+    /// There is no `main` function.
+    MissingMain,
+    /// `main` takes parameters or returns a value.
+    MainSignature,
+    /// A function calls itself, directly or indirectly.
+    Recursion(String),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.span)?;
+        match self.kind {
+            DiagnosticKind::UndeclaredVariable(ref name) => write!(f, "`{}` is not declared", name),
+
+            DiagnosticKind::UndeclaredFunction(ref name) => write!(f, "there is no function `{}`", name),
+
+            DiagnosticKind::Redeclared(ref name) => write!(f, "`{}` is already declared", name),
+
+            DiagnosticKind::DuplicateFunction(ref name) => write!(f, "function `{}` is already defined", name),
+
+            DiagnosticKind::TypeMismatch { expected, found } => write!(f, "expected {}, found {}", expected, found),
+
+            DiagnosticKind::NotAList(ty) => write!(f, "expected a list, found {}", ty),
+
+            DiagnosticKind::NoListOf(ty) => write!(f, "there are no lists of {}", ty),
+
+            DiagnosticKind::Arity { ref function, expected, found } =>
+                write!(f, "`{}` takes {} argument(s), found {}", function, expected, found),
+
+            DiagnosticKind::NoValue(ref name) => write!(f, "`{}` does not return a value", name),
+
+            DiagnosticKind::NotAStatement => write!(f, "only calls can be used as statements"),
+
+            DiagnosticKind::MissingReturn(ref name) => write!(f, "`{}` does not return a value on every path", name),
+
+            DiagnosticKind::MissingReturnValue(ty) => write!(f, "expected a return value of type {}", ty),
+
+            DiagnosticKind::UnexpectedReturnValue => write!(f, "the function does not return a value"),
+
+            DiagnosticKind::MissingMain => write!(f, "there is no function `main`"),
+
+            DiagnosticKind::MainSignature => write!(f, "`main` must take no parameters and return nothing"),
+
+            DiagnosticKind::Recursion(ref name) => write!(f, "`{}` calls itself", name),
+        }
+    }
+}
+
+impl Error for Diagnostic {}
+
+/// Check `program`, filling in the type of every expression that has one.
+/// The returned diagnostics are ordered by position and the program is only
+/// correct when there are none.
+pub fn check(program: &mut Program) -> Vec<Diagnostic> {
+    let mut checker = Checker { signatures: HashMap::new(), scopes: Vec::new(), returns: None, diagnostics: Vec::new() };
+    checker.signatures(program);
+    for function in &mut program.functions {
+        checker.function(function);
+    }
+    checker.recursion(program);
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+#[derive(Clone)]
+struct Signature {
+    parameters: Vec<Type>,
+    returns: Option<Type>,
+}
+
+/// Marker for a mistake that has already been reported.
+struct Reported;
+
+struct Checker {
+    signatures: HashMap<String, Signature>,
+    scopes: Vec<HashMap<String, Type>>,
+    returns: Option<Type>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn report(&mut self, span: Span, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic { span, kind });
+    }
+
+    fn signatures(&mut self, program: &Program) {
+        for function in &program.functions {
+            let name = &function.name;
+            if name.name == "len" || self.signatures.contains_key(&name.name) {
+                self.report(name.span, DiagnosticKind::DuplicateFunction(name.name.clone()));
+            } else {
+                let parameters = function.parameters.iter().map(|parameter| parameter.ty).collect();
+                self.signatures.insert(name.name.clone(), Signature { parameters, returns: function.returns });
+            }
+        }
+
+        match program.function("main") {
+            Some(main) => if !main.parameters.is_empty() || main.returns.is_some() {
+                self.report(main.name.span, DiagnosticKind::MainSignature);
+            },
+
+            None => self.report(Span { start: 0, end: 0, line: 1, column: 1 }, DiagnosticKind::MissingMain),
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) {
+        self.scopes = vec![HashMap::new()];
+        self.returns = function.returns;
+        for parameter in &function.parameters {
+            self.declare(&parameter.name, parameter.ty);
+        }
+        self.block(&mut function.body);
+
+        if function.returns.is_some() && !always_returns(&function.body) {
+            self.report(function.name.span, DiagnosticKind::MissingReturn(function.name.name.clone()));
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, ty: Type) {
+        if self.lookup(&name.name).is_some() {
+            self.report(name.span, DiagnosticKind::Redeclared(name.name.clone()));
+        } else {
+            self.scopes.last_mut().expect("a scope").insert(name.name.clone(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next().cloned()
+    }
+
+    fn variable(&mut self, name: &str, span: Span) -> Option<Type> {
+        let ty = self.lookup(name);
+        if ty.is_none() {
+            self.report(span, DiagnosticKind::UndeclaredVariable(name.to_string()));
+        }
+        ty
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        self.scopes.push(HashMap::new());
+        for statement in &mut block.statements {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        let span = statement.span;
+        match statement.kind {
+            StatementKind::Declaration { ty, ref name, ref mut value } => {
+                if let Some(ref mut value) = *value {
+                    self.expect(value, ty);
+                }
+                self.declare(name, ty);
+            }
+
+            StatementKind::Assignment { ref target, ref mut indices, ref mut value } => {
+                let mut ty = self.variable(&target.name, target.span);
+                for index in indices.iter_mut() {
+                    self.expect(index, Type::Int);
+                    ty = ty.and_then(|ty| self.element(ty, index.span));
+                }
+                match ty {
+                    Some(ty) => self.expect(value, ty),
+
+                    None => {
+                        self.infer(value, None);
+                    }
+                }
+            }
+
+            StatementKind::While { ref mut condition, ref mut body } => {
+                self.expect(condition, Type::Int);
+                self.block(body);
+            }
+
+            StatementKind::If { ref mut condition, ref mut then, ref mut otherwise } => {
+                self.expect(condition, Type::Int);
+                self.block(then);
+                if let Some(ref mut otherwise) = *otherwise {
+                    self.block(otherwise);
+                }
+            }
+
+            StatementKind::Return(ref mut value) => match (value.as_mut(), self.returns) {
+                (Some(value), Some(ty)) => self.expect(value, ty),
+
+                (Some(value), None) => {
+                    self.infer(value, None);
+                    self.report(span, DiagnosticKind::UnexpectedReturnValue);
+                }
+
+                (None, Some(ty)) => self.report(span, DiagnosticKind::MissingReturnValue(ty)),
+
+                (None, None) => (),
+            },
+
+            StatementKind::Print(ref mut value) => self.expect(value, Type::Int),
+
+            StatementKind::Expression(ref mut expression) => {
+                if let ExpressionKind::Call(ref name, ref mut arguments) = expression.kind {
+                    expression.ty = self.call(name, arguments).unwrap_or(None);
+                } else {
+                    self.infer(expression, None);
+                    self.report(expression.span, DiagnosticKind::NotAStatement);
+                }
+            }
+        }
+    }
+
+    /// Resolve the type of `expression`, reporting a mismatch with `ty`.
+    fn expect(&mut self, expression: &mut Expression, ty: Type) {
+        if let Some(found) = self.infer(expression, Some(ty)) {
+            if found != ty {
+                self.report(expression.span, DiagnosticKind::TypeMismatch { expected: ty, found });
+            }
+        }
+    }
+
+    /// Resolve the type of `expression`, using `hint` for empty lists. The
+    /// type is `None` when it can not be resolved because of a mistake.
+    fn infer(&mut self, expression: &mut Expression, hint: Option<Type>) -> Option<Type> {
+        let span = expression.span;
+        let ty = match expression.kind {
+            ExpressionKind::Integer(_) => Some(Type::Int),
+
+            ExpressionKind::Variable(ref name) => self.variable(name, span),
+
+            ExpressionKind::List(ref mut elements) => self.list(elements, hint, span),
+
+            ExpressionKind::Binary(operator, ref mut left, ref mut right) => self.binary(operator, left, right, hint),
+
+            ExpressionKind::Unary(_, ref mut operand) => {
+                self.expect(operand, Type::Int);
+                Some(Type::Int)
+            }
+
+            ExpressionKind::Call(ref name, ref mut arguments) => match self.call(name, arguments) {
+                Ok(Some(ty)) => Some(ty),
+
+                Ok(None) => {
+                    self.report(span, DiagnosticKind::NoValue(name.name.clone()));
+                    None
+                }
+
+                Err(Reported) => None,
+            },
+
+            ExpressionKind::Index(ref mut list, ref mut index) => {
+                let ty = self.infer(list, None);
+                self.expect(index, Type::Int);
+                ty.and_then(|ty| self.element(ty, list.span))
+            }
+        };
+        expression.ty = ty;
+        ty
+    }
+
+    fn element(&mut self, ty: Type, span: Span) -> Option<Type> {
+        let element = ty.element();
+        if element.is_none() {
+            self.report(span, DiagnosticKind::NotAList(ty));
+        }
+        element
+    }
+
+    fn list(&mut self, elements: &mut [Expression], hint: Option<Type>, span: Span) -> Option<Type> {
+        let mut inferred = None;
+        let element = match hint.and_then(|ty| ty.element()) {
+            Some(element) => Some(element),
+
+            None => match elements.iter().position(|element| !is_empty_list(element)) {
+                Some(index) => {
+                    inferred = Some(index);
+                    self.infer(&mut elements[index], None)
+                }
+
+                None if elements.is_empty() => Some(Type::Int),
+
+                None => Some(Type::List),
+            },
+        };
+
+        for (index, expression) in elements.iter_mut().enumerate() {
+            if inferred == Some(index) {
+                continue;
+            }
+            match element {
+                Some(element) => self.expect(expression, element),
+
+                None => {
+                    self.infer(expression, None);
+                }
+            }
+        }
+
+        let element = element?;
+        let ty = element.list_of();
+        if ty.is_none() {
+            self.report(span, DiagnosticKind::NoListOf(element));
+        }
+        ty
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, left: &mut Expression, right: &mut Expression, hint: Option<Type>) -> Option<Type> {
+        if operator != BinaryOperator::Add {
+            self.expect(left, Type::Int);
+            self.expect(right, Type::Int);
+            return Some(Type::Int);
+        }
+
+        // Both operands of `+` have the same type; resolve the one that is not
+        // an empty list first, so the other can follow it.
+        let (first, second) = if hint.is_none() && is_empty_list(left) { (right, left) } else { (left, right) };
+        match self.infer(first, hint) {
+            Some(ty) => {
+                self.expect(second, ty);
+                Some(ty)
+            }
+
+            None => {
+                self.infer(second, hint);
+                None
+            }
+        }
+    }
+
+    /// Check a call and resolve the type of the returned value, if any.
+    fn call(&mut self, name: &Identifier, arguments: &mut [Expression]) -> Result<Option<Type>, Reported> {
+        let signature = if name.name == "len" {
+            None
+        } else {
+            match self.signatures.get(&name.name) {
+                Some(signature) => Some(signature.clone()),
+
+                None => {
+                    self.report(name.span, DiagnosticKind::UndeclaredFunction(name.name.clone()));
+                    for argument in arguments.iter_mut() {
+                        self.infer(argument, None);
+                    }
+                    return Err(Reported);
+                }
+            }
+        };
+
+        let expected = signature.as_ref().map_or(1, |signature| signature.parameters.len());
+        if arguments.len() != expected {
+            let kind = DiagnosticKind::Arity { function: name.name.clone(), expected, found: arguments.len() };
+            self.report(name.span, kind);
+            for argument in arguments.iter_mut() {
+                self.infer(argument, None);
+            }
+        } else if let Some(ref signature) = signature {
+            for (argument, &ty) in arguments.iter_mut().zip(&signature.parameters) {
+                self.expect(argument, ty);
+            }
+        } else if let Some(ty) = self.infer(&mut arguments[0], None) {
+            self.element(ty, arguments[0].span);
+        }
+
+        Ok(signature.map_or(Some(Type::Int), |signature| signature.returns))
+    }
+
+    fn recursion(&mut self, program: &Program) {
+        let mut calls: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for function in &program.functions {
+            let callees = calls.entry(&function.name.name).or_default();
+            block_calls(&function.body, callees);
+        }
+
+        let mut reported = HashSet::new();
+        for function in &program.functions {
+            let name: &str = &function.name.name;
+            if reported.contains(name) {
+                continue;
+            }
+            let mut seen = HashSet::new();
+            let mut pending: Vec<&str> = calls[name].iter().cloned().collect();
+            while let Some(callee) = pending.pop() {
+                if seen.insert(callee) {
+                    pending.extend(calls.get(callee).into_iter().flat_map(|callees| callees.iter().cloned()));
+                }
+            }
+            if seen.contains(name) {
+                reported.insert(name);
+                self.report(function.name.span, DiagnosticKind::Recursion(name.to_string()));
+            }
+        }
+    }
+}
+
+fn is_empty_list(expression: &Expression) -> bool {
+    match expression.kind {
+        ExpressionKind::List(ref elements) => elements.is_empty(),
+
+        _ => false,
+    }
+}
+
+fn always_returns(block: &Block) -> bool {
+    block.statements.iter().any(|statement| match statement.kind {
+        StatementKind::Return(_) => true,
+
+        StatementKind::If { ref then, otherwise: Some(ref otherwise), .. } => always_returns(then) && always_returns(otherwise),
+
+        _ => false,
+    })
+}
+
+fn block_calls<'a>(block: &'a Block, calls: &mut HashSet<&'a str>) {
+    for statement in &block.statements {
+        match statement.kind {
+            StatementKind::Declaration { ref value, .. } => {
+                if let Some(ref value) = *value {
+                    expression_calls(value, calls);
+                }
+            }
+
+            StatementKind::Assignment { ref indices, ref value, .. } => {
+                for index in indices {
+                    expression_calls(index, calls);
+                }
+                expression_calls(value, calls);
+            }
+
+            StatementKind::While { ref condition, ref body } => {
+                expression_calls(condition, calls);
+                block_calls(body, calls);
+            }
+
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                expression_calls(condition, calls);
+                block_calls(then, calls);
+                if let Some(ref otherwise) = *otherwise {
+                    block_calls(otherwise, calls);
+                }
+            }
+
+            StatementKind::Return(ref value) => {
+                if let Some(ref value) = *value {
+                    expression_calls(value, calls);
+                }
+            }
+
+            StatementKind::Print(ref value) | StatementKind::Expression(ref value) => expression_calls(value, calls),
+        }
+    }
+}
+
+fn expression_calls<'a>(expression: &'a Expression, calls: &mut HashSet<&'a str>) {
+    match expression.kind {
+        ExpressionKind::Integer(_) | ExpressionKind::Variable(_) => (),
+
+        ExpressionKind::List(ref elements) => {
+            for element in elements {
+                expression_calls(element, calls);
+            }
+        }
+
+        ExpressionKind::Binary(_, ref left, ref right) | ExpressionKind::Index(ref left, ref right) => {
+            expression_calls(left, calls);
+            expression_calls(right, calls);
+        }
+
+        ExpressionKind::Unary(_, ref operand) => expression_calls(operand, calls),
+
+        ExpressionKind::Call(ref name, ref arguments) => {
+            calls.insert(&name.name);
+            for argument in arguments {
+                expression_calls(argument, calls);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::parse;
+
+    fn diagnostics(source: &str) -> Vec<String> {
+        let mut program = parse(source).expect("a valid parse");
+        check(&mut program).iter().map(|diagnostic| diagnostic.to_string()).collect()
+    }
+
+    #[test]
+    fn should_accept_a_correct_program_and_resolve_types() {
+        let mut program = parse("
+            func sum(list l) int {
+                int total;
+                int i = 0;
+                while (i < len(l)) {
+                    total = total + l[i];
+                    i = i + 1;
+                }
+                return total;
+            }
+
+            func main() {
+                list2 m = [[], [1, 2]] + [];
+                m[0] = [] + [3];
+                print(sum(m[1]) + len(m[0]));
+            }
+        ").expect("a valid parse");
+
+        assert!(check(&mut program).is_empty());
+
+        let main = program.function("main").expect("a function main");
+        match main.body.statements[0].kind {
+            StatementKind::Declaration { value: Some(ref value), .. } => {
+                assert_eq!(value.ty, Some(Type::List2));
+                match value.kind {
+                    ExpressionKind::Binary(_, ref left, ref right) => {
+                        assert_eq!(left.ty, Some(Type::List2));
+                        assert_eq!(right.ty, Some(Type::List2));
+                        match left.kind {
+                            ExpressionKind::List(ref elements) => assert_eq!(elements[0].ty, Some(Type::List)),
+
+                            ref kind => panic!("not a list: {:?}", kind),
+                        }
+                    }
+
+                    ref kind => panic!("not a concatenation: {:?}", kind),
+                }
+            }
+
+            ref kind => panic!("not a declaration: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn should_report_type_mistakes() {
+        assert_eq!(diagnostics("
+func f(int a, list b) int {
+    return a + b[0];
+}
+
+func main() {
+    list l = 3;
+    int x = [1];
+    print(f(l, 1));
+    print(x[0] + f(1));
+    l = l + [[1]];
+    x = len(x);
+    print(g(x));
+    x + 1;
+}
+"), vec![
+            "7:14: expected list, found int",
+            "8:13: expected int, found list",
+            "9:13: expected int, found list",
+            "9:16: expected list, found int",
+            "10:11: expected a list, found int",
+            "10:18: `f` takes 2 argument(s), found 1",
+            "11:14: expected int, found list",
+            "12:13: expected a list, found int",
+            "13:11: there is no function `g`",
+            "14:5: only calls can be used as statements",
+        ]);
+    }
+
+    #[test]
+    fn should_report_undeclared_and_redeclared_names() {
+        assert_eq!(diagnostics("
+func f(int a) {
+    int a;
+    if (a) {
+        int b = c;
+    }
+    int b;
+    int b;
+}
+
+func f() {
+}
+
+func len(list l) int {
+    return 0;
+}
+
+func main() {
+    b = 1;
+}
+"), vec![
+            "3:9: `a` is already declared",
+            "5:17: `c` is not declared",
+            "8:9: `b` is already declared",
+            "11:6: function `f` is already defined",
+            "14:6: function `len` is already defined",
+            "19:5: `b` is not declared",
+        ]);
+    }
+
+    #[test]
+    fn should_report_missing_and_misplaced_returns() {
+        assert_eq!(diagnostics("
+func f(int a) int {
+    if (a) {
+        return 1;
+    } else if (a - 1) {
+        return 2;
+    } else {
+        return 3;
+    }
+}
+
+func g(int a) int {
+    while (a) {
+        return a;
+    }
+}
+
+func h() {
+    return 1;
+}
+
+func i() int {
+    return;
+}
+
+func main() {
+    h();
+    print(h());
+}
+"), vec![
+            "12:6: `g` does not return a value on every path",
+            "19:5: the function does not return a value",
+            "23:5: expected a return value of type int",
+            "28:11: `h` does not return a value",
+        ]);
+    }
+
+    #[test]
+    fn should_reject_recursion() {
+        assert_eq!(diagnostics("
+func even(int n) int {
+    if (n == 0) {
+        return 1;
+    }
+    return odd(n - 1);
+}
+
+func odd(int n) int {
+    return !even(n);
+}
+
+func loop() {
+    loop();
+}
+
+func main() {
+    print(even(4));
+}
+"), vec![
+            "2:6: `even` calls itself",
+            "9:6: `odd` calls itself",
+            "13:6: `loop` calls itself",
+        ]);
+    }
+
+    #[test]
+    fn should_require_a_main_without_parameters_or_return_value() {
+        assert_eq!(diagnostics("func f() {}"), vec!["1:1: there is no function `main`"]);
+        assert_eq!(diagnostics("func main(int a) {}"), vec!["1:6: `main` must take no parameters and return nothing"]);
+    }
+}
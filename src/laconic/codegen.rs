@@ -0,0 +1,967 @@
+//! Codegen lowers a checked Laconic program to TMD; see the `tmd` module for
+//! the language itself.
+//!
+//! Every Laconic variable becomes a tape with the same name. Values are laid
+//! out from the first cell, which holds the start marker `S`:
+//!
+//! * an `int` `n` is `n` times `1`;
+//! * a `list` is its elements, each followed by `E`;
+//! * a `list2` is its elements, each followed by `F`.
+//!
+//! So `[[2], []]` is written `S11EFF`. Everything after the value is blank,
+//! and between statements every head is on the start marker.
+//!
+//! Operations are calls to a library of TMD functions, named `lib.*`, that is
+//! included in the output. Intermediate values live on temporary tapes named
+//! `sys.t0`, `sys.t1` and so on. Arguments are copied to temporaries before a
+//! call, a returned value is written to the tape `sys.return` of the caller,
+//! and `print` appends to the list on the tape `sys.output`, which is the
+//! only parameter of `main`.
+
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use super::ast::{BinaryOperator, Block, Expression, ExpressionKind, Function, Program, Statement, StatementKind, Type, UnaryOperator};
+
+/// The tape of `main` that holds the printed values.
+pub const OUTPUT: &str = "sys.output";
+
+const RETURN: &str = "sys.return";
+
+/// Generate the TMD source for `program`, which should be checked without
+/// diagnostics.
+pub fn generate(program: &Program) -> String {
+    let mut source = String::from(LIBRARY);
+    for function in &program.functions {
+        source.push('\n');
+        source.push_str(&Generator::function(function));
+    }
+    source
+}
+
+/// Read the printed values from the cells of the output tape, starting at the
+/// first cell. `None` when the cells do not hold a list.
+pub fn decode_output(cells: &[char]) -> Option<Vec<BigUint>> {
+    let (first, rest) = cells.split_first()?;
+    if *first != 'S' {
+        return None;
+    }
+    let mut values = Vec::new();
+    let mut value = 0u64;
+    for &cell in rest.iter().take_while(|&&cell| cell != '_') {
+        match cell {
+            '1' => value += 1,
+
+            'E' => {
+                values.push(BigUint::from(value));
+                value = 0;
+            }
+
+            _ => return None,
+        }
+    }
+    if value == 0 {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+/// Whether the TMD function `name` belongs to the library, rather than being
+/// generated for a Laconic function of the same name.
+pub fn is_library(name: &str) -> bool {
+    name.starts_with("lib.")
+}
+
+struct Generator {
+    lines: Vec<String>,
+    variables: Vec<String>,
+    labels: usize,
+    temporaries: usize,
+    used: usize,
+}
+
+impl Generator {
+    fn function(function: &Function) -> String {
+        let mut generator = Generator { lines: Vec::new(), variables: Vec::new(), labels: 0, temporaries: 0, used: 0 };
+        generator.block(&function.body);
+
+        let mut parameters: Vec<String> = function.parameters.iter().map(|parameter| parameter.name.name.clone()).collect();
+        if function.returns.is_some() {
+            parameters.push(RETURN.to_string());
+        }
+        parameters.push(OUTPUT.to_string());
+
+        let mut tapes = generator.variables.clone();
+        tapes.extend((0..generator.used).map(temporary));
+
+        let mut source = format!("func {}({})\n", function.name.name, parameters.join(", "));
+        if !tapes.is_empty() {
+            source.push_str(&format!("    var {}\n", tapes.join(" ")));
+        }
+        if function.name.name == "main" {
+            source.push_str(&format!("    write {} S\n", OUTPUT));
+        }
+        for tape in &tapes {
+            source.push_str(&format!("    write {} S\n", tape));
+        }
+        for line in &generator.lines {
+            source.push_str(line);
+            source.push('\n');
+        }
+        source.push_str("end\n");
+        source
+    }
+
+    fn emit(&mut self, command: String) {
+        self.lines.push(format!("    {}", command));
+    }
+
+    fn call(&mut self, function: &str, arguments: &[&str]) {
+        self.emit(format!("call {}({})", function, arguments.join(", ")));
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!("l{}", self.labels)
+    }
+
+    fn place(&mut self, label: &str) {
+        self.lines.push(format!("{}:", label));
+    }
+
+    fn temporary(&mut self) -> String {
+        let name = temporary(self.temporaries);
+        self.temporaries += 1;
+        self.used = self.used.max(self.temporaries);
+        name
+    }
+
+    fn declare(&mut self, name: &str) {
+        if !self.variables.iter().any(|variable| variable == name) {
+            self.variables.push(name.to_string());
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for statement in &block.statements {
+            let mark = self.temporaries;
+            self.statement(statement);
+            self.temporaries = mark;
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement.kind {
+            StatementKind::Declaration { ref name, ref value, .. } => {
+                self.declare(&name.name);
+                match *value {
+                    Some(ref value) => self.expression(value, &name.name),
+
+                    None => self.call("lib.clear", &[&name.name]),
+                }
+            }
+
+            StatementKind::Assignment { ref target, ref indices, ref value } => self.assignment(&target.name, indices, value),
+
+            StatementKind::While { ref condition, ref body } => {
+                let start = self.label();
+                let end = self.label();
+                self.place(&start);
+                let mark = self.temporaries;
+                let tape = self.temporary();
+                self.expression(condition, &tape);
+                self.branch_if_zero(&tape, &end);
+                self.temporaries = mark;
+                self.block(body);
+                self.emit(format!("goto {}", start));
+                self.place(&end);
+            }
+
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                let alternative = self.label();
+                let mark = self.temporaries;
+                let tape = self.temporary();
+                self.expression(condition, &tape);
+                self.branch_if_zero(&tape, &alternative);
+                self.temporaries = mark;
+                self.block(then);
+                match *otherwise {
+                    Some(ref otherwise) => {
+                        let end = self.label();
+                        self.emit(format!("goto {}", end));
+                        self.place(&alternative);
+                        self.block(otherwise);
+                        self.place(&end);
+                    }
+
+                    None => self.place(&alternative),
+                }
+            }
+
+            StatementKind::Return(ref value) => {
+                if let Some(ref value) = *value {
+                    self.expression(value, RETURN);
+                }
+                self.emit("return".to_string());
+            }
+
+            StatementKind::Print(ref value) => {
+                let tape = self.operand(value, &[OUTPUT]);
+                self.call("lib.push1", &[OUTPUT, &tape]);
+            }
+
+            StatementKind::Expression(ref expression) => match expression.kind {
+                ExpressionKind::Call(ref name, ref arguments) if expression.ty.is_none() => {
+                    let mut tapes = self.arguments(arguments);
+                    tapes.push(OUTPUT.to_string());
+                    let tapes: Vec<&str> = tapes.iter().map(String::as_str).collect();
+                    self.call(&name.name, &tapes);
+                }
+
+                _ => {
+                    let tape = self.temporary();
+                    self.expression(expression, &tape);
+                }
+            },
+        }
+    }
+
+    fn assignment(&mut self, target: &str, indices: &[Expression], value: &Expression) {
+        match indices.len() {
+            0 => if mentions(value, target) {
+                let tape = self.temporary();
+                self.expression(value, &tape);
+                self.call("lib.copy", &[&tape, target]);
+            } else {
+                self.expression(value, target);
+            },
+
+            1 => {
+                let set = if value.ty == Some(Type::List) { "lib.set2" } else { "lib.set1" };
+                let index = self.operand(&indices[0], &[target]);
+                let value = self.operand(value, &[target, &index]);
+                self.call(set, &[target, &index, &value]);
+            }
+
+            _ => {
+                let outer = self.operand(&indices[0], &[target]);
+                let inner = self.operand(&indices[1], &[target, &outer]);
+                let value = self.operand(value, &[target, &outer, &inner]);
+                let element = self.temporary();
+                self.call("lib.index2", &[target, &outer, &element]);
+                self.call("lib.set1", &[&element, &inner, &value]);
+                self.call("lib.set2", &[target, &outer, &element]);
+            }
+        }
+    }
+
+    /// Jump to `label` when the integer on `tape` is 0.
+    fn branch_if_zero(&mut self, tape: &str, label: &str) {
+        let nonzero = self.label();
+        self.emit(format!("move {} R", tape));
+        self.emit(format!("if {} 1 goto {}", tape, nonzero));
+        self.emit(format!("move {} L", tape));
+        self.emit(format!("goto {}", label));
+        self.place(&nonzero);
+        self.emit(format!("move {} L", tape));
+    }
+
+    /// The tape holding the value of `expression`: the variable itself when
+    /// the expression is a variable not in `taken`, otherwise a temporary.
+    fn operand(&mut self, expression: &Expression, taken: &[&str]) -> String {
+        if let ExpressionKind::Variable(ref name) = expression.kind {
+            if !taken.contains(&name.as_str()) {
+                return name.clone();
+            }
+        }
+        let tape = self.temporary();
+        self.expression(expression, &tape);
+        tape
+    }
+
+    /// Copy every argument to a fresh temporary, so the callee can change it.
+    fn arguments(&mut self, arguments: &[Expression]) -> Vec<String> {
+        arguments.iter().map(|argument| {
+            let tape = self.temporary();
+            self.expression(argument, &tape);
+            tape
+        }).collect()
+    }
+
+    /// Write the value of `expression` on `target`, which the expression does
+    /// not mention.
+    fn expression(&mut self, expression: &Expression, target: &str) {
+        let mark = self.temporaries;
+        match expression.kind {
+            ExpressionKind::Integer(ref digits) => {
+                let n = BigUint::parse_bytes(digits.as_bytes(), 10).expect("decimal digits");
+                self.literal(&n, target);
+            }
+
+            ExpressionKind::Variable(ref name) => if name != target {
+                self.call("lib.copy", &[name, target]);
+            },
+
+            ExpressionKind::List(ref elements) => {
+                let push = if expression.ty == Some(Type::List2) { "lib.push2" } else { "lib.push1" };
+                let mut tapes = Vec::new();
+                for element in elements {
+                    tapes.push(self.operand(element, &[target]));
+                }
+                self.call("lib.clear", &[target]);
+                for tape in &tapes {
+                    self.call(push, &[target, tape]);
+                }
+            }
+
+            ExpressionKind::Binary(BinaryOperator::And, ref left, ref right) => self.logical(left, right, target, false),
+
+            ExpressionKind::Binary(BinaryOperator::Or, ref left, ref right) => self.logical(left, right, target, true),
+
+            ExpressionKind::Binary(operator, ref left, ref right) => {
+                let left = self.operand(left, &[target]);
+                let right = self.operand(right, &[target, &left]);
+                let (function, swap, negate) = match operator {
+                    BinaryOperator::Equal => ("lib.equal", false, false),
+
+                    BinaryOperator::NotEqual => ("lib.equal", false, true),
+
+                    BinaryOperator::Less => ("lib.less", false, false),
+
+                    BinaryOperator::LessEqual => ("lib.less", true, true),
+
+                    BinaryOperator::Greater => ("lib.less", true, false),
+
+                    BinaryOperator::GreaterEqual => ("lib.less", false, true),
+
+                    BinaryOperator::Add => ("lib.add", false, false),
+
+                    BinaryOperator::Subtract => ("lib.subtract", false, false),
+
+                    BinaryOperator::Multiply => ("lib.multiply", false, false),
+
+                    BinaryOperator::Divide => ("lib.divide", false, false),
+
+                    BinaryOperator::Remainder => ("lib.remainder", false, false),
+
+                    BinaryOperator::And | BinaryOperator::Or => unreachable!("logical operators are handled above"),
+                };
+                if swap {
+                    self.call(function, &[&right, &left, target]);
+                } else {
+                    self.call(function, &[&left, &right, target]);
+                }
+                if negate {
+                    self.call("lib.negate", &[target]);
+                }
+            }
+
+            ExpressionKind::Unary(UnaryOperator::Not, ref operand) => {
+                self.expression(operand, target);
+                self.call("lib.negate", &[target]);
+            }
+
+            ExpressionKind::Call(ref name, ref arguments) if name.name == "len" => {
+                let length = if arguments[0].ty == Some(Type::List2) { "lib.length2" } else { "lib.length1" };
+                let list = self.operand(&arguments[0], &[target]);
+                self.call(length, &[&list, target]);
+            }
+
+            ExpressionKind::Call(ref name, ref arguments) => {
+                let mut tapes = self.arguments(arguments);
+                tapes.push(target.to_string());
+                tapes.push(OUTPUT.to_string());
+                let tapes: Vec<&str> = tapes.iter().map(String::as_str).collect();
+                self.call(&name.name, &tapes);
+            }
+
+            ExpressionKind::Index(ref list, ref index) => {
+                let element = if list.ty == Some(Type::List2) { "lib.index2" } else { "lib.index1" };
+                let list = self.operand(list, &[target]);
+                let index = self.operand(index, &[target, &list]);
+                self.call(element, &[&list, &index, target]);
+            }
+        }
+        self.temporaries = mark;
+    }
+
+    /// `left && right` or, when `or` holds, `left || right`; the right operand
+    /// is only evaluated when the left one does not decide.
+    fn logical(&mut self, left: &Expression, right: &Expression, target: &str, or: bool) {
+        let end = self.label();
+        self.expression(left, target);
+        if or {
+            let right_needed = self.label();
+            self.branch_if_zero(target, &right_needed);
+            self.emit(format!("goto {}", end));
+            self.place(&right_needed);
+        } else {
+            self.branch_if_zero(target, &end);
+        }
+        self.expression(right, target);
+        self.place(&end);
+        self.call("lib.truth", &[target]);
+    }
+
+    /// Write the natural number `n` on `target`. Small numbers are written
+    /// directly, larger ones are computed from their decimal digits.
+    fn literal(&mut self, n: &BigUint, target: &str) {
+        match n.to_u8().filter(|&n| n <= 16) {
+            Some(n) => {
+                self.call("lib.clear", &[target]);
+                for _ in 0..n {
+                    self.emit(format!("move {} R", target));
+                    self.emit(format!("write {} 1", target));
+                }
+                if n > 0 {
+                    self.call("lib.rewind", &[target]);
+                }
+            }
+
+            None => {
+                let ten = BigUint::from(10u8);
+                let high = self.temporary();
+                self.literal(&(n / &ten), &high);
+                let base = self.temporary();
+                self.literal(&ten, &base);
+                let product = self.temporary();
+                self.call("lib.multiply", &[&high, &base, &product]);
+                let digit = n % &ten;
+                if digit.is_zero() {
+                    self.call("lib.copy", &[&product, target]);
+                } else {
+                    self.literal(&digit, &high);
+                    self.call("lib.add", &[&product, &high, target]);
+                }
+            }
+        }
+    }
+}
+
+fn temporary(index: usize) -> String {
+    format!("sys.t{}", index)
+}
+
+fn mentions(expression: &Expression, name: &str) -> bool {
+    match expression.kind {
+        ExpressionKind::Integer(_) => false,
+
+        ExpressionKind::Variable(ref variable) => variable == name,
+
+        ExpressionKind::List(ref elements) | ExpressionKind::Call(_, ref elements) =>
+            elements.iter().any(|element| mentions(element, name)),
+
+        ExpressionKind::Binary(_, ref left, ref right) | ExpressionKind::Index(ref left, ref right) =>
+            mentions(left, name) || mentions(right, name),
+
+        ExpressionKind::Unary(_, ref operand) => mentions(operand, name),
+    }
+}
+
+/// The TMD functions the generated code is built from. All of them expect
+/// and leave the heads of their tapes on the start marker, and no tape may be
+/// passed twice in a call.
+const LIBRARY: &str = "\
+// Move the head of x back to the start marker.
+func lib.rewind(x)
+loop:
+    if x S goto done
+    move x L
+    goto loop
+done:
+end
+
+// x := 0, which is also the empty list.
+func lib.clear(x)
+loop:
+    move x R
+    if x _ goto done
+    write x _
+    goto loop
+done:
+    call lib.rewind(x)
+end
+
+// y := y followed by the contents of x.
+func lib.append(x, y)
+seek:
+    move y R
+    if y _ goto copy
+    goto seek
+copy:
+    move x R
+    if x _ goto done
+    if x 1 goto one
+    if x E goto element
+    write y F
+    goto next
+one:
+    write y 1
+    goto next
+element:
+    write y E
+next:
+    move y R
+    goto copy
+done:
+    call lib.rewind(x)
+    call lib.rewind(y)
+end
+
+// y := x
+func lib.copy(x, y)
+    call lib.clear(y)
+    call lib.append(x, y)
+end
+
+// r := a + b, which concatenates lists.
+func lib.add(a, b, r)
+    call lib.copy(a, r)
+    call lib.append(b, r)
+end
+
+// x := x + 1
+func lib.increment(x)
+seek:
+    move x R
+    if x _ goto done
+    goto seek
+done:
+    write x 1
+    call lib.rewind(x)
+end
+
+// x := x - y, stopping at 0.
+func lib.decrease(x, y)
+seek:
+    move x R
+    if x _ goto loop
+    goto seek
+loop:
+    move y R
+    if y _ goto done
+    move x L
+    if x S goto done
+    write x _
+    goto loop
+done:
+    call lib.rewind(x)
+    call lib.rewind(y)
+end
+
+// r := a - b, stopping at 0.
+func lib.subtract(a, b, r)
+    call lib.copy(a, r)
+    call lib.decrease(r, b)
+end
+
+// r := a * b
+func lib.multiply(a, b, r)
+    call lib.clear(r)
+loop:
+    move a R
+    if a _ goto done
+    call lib.append(b, r)
+    goto loop
+done:
+    call lib.rewind(a)
+end
+
+// q := a / b and m := a % b, failing when b is 0.
+func lib.divmod(a, b, q, m)
+    var c
+    write c S
+    move b R
+    if b _ goto zero
+    move b L
+    call lib.clear(q)
+    call lib.copy(a, m)
+loop:
+    call lib.less(m, b, c)
+    move c R
+    if c 1 goto done
+    move c L
+    call lib.decrease(m, b)
+    call lib.increment(q)
+    goto loop
+zero:
+    error
+done:
+    move c L
+end
+
+// r := a / b, failing when b is 0.
+func lib.divide(a, b, r)
+    var m
+    write m S
+    call lib.divmod(a, b, r, m)
+end
+
+// r := a % b, failing when b is 0.
+func lib.remainder(a, b, r)
+    var q
+    write q S
+    call lib.divmod(a, b, q, r)
+end
+
+// r := a < b
+func lib.less(a, b, r)
+    call lib.clear(r)
+loop:
+    move a R
+    move b R
+    if b _ goto done
+    if a _ goto true
+    goto loop
+true:
+    move r R
+    write r 1
+    move r L
+done:
+    call lib.rewind(a)
+    call lib.rewind(b)
+end
+
+// r := a == b
+func lib.equal(a, b, r)
+    call lib.clear(r)
+loop:
+    move a R
+    move b R
+    if a _ goto ended
+    if b _ goto done
+    goto loop
+ended:
+    if b _ goto true
+    goto done
+true:
+    move r R
+    write r 1
+    move r L
+done:
+    call lib.rewind(a)
+    call lib.rewind(b)
+end
+
+// x := !x
+func lib.negate(x)
+    move x R
+    if x _ goto zero
+    move x L
+    call lib.clear(x)
+    return
+zero:
+    write x 1
+    move x L
+end
+
+// x := x != 0
+func lib.truth(x)
+    move x R
+    if x _ goto done
+    move x R
+loop:
+    if x _ goto done
+    write x _
+    move x R
+    goto loop
+done:
+    call lib.rewind(x)
+end
+
+// r := len(l) for a list l.
+func lib.length1(l, r)
+    call lib.clear(r)
+loop:
+    move l R
+    if l _ goto done
+    if l E goto count
+    goto loop
+count:
+    move r R
+    write r 1
+    goto loop
+done:
+    call lib.rewind(l)
+    call lib.rewind(r)
+end
+
+// r := len(l) for a list2 l.
+func lib.length2(l, r)
+    call lib.clear(r)
+loop:
+    move l R
+    if l _ goto done
+    if l F goto count
+    goto loop
+count:
+    move r R
+    write r 1
+    goto loop
+done:
+    call lib.rewind(l)
+    call lib.rewind(r)
+end
+
+// l := l + [x] for a list l.
+func lib.push1(l, x)
+    call lib.append(x, l)
+seek:
+    move l R
+    if l _ goto done
+    goto seek
+done:
+    write l E
+    call lib.rewind(l)
+end
+
+// l := l + [x] for a list2 l.
+func lib.push2(l, x)
+    call lib.append(x, l)
+seek:
+    move l R
+    if l _ goto done
+    goto seek
+done:
+    write l F
+    call lib.rewind(l)
+end
+
+// r := l[i] for a list l, failing when i is out of range.
+func lib.index1(l, i, r)
+    call lib.clear(r)
+skip:
+    move i R
+    if i _ goto copy
+skip_element:
+    move l R
+    if l _ goto out_of_range
+    if l E goto skip
+    goto skip_element
+copy:
+    move l R
+    if l _ goto out_of_range
+    if l E goto done
+    move r R
+    write r 1
+    goto copy
+out_of_range:
+    error
+done:
+    call lib.rewind(l)
+    call lib.rewind(i)
+    call lib.rewind(r)
+end
+
+// r := l[i] for a list2 l, failing when i is out of range.
+func lib.index2(l, i, r)
+    call lib.clear(r)
+skip:
+    move i R
+    if i _ goto copy
+skip_element:
+    move l R
+    if l _ goto out_of_range
+    if l F goto skip
+    goto skip_element
+copy:
+    move l R
+    if l _ goto out_of_range
+    if l F goto done
+    move r R
+    if l E goto element
+    write r 1
+    goto copy
+element:
+    write r E
+    goto copy
+out_of_range:
+    error
+done:
+    call lib.rewind(l)
+    call lib.rewind(i)
+    call lib.rewind(r)
+end
+
+// l[i] := v for a list l, failing when i is out of range.
+func lib.set1(l, i, v)
+    var n
+    write n S
+skip:
+    move i R
+    if i _ goto drop
+keep:
+    move l R
+    if l _ goto out_of_range
+    move n R
+    if l E goto kept
+    write n 1
+    goto keep
+kept:
+    write n E
+    goto skip
+drop:
+    move l R
+    if l _ goto out_of_range
+    if l E goto replace
+    goto drop
+replace:
+    call lib.rewind(n)
+    call lib.push1(n, v)
+seek:
+    move n R
+    if n _ goto rest
+    goto seek
+rest:
+    move l R
+    if l _ goto done
+    if l E goto rest_element
+    write n 1
+    move n R
+    goto rest
+rest_element:
+    write n E
+    move n R
+    goto rest
+out_of_range:
+    error
+done:
+    call lib.rewind(l)
+    call lib.rewind(i)
+    call lib.rewind(n)
+    call lib.copy(n, l)
+end
+
+// l[i] := v for a list2 l, failing when i is out of range.
+func lib.set2(l, i, v)
+    var n
+    write n S
+skip:
+    move i R
+    if i _ goto drop
+keep:
+    move l R
+    if l _ goto out_of_range
+    move n R
+    if l F goto kept
+    if l E goto kept_element
+    write n 1
+    goto keep
+kept_element:
+    write n E
+    goto keep
+kept:
+    write n F
+    goto skip
+drop:
+    move l R
+    if l _ goto out_of_range
+    if l F goto replace
+    goto drop
+replace:
+    call lib.rewind(n)
+    call lib.push2(n, v)
+seek:
+    move n R
+    if n _ goto rest
+    goto seek
+rest:
+    move l R
+    if l _ goto done
+    if l F goto rest_list
+    if l E goto rest_element
+    write n 1
+    move n R
+    goto rest
+rest_element:
+    write n E
+    move n R
+    goto rest
+rest_list:
+    write n F
+    move n R
+    goto rest
+out_of_range:
+    error
+done:
+    call lib.rewind(l)
+    call lib.rewind(i)
+    call lib.rewind(n)
+    call lib.copy(n, l)
+end
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::check::check;
+    use super::super::parser::parse;
+
+    fn generated(source: &str) -> String {
+        let mut program = parse(source).expect("a valid parse");
+        assert_eq!(check(&mut program), vec![]);
+        generate(&program)
+    }
+
+    #[test]
+    fn should_pass_hidden_tapes_to_functions() {
+        let source = generated("
+            func double(int n) int {
+                return n + n;
+            }
+
+            func main() {
+                int x = double(2);
+                print(x);
+            }
+        ");
+
+        assert!(source.starts_with(LIBRARY));
+        assert!(source.ends_with("\
+func double(n, sys.return, sys.output)
+    var sys.t0
+    write sys.t0 S
+    call lib.copy(n, sys.t0)
+    call lib.add(n, sys.t0, sys.return)
+    return
+end
+
+func main(sys.output)
+    var x sys.t0
+    write sys.output S
+    write x S
+    write sys.t0 S
+    call lib.clear(sys.t0)
+    move sys.t0 R
+    write sys.t0 1
+    move sys.t0 R
+    write sys.t0 1
+    call lib.rewind(sys.t0)
+    call double(sys.t0, x, sys.output)
+    call lib.push1(sys.output, x)
+end
+"));
+    }
+
+    #[test]
+    fn should_not_pass_a_tape_twice_to_the_library() {
+        let source = generated("func main() { int x = 2; x = x * x; print(x && x); }");
+
+        assert!(source.contains("call lib.copy(x, sys.t1)\n    call lib.multiply(x, sys.t1, sys.t0)\n    call lib.copy(sys.t0, x)\n"));
+    }
+
+    #[test]
+    fn should_build_large_literals_from_digits() {
+        let source = generated("func main() { print(1234); }");
+
+        assert_eq!(source.matches("call lib.multiply").count(), 2 + LIBRARY.matches("call lib.multiply").count());
+    }
+
+    #[test]
+    fn should_decode_printed_values() {
+        assert_eq!(decode_output(&['S', '1', 'E', 'E', '1', '1', 'E', '_']), Some(vec![BigUint::from(1u8), BigUint::zero(), BigUint::from(2u8)]));
+        assert_eq!(decode_output(&['S']), Some(vec![]));
+        assert_eq!(decode_output(&['S', '1']), None);
+        assert_eq!(decode_output(&['1', 'E']), None);
+    }
+}
@@ -0,0 +1,506 @@
+//! Interp runs a Laconic program directly from its syntax tree.
+//!
+//! It is the reference for what a program should do: integers are unbounded
+//! and every operation behaves as described in the language, so compiled
+//! machines can be compared against it.
+//!
+//! The interpreter counts steps, which are executed statements and evaluated
+//! loop conditions, and stops when a budget of steps is used up.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use super::ast::{BinaryOperator, Block, Expression, ExpressionKind, Program, Statement, StatementKind, Type, UnaryOperator};
+use super::lexer::Span;
+
+/// Everything observed while running a program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Execution {
+    /// The printed values, in order.
+    pub output: Vec<BigUint>,
+    /// The number of steps taken.
+    pub steps: u64,
+    /// How the run ended.
+    pub outcome: Outcome,
+}
+
+/// The ways a run can end.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// `main` returned.
+    Halted,
+    /// An operation failed.
+    Error(RuntimeError),
+    /// The budget of steps was used up before the program halted.
+    FuelExhausted,
+}
+
+/// Operation that failed while running a program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuntimeError {
+    /// The expression that failed.
+    pub span: Span,
+    /// How it failed.
+    pub kind: RuntimeErrorKind,
+}
+
+/// The ways an operation can fail.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RuntimeErrorKind {
+    /// `/` or `%` with 0 on the right.
+    DivisionByZero,
+    /// An index at or beyond the length of a list.
+    IndexOutOfRange {
+        /// The index.
+        index: BigUint,
+        /// The length of the list.
+        length: usize,
+    },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.span)?;
+        match self.kind {
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+
+            RuntimeErrorKind::IndexOutOfRange { ref index, length } =>
+                write!(f, "index {} is out of range for a list of length {}", index, length),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+/// Run the `main` function of `program` for at most `fuel` steps.
+///
+/// The program should be checked without diagnostics; the interpreter does
+/// not repeat those checks.
+pub fn run(program: &Program, fuel: u64) -> Execution {
+    let mut interpreter = Interpreter { program, output: Vec::new(), steps: 0, fuel };
+    let outcome = match interpreter.call("main", Vec::new()) {
+        Ok(_) => Outcome::Halted,
+
+        Err(Stop::Error(error)) => Outcome::Error(error),
+
+        Err(Stop::FuelExhausted) => Outcome::FuelExhausted,
+    };
+    Execution { output: interpreter.output, steps: interpreter.steps, outcome }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Value {
+    Int(BigUint),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn default(ty: Type) -> Value {
+        match ty {
+            Type::Int => Value::Int(BigUint::zero()),
+
+            Type::List | Type::List2 => Value::List(Vec::new()),
+        }
+    }
+
+    fn boolean(b: bool) -> Value {
+        Value::Int(BigUint::from(b as u8))
+    }
+
+    fn int(self) -> BigUint {
+        match self {
+            Value::Int(n) => n,
+
+            Value::List(_) => panic!("an int in a checked program"),
+        }
+    }
+
+    fn list(self) -> Vec<Value> {
+        match self {
+            Value::List(elements) => elements,
+
+            Value::Int(_) => panic!("a list in a checked program"),
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        match *self {
+            Value::Int(ref n) => !n.is_zero(),
+
+            Value::List(_) => panic!("an int in a checked program"),
+        }
+    }
+}
+
+/// Why execution stopped before `main` returned.
+enum Stop {
+    Error(RuntimeError),
+    FuelExhausted,
+}
+
+/// How a statement finished.
+enum Flow {
+    Next,
+    Return(Option<Value>),
+}
+
+type Variables = HashMap<String, Value>;
+
+struct Interpreter<'a> {
+    program: &'a Program,
+    output: Vec<BigUint>,
+    steps: u64,
+    fuel: u64,
+}
+
+impl<'a> Interpreter<'a> {
+    fn step(&mut self) -> Result<(), Stop> {
+        if self.steps == self.fuel {
+            return Err(Stop::FuelExhausted);
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Option<Value>, Stop> {
+        let function = self.program.function(name).expect("a function in a checked program");
+        let mut variables: Variables = function.parameters.iter()
+            .map(|parameter| parameter.name.name.clone())
+            .zip(arguments)
+            .collect();
+        match self.block(&function.body, &mut variables)? {
+            Flow::Next => Ok(None),
+
+            Flow::Return(value) => Ok(value),
+        }
+    }
+
+    fn block(&mut self, block: &Block, variables: &mut Variables) -> Result<Flow, Stop> {
+        for statement in &block.statements {
+            if let Flow::Return(value) = self.statement(statement, variables)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn statement(&mut self, statement: &Statement, variables: &mut Variables) -> Result<Flow, Stop> {
+        self.step()?;
+        match statement.kind {
+            StatementKind::Declaration { ty, ref name, ref value } => {
+                let value = match *value {
+                    Some(ref value) => self.expression(value, variables)?,
+
+                    None => Value::default(ty),
+                };
+                variables.insert(name.name.clone(), value);
+            }
+
+            StatementKind::Assignment { ref target, ref indices, ref value } => {
+                let mut positions = Vec::new();
+                for index in indices {
+                    positions.push((self.expression(index, variables)?.int(), index.span));
+                }
+                let value = self.expression(value, variables)?;
+
+                let mut place = variables.get_mut(&target.name).expect("a variable in a checked program");
+                for (index, span) in positions {
+                    place = match *place {
+                        Value::List(ref mut elements) => {
+                            let length = elements.len();
+                            match index.to_usize().filter(|&index| index < length) {
+                                Some(index) => &mut elements[index],
+
+                                None => return Err(out_of_range(span, index, length)),
+                            }
+                        }
+
+                        Value::Int(_) => panic!("a list in a checked program"),
+                    };
+                }
+                *place = value;
+            }
+
+            StatementKind::While { ref condition, ref body } => {
+                while self.expression(condition, variables)?.is_true() {
+                    if let Flow::Return(value) = self.block(body, variables)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    self.step()?;
+                }
+            }
+
+            StatementKind::If { ref condition, ref then, ref otherwise } => {
+                if self.expression(condition, variables)?.is_true() {
+                    return self.block(then, variables);
+                } else if let Some(ref otherwise) = *otherwise {
+                    return self.block(otherwise, variables);
+                }
+            }
+
+            StatementKind::Return(ref value) => {
+                let value = match *value {
+                    Some(ref value) => Some(self.expression(value, variables)?),
+
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            }
+
+            StatementKind::Print(ref value) => {
+                let value = self.expression(value, variables)?.int();
+                self.output.push(value);
+            }
+
+            StatementKind::Expression(ref expression) => {
+                self.value(expression, variables)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn expression(&mut self, expression: &Expression, variables: &mut Variables) -> Result<Value, Stop> {
+        Ok(self.value(expression, variables)?.expect("a value in a checked program"))
+    }
+
+    /// Evaluate `expression`, which only has no value when it calls a
+    /// function without a return type.
+    fn value(&mut self, expression: &Expression, variables: &mut Variables) -> Result<Option<Value>, Stop> {
+        let value = match expression.kind {
+            ExpressionKind::Integer(ref digits) =>
+                Value::Int(BigUint::parse_bytes(digits.as_bytes(), 10).expect("decimal digits")),
+
+            ExpressionKind::Variable(ref name) => variables[name].clone(),
+
+            ExpressionKind::List(ref elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.expression(element, variables)?);
+                }
+                Value::List(values)
+            }
+
+            ExpressionKind::Binary(operator, ref left, ref right) => self.binary(operator, left, right, expression.span, variables)?,
+
+            ExpressionKind::Unary(UnaryOperator::Not, ref operand) => {
+                let operand = self.expression(operand, variables)?;
+                Value::boolean(!operand.is_true())
+            }
+
+            ExpressionKind::Call(ref name, ref arguments) => {
+                let mut values = Vec::new();
+                for argument in arguments {
+                    values.push(self.expression(argument, variables)?);
+                }
+                if name.name == "len" {
+                    let length = values.pop().expect("an argument of len").list().len();
+                    Value::Int(BigUint::from(length))
+                } else {
+                    return self.call(&name.name, values);
+                }
+            }
+
+            ExpressionKind::Index(ref list, ref index) => {
+                let mut elements = self.expression(list, variables)?.list();
+                let position = self.expression(index, variables)?.int();
+                let length = elements.len();
+                match position.to_usize().filter(|&position| position < length) {
+                    Some(position) => elements.swap_remove(position),
+
+                    None => return Err(out_of_range(index.span, position, length)),
+                }
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, left: &Expression, right: &Expression, span: Span, variables: &mut Variables) -> Result<Value, Stop> {
+        let left = self.expression(left, variables)?;
+        match operator {
+            BinaryOperator::And if !left.is_true() => return Ok(Value::boolean(false)),
+
+            BinaryOperator::Or if left.is_true() => return Ok(Value::boolean(true)),
+
+            _ => (),
+        }
+        let right = self.expression(right, variables)?;
+
+        let value = match (operator, left, right) {
+            (BinaryOperator::And, _, right) | (BinaryOperator::Or, _, right) => Value::boolean(right.is_true()),
+
+            (BinaryOperator::Add, Value::List(mut left), right) => {
+                left.extend(right.list());
+                Value::List(left)
+            }
+
+            (operator, left, right) => {
+                let (left, right) = (left.int(), right.int());
+                match operator {
+                    BinaryOperator::Equal => Value::boolean(left == right),
+
+                    BinaryOperator::NotEqual => Value::boolean(left != right),
+
+                    BinaryOperator::Less => Value::boolean(left < right),
+
+                    BinaryOperator::LessEqual => Value::boolean(left <= right),
+
+                    BinaryOperator::Greater => Value::boolean(left > right),
+
+                    BinaryOperator::GreaterEqual => Value::boolean(left >= right),
+
+                    BinaryOperator::Add => Value::Int(left + right),
+
+                    BinaryOperator::Subtract if left < right => Value::Int(BigUint::zero()),
+
+                    BinaryOperator::Subtract => Value::Int(left - right),
+
+                    BinaryOperator::Multiply => Value::Int(left * right),
+
+                    BinaryOperator::Divide | BinaryOperator::Remainder if right.is_zero() =>
+                        return Err(Stop::Error(RuntimeError { span, kind: RuntimeErrorKind::DivisionByZero })),
+
+                    BinaryOperator::Divide => Value::Int(left / right),
+
+                    BinaryOperator::Remainder => Value::Int(left % right),
+
+                    BinaryOperator::And | BinaryOperator::Or => unreachable!("logical operators are handled above"),
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn out_of_range(span: Span, index: BigUint, length: usize) -> Stop {
+    Stop::Error(RuntimeError { span, kind: RuntimeErrorKind::IndexOutOfRange { index, length } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::check::check;
+    use super::super::parser::parse;
+
+    fn execute(source: &str, fuel: u64) -> Execution {
+        let mut program = parse(source).expect("a valid parse");
+        assert_eq!(check(&mut program), vec![]);
+        run(&program, fuel)
+    }
+
+    fn output(execution: &Execution) -> Vec<String> {
+        execution.output.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn should_print_the_primes_below_20() {
+        let execution = execute("
+            func isPrime(int n) int {
+                int d = 2;
+                while (d * d <= n) {
+                    if (n % d == 0) {
+                        return 0;
+                    }
+                    d = d + 1;
+                }
+                return n >= 2;
+            }
+
+            func main() {
+                int n = 0;
+                while (n < 20) {
+                    if (isPrime(n)) {
+                        print(n);
+                    }
+                    n = n + 1;
+                }
+            }
+        ", 10_000);
+
+        assert_eq!(execution.outcome, Outcome::Halted);
+        assert_eq!(output(&execution), vec!["2", "3", "5", "7", "11", "13", "17", "19"]);
+    }
+
+    #[test]
+    fn should_compute_with_unbounded_integers() {
+        let execution = execute("
+            func main() {
+                int n = 1;
+                int i = 0;
+                while (i < 100) {
+                    n = n * 2;
+                    i = i + 1;
+                }
+                print(n);
+                print(3 - 5);
+                print(17 / 5 + 17 % 5);
+                print(!0 + (2 < 1) + (0 || 7) + (3 && 0));
+            }
+        ", 1_000);
+
+        assert_eq!(output(&execution), vec!["1267650600228229401496703205376", "0", "5", "2"]);
+    }
+
+    #[test]
+    fn should_copy_lists() {
+        let execution = execute("
+            func clear(list l) list {
+                l[0] = 0;
+                return l;
+            }
+
+            func main() {
+                list2 m = [[1, 2], [3]];
+                list l = m[0];
+                l[1] = 5;
+                m[1] = clear(m[1]) + l;
+                m[0][0] = 9;
+                print(m[0][0] + m[0][1]);
+                print(len(m[1]));
+                print(m[1][0] + m[1][1] + m[1][2]);
+                print(len(m) + len([]));
+            }
+        ", 1_000);
+
+        assert_eq!(output(&execution), vec!["11", "3", "6", "2"]);
+    }
+
+    #[test]
+    fn should_stop_on_errors() {
+        let execution = execute("
+func main() {
+    list l = [1, 2];
+    print(l[1]);
+    print(l[0] && l[2]);
+    print(1);
+}
+", 100);
+
+        assert_eq!(output(&execution), vec!["2"]);
+        match execution.outcome {
+            Outcome::Error(ref error) => assert_eq!(error.to_string(), "5:21: index 2 is out of range for a list of length 2"),
+
+            ref outcome => panic!("not an error: {:?}", outcome),
+        }
+
+        let execution = execute("func main() { print(1 / (2 - 3)); }", 100);
+        match execution.outcome {
+            Outcome::Error(ref error) => assert_eq!(error.kind, RuntimeErrorKind::DivisionByZero),
+
+            ref outcome => panic!("not an error: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn should_stop_when_out_of_fuel() {
+        let execution = execute("func main() { int n; while (1) { n = n + 1; } }", 101);
+
+        assert_eq!(execution.outcome, Outcome::FuelExhausted);
+        assert_eq!(execution.steps, 101);
+
+        let execution = execute("func main() { int n; while (n < 3) { n = n + 1; } }", 8);
+        assert_eq!(execution.outcome, Outcome::Halted);
+        assert_eq!(execution.steps, 8);
+    }
+}
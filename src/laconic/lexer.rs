@@ -0,0 +1,446 @@
+//! Lexer turns Laconic source into tokens.
+//!
+//! Every token remembers where it came from, both as a byte range and as the
+//! line and column it starts on. Whitespace and `//` comments separate tokens
+//! but are not tokens themselves.
+
+use std::error::Error;
+use std::fmt;
+
+/// A region of the source.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset just after the last character.
+    pub end: usize,
+    /// The line of the first character, starting at 1.
+    pub line: usize,
+    /// The column of the first character, starting at 1.
+    pub column: usize,
+}
+
+impl Span {
+    /// The smallest span covering both `self` and `other`, assuming `other`
+    /// does not start before `self`.
+    pub fn to(&self, other: &Span) -> Span {
+        Span { end: other.end.max(self.end), .. *self }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The kinds of tokens in Laconic.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+    /// `func`
+    Func,
+    /// `while`
+    While,
+    /// `if`
+    If,
+    /// `else`
+    Else,
+    /// `return`
+    Return,
+    /// `print`
+    Print,
+    /// `int`
+    Int,
+    /// `list`
+    List,
+    /// `list2`
+    List2,
+    /// A name of a variable or function.
+    Identifier(String),
+    /// A non-negative integer literal, as written.
+    Integer(String),
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+    /// `*`
+    Star,
+    /// `/`
+    Slash,
+    /// `%`
+    Percent,
+    /// `==`
+    EqualEqual,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `!`
+    Not,
+    /// `=`
+    Assign,
+    /// `(`
+    LeftParen,
+    /// `)`
+    RightParen,
+    /// `{`
+    LeftBrace,
+    /// `}`
+    RightBrace,
+    /// `[`
+    LeftBracket,
+    /// `]`
+    RightBracket,
+    /// `,`
+    Comma,
+    /// `;`
+    Semicolon,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            TokenKind::Func => "func",
+            TokenKind::While => "while",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::Return => "return",
+            TokenKind::Print => "print",
+            TokenKind::Int => "int",
+            TokenKind::List => "list",
+            TokenKind::List2 => "list2",
+            TokenKind::Identifier(ref name) => return write!(f, "identifier `{}`", name),
+            TokenKind::Integer(ref digits) => return write!(f, "integer `{}`", digits),
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::EqualEqual => "==",
+            TokenKind::NotEqual => "!=",
+            TokenKind::Less => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::Greater => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::And => "&&",
+            TokenKind::Or => "||",
+            TokenKind::Not => "!",
+            TokenKind::Assign => "=",
+            TokenKind::LeftParen => "(",
+            TokenKind::RightParen => ")",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::LeftBracket => "[",
+            TokenKind::RightBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+        };
+        write!(f, "`{}`", text)
+    }
+}
+
+/// A token together with where it was found.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Token {
+    /// What kind of token this is.
+    pub kind: TokenKind,
+    /// Where the token was found.
+    pub span: Span,
+}
+
+/// Problem encountered while tokenizing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LexError {
+    /// Where the problem is.
+    pub span: Span,
+    /// What the problem is.
+    pub kind: LexErrorKind,
+}
+
+/// The kinds of problems encountered while tokenizing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LexErrorKind {
+    /// A character that can not start a token.
+    InvalidCharacter(char),
+    /// A single `&` or `|`, which are only valid doubled.
+    Incomplete(char),
+    /// A number that runs straight into identifier characters, e.g. `12ab`.
+    InvalidNumber,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.span)?;
+        match self.kind {
+            LexErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+
+            LexErrorKind::Incomplete(c) => write!(f, "expected `{}{}`", c, c),
+
+            LexErrorKind::InvalidNumber => write!(f, "invalid number"),
+        }
+    }
+}
+
+impl Error for LexError {}
+
+/// Iterator over the tokens of Laconic source.
+///
+/// After an error the lexer skips the offending characters and carries on, so
+/// all problems in a source can be reported at once.
+pub struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Create a lexer for `source`.
+    pub fn new(source: &'a str) -> Lexer<'a> {
+        Lexer { source, offset: 0, line: 1, column: 1 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.offset..].chars().nth(1)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn advance_while<P>(&mut self, predicate: P) where P: Fn(char) -> bool {
+        while self.peek().is_some_and(&predicate) {
+            self.advance();
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.advance();
+                }
+
+                Some('/') if self.peek_second() == Some('/') => self.advance_while(|c| c != '\n'),
+
+                _ => return,
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        self.skip_whitespace_and_comments();
+        let (start, line, column) = (self.offset, self.line, self.column);
+        let c = self.advance()?;
+        let doubled = |lexer: &mut Lexer, second: char| {
+            if lexer.peek() == Some(second) {
+                lexer.advance();
+                true
+            } else {
+                false
+            }
+        };
+
+        let kind = match c {
+            '+' => Ok(TokenKind::Plus),
+            '-' => Ok(TokenKind::Minus),
+            '*' => Ok(TokenKind::Star),
+            '/' => Ok(TokenKind::Slash),
+            '%' => Ok(TokenKind::Percent),
+            '(' => Ok(TokenKind::LeftParen),
+            ')' => Ok(TokenKind::RightParen),
+            '{' => Ok(TokenKind::LeftBrace),
+            '}' => Ok(TokenKind::RightBrace),
+            '[' => Ok(TokenKind::LeftBracket),
+            ']' => Ok(TokenKind::RightBracket),
+            ',' => Ok(TokenKind::Comma),
+            ';' => Ok(TokenKind::Semicolon),
+            '=' => Ok(if doubled(self, '=') { TokenKind::EqualEqual } else { TokenKind::Assign }),
+            '!' => Ok(if doubled(self, '=') { TokenKind::NotEqual } else { TokenKind::Not }),
+            '<' => Ok(if doubled(self, '=') { TokenKind::LessEqual } else { TokenKind::Less }),
+            '>' => Ok(if doubled(self, '=') { TokenKind::GreaterEqual } else { TokenKind::Greater }),
+            '&' => if doubled(self, '&') { Ok(TokenKind::And) } else { Err(LexErrorKind::Incomplete('&')) },
+            '|' => if doubled(self, '|') { Ok(TokenKind::Or) } else { Err(LexErrorKind::Incomplete('|')) },
+
+            c if c.is_ascii_digit() => {
+                self.advance_while(|c| c.is_ascii_digit());
+                if self.peek().is_some_and(is_identifier) {
+                    self.advance_while(is_identifier);
+                    Err(LexErrorKind::InvalidNumber)
+                } else {
+                    Ok(TokenKind::Integer(self.source[start..self.offset].to_string()))
+                }
+            }
+
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                self.advance_while(is_identifier);
+                Ok(match &self.source[start..self.offset] {
+                    "func" => TokenKind::Func,
+                    "while" => TokenKind::While,
+                    "if" => TokenKind::If,
+                    "else" => TokenKind::Else,
+                    "return" => TokenKind::Return,
+                    "print" => TokenKind::Print,
+                    "int" => TokenKind::Int,
+                    "list" => TokenKind::List,
+                    "list2" => TokenKind::List2,
+                    name => TokenKind::Identifier(name.to_string()),
+                })
+            }
+
+            c => Err(LexErrorKind::InvalidCharacter(c)),
+        };
+
+        let span = Span { start, end: self.offset, line, column };
+        Some(match kind {
+            Ok(kind) => Ok(Token { kind, span }),
+
+            Err(kind) => Err(LexError { span, kind }),
+        })
+    }
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Tokenize all of `source`, reporting every problem found.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, Vec<LexError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for result in Lexer::new(source) {
+        match result {
+            Ok(token) => tokens.push(token),
+
+            Err(error) => errors.push(error),
+        }
+    }
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source).expect("valid tokens").into_iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn should_recognize_keywords_and_identifiers() {
+        assert_eq!(
+            kinds("func while if else return print int list list2 list3 _x"),
+            vec![
+                TokenKind::Func, TokenKind::While, TokenKind::If, TokenKind::Else,
+                TokenKind::Return, TokenKind::Print, TokenKind::Int, TokenKind::List,
+                TokenKind::List2, TokenKind::Identifier("list3".to_string()),
+                TokenKind::Identifier("_x".to_string()),
+            ]);
+    }
+
+    #[test]
+    fn should_recognize_operators() {
+        assert_eq!(
+            kinds("+-*/% == != < <= > >= && || ! = ( ) { } [ ] , ;"),
+            vec![
+                TokenKind::Plus, TokenKind::Minus, TokenKind::Star, TokenKind::Slash,
+                TokenKind::Percent, TokenKind::EqualEqual, TokenKind::NotEqual, TokenKind::Less,
+                TokenKind::LessEqual, TokenKind::Greater, TokenKind::GreaterEqual, TokenKind::And,
+                TokenKind::Or, TokenKind::Not, TokenKind::Assign, TokenKind::LeftParen,
+                TokenKind::RightParen, TokenKind::LeftBrace, TokenKind::RightBrace,
+                TokenKind::LeftBracket, TokenKind::RightBracket, TokenKind::Comma, TokenKind::Semicolon,
+            ]);
+    }
+
+    #[test]
+    fn should_keep_integer_literals_as_written() {
+        assert_eq!(
+            kinds("x=123456789012345678901234567890;"),
+            vec![
+                TokenKind::Identifier("x".to_string()),
+                TokenKind::Assign,
+                TokenKind::Integer("123456789012345678901234567890".to_string()),
+                TokenKind::Semicolon,
+            ]);
+    }
+
+    #[test]
+    fn should_skip_comments() {
+        assert_eq!(
+            kinds("int x; // a comment / with slashes\n// another\nx = x / 2;"),
+            vec![
+                TokenKind::Int, TokenKind::Identifier("x".to_string()), TokenKind::Semicolon,
+                TokenKind::Identifier("x".to_string()), TokenKind::Assign,
+                TokenKind::Identifier("x".to_string()), TokenKind::Slash,
+                TokenKind::Integer("2".to_string()), TokenKind::Semicolon,
+            ]);
+    }
+
+    #[test]
+    fn should_track_spans() {
+        let tokens = tokenize("func f() {\n  return 10;\n}").expect("valid tokens");
+
+        assert_eq!(tokens[5].kind, TokenKind::Return);
+        assert_eq!(tokens[5].span, Span { start: 13, end: 19, line: 2, column: 3 });
+        assert_eq!(tokens[6].span, Span { start: 20, end: 22, line: 2, column: 10 });
+    }
+
+    #[test]
+    fn should_report_every_invalid_character() {
+        let errors = tokenize("int x = 3 $ 4;\nx = x & 1 @").unwrap_err();
+
+        assert_eq!(errors, vec![
+            LexError {
+                span: Span { start: 10, end: 11, line: 1, column: 11 },
+                kind: LexErrorKind::InvalidCharacter('$'),
+            },
+            LexError {
+                span: Span { start: 21, end: 22, line: 2, column: 7 },
+                kind: LexErrorKind::Incomplete('&'),
+            },
+            LexError {
+                span: Span { start: 25, end: 26, line: 2, column: 11 },
+                kind: LexErrorKind::InvalidCharacter('@'),
+            },
+        ]);
+        assert_eq!(errors[0].to_string(), "1:11: invalid character '$'");
+    }
+
+    #[test]
+    fn should_reject_numbers_running_into_letters() {
+        let errors = tokenize("12ab").unwrap_err();
+
+        assert_eq!(errors[0].kind, LexErrorKind::InvalidNumber);
+        assert_eq!(errors[0].span.end, 4);
+    }
+}
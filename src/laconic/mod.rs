@@ -1,3 +1,65 @@
+#![deny(missing_docs)]
+//! Laconic is the high-level language that Parsimony compiles down to
+//! two-symbol Turing machines.
+//!
+//! A Laconic program is a sequence of functions. Execution starts in `main`,
+//! which takes no arguments, and the program halts when `main` returns.
+//!
+//! ```text
+//! // Prints the primes below 20.
+//! func isPrime(int n) int {
+//!     int d = 2;
+//!     while (d * d <= n) {
+//!         if (n % d == 0) {
+//!             return 0;
+//!         }
+//!         d = d + 1;
+//!     }
+//!     return n >= 2;
+//! }
+//!
+//! func main() {
+//!     int n = 0;
+//!     while (n < 20) {
+//!         if (isPrime(n)) {
+//!             print(n);
+//!         }
+//!         n = n + 1;
+//!     }
+//! }
+//! ```
+//!
+//! There are three types:
+//!
+//! * `int`, the unbounded natural numbers
+//! * `list`, lists of `int`
+//! * `list2`, lists of `list`
+//!
+//! Variables are declared with their type and an optional initial value.
+//! Without an initial value an `int` is 0 and a list is empty. Values are
+//! copied on assignment and when passed to a function.
+//!
+//! Integers support `+`, `-`, `*`, `/` and `%`. Subtraction stops at 0 and
+//! dividing by 0 is an error. Comparisons and the logical operators `&&`, `||`
+//! and `!` work on integers, treat 0 as false and everything else as true, and
+//! produce 0 or 1. The right operand of `&&` and `||` is only evaluated when
+//! it decides the outcome. `+` concatenates lists, `len(l)` is the length of a list
+//! and `l[i]` its element at index `i`, counting from 0. Elements can be
+//! assigned with `l[i] = e`. Indexing outside of a list is an error.
+//!
+//! `print` outputs an `int`.
+//!
+//! A name can not be declared again while it is visible, not even in a nested
+//! block, and functions can not call themselves, directly or indirectly.
+
+pub mod ast;
+pub mod check;
+pub mod codegen;
+pub mod interp;
+pub mod lexer;
+pub mod parser;
+pub mod report;
+
 #[cfg(test)]
 mod tests {
     #[test]
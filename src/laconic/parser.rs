@@ -0,0 +1,702 @@
+//! Parser turns Laconic source into an abstract syntax tree.
+//!
+//! The parser is a recursive descent parser. When it runs into a problem it
+//! records it, skips ahead to the next statement or function and carries on,
+//! so a single run reports several problems.
+//!
+//! Expressions bind from loosest to tightest as follows:
+//!
+//! 1. `||`
+//! 2. `&&`
+//! 3. `==`, `!=`
+//! 4. `<`, `<=`, `>`, `>=`
+//! 5. `+`, `-`
+//! 6. `*`, `/`, `%`
+//! 7. `!`
+//! 8. calls `f(a, b)` and indexing `l[i]`
+//!
+//! All binary operators associate to the left.
+
+use std::error::Error;
+use std::fmt;
+use super::ast::{BinaryOperator, Block, Expression, ExpressionKind, Function, Identifier, Parameter, Program, Statement, StatementKind, Type, UnaryOperator};
+use super::lexer::{LexError, LexErrorKind, Lexer, Span, Token, TokenKind};
+
+/// Problem encountered while parsing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    /// Where the problem is.
+    pub span: Span,
+    /// What the problem is.
+    pub kind: ParseErrorKind,
+}
+
+/// The kinds of problems encountered while parsing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseErrorKind {
+    /// The source could not be tokenized.
+    Lex(LexErrorKind),
+    /// Something else was found than what was expected; `None` is the end of
+    /// the source.
+    Expected {
+        /// A description of what was expected.
+        expected: &'static str,
+        /// What was found instead.
+        found: Option<TokenKind>,
+    },
+    /// The left hand side of `=` is not a variable or an element of one.
+    InvalidAssignmentTarget,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::Lex(ref kind) => write!(f, "{}", LexError { span: self.span, kind: kind.clone() }),
+
+            ParseErrorKind::Expected { expected, found: Some(ref found) } =>
+                write!(f, "{}: expected {}, found {}", self.span, expected, found),
+
+            ParseErrorKind::Expected { expected, found: None } =>
+                write!(f, "{}: expected {}, found end of source", self.span, expected),
+
+            ParseErrorKind::InvalidAssignmentTarget =>
+                write!(f, "{}: can only assign to a variable or an element of one", self.span),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a Laconic program, reporting all problems found.
+pub fn parse(source: &str) -> Result<Program, Vec<ParseError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for result in Lexer::new(source) {
+        match result {
+            Ok(token) => tokens.push(token),
+
+            Err(error) => errors.push(ParseError { span: error.span, kind: ParseErrorKind::Lex(error.kind) }),
+        }
+    }
+
+    let end = end_of(source);
+    let mut parser = Parser { tokens, position: 0, end, errors };
+    let program = parser.program();
+    if parser.errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(parser.errors)
+    }
+}
+
+fn end_of(source: &str) -> Span {
+    let line = source.matches('\n').count() + 1;
+    let column = source[source.rfind('\n').map_or(0, |index| index + 1)..].chars().count() + 1;
+    Span { start: source.len(), end: source.len(), line, column }
+}
+
+/// Marker for a problem that has already been recorded.
+struct Recorded;
+
+type Parsed<T> = Result<T, Recorded>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end: Span,
+    errors: Vec<ParseError>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens.get(self.position).map_or(self.end, |token| token.span)
+    }
+
+    fn previous_span(&self) -> Span {
+        self.tokens[self.position - 1].span
+    }
+
+    fn check(&self, kind: &TokenKind) -> bool {
+        self.peek() == Some(kind)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.check(kind) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T>(&mut self, expected: &'static str) -> Parsed<T> {
+        let error = ParseError {
+            span: self.span(),
+            kind: ParseErrorKind::Expected { expected, found: self.peek().cloned() },
+        };
+        self.errors.push(error);
+        Err(Recorded)
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &'static str) -> Parsed<Span> {
+        if self.eat(&kind) {
+            Ok(self.previous_span())
+        } else {
+            self.error(expected)
+        }
+    }
+
+    fn identifier(&mut self, expected: &'static str) -> Parsed<Identifier> {
+        match self.peek() {
+            Some(TokenKind::Identifier(name)) => {
+                let name = name.clone();
+                let span = self.span();
+                self.position += 1;
+                Ok(Identifier { name, span })
+            }
+
+            _ => self.error(expected),
+        }
+    }
+
+    fn ty(&mut self) -> Option<Type> {
+        let ty = match self.peek() {
+            Some(&TokenKind::Int) => Type::Int,
+
+            Some(&TokenKind::List) => Type::List,
+
+            Some(&TokenKind::List2) => Type::List2,
+
+            _ => return None,
+        };
+        self.position += 1;
+        Some(ty)
+    }
+
+    /// Skip to the next `func`, to recover from a problem outside of a body.
+    fn skip_to_function(&mut self) {
+        while self.peek().is_some() && !self.check(&TokenKind::Func) {
+            self.position += 1;
+        }
+    }
+
+    /// Skip past the current statement, to recover from a problem inside it.
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(kind) = self.peek().cloned() {
+            match kind {
+                TokenKind::Semicolon if depth == 0 => {
+                    self.position += 1;
+                    return;
+                }
+
+                TokenKind::LeftBrace => depth += 1,
+
+                TokenKind::RightBrace if depth == 0 => return,
+
+                TokenKind::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.position += 1;
+                        return;
+                    }
+                }
+
+                TokenKind::Func => return,
+
+                _ => (),
+            }
+            self.position += 1;
+        }
+    }
+
+    fn program(&mut self) -> Program {
+        let mut functions = Vec::new();
+        while self.peek().is_some() {
+            if self.check(&TokenKind::Func) {
+                if let Ok(function) = self.function() {
+                    functions.push(function);
+                }
+            } else {
+                let _ = self.error::<()>("`func`");
+                self.position += 1;
+                self.skip_to_function();
+            }
+        }
+        Program { functions }
+    }
+
+    fn function(&mut self) -> Parsed<Function> {
+        let start = self.span();
+        self.position += 1;
+        let header = self.function_header();
+        if header.is_err() {
+            self.skip_to_function();
+        }
+        let (name, parameters, returns) = header?;
+        let body = self.block()?;
+        let span = start.to(&body.span);
+        Ok(Function { name, parameters, returns, body, span })
+    }
+
+    fn function_header(&mut self) -> Parsed<(Identifier, Vec<Parameter>, Option<Type>)> {
+        let name = self.identifier("function name")?;
+        self.expect(TokenKind::LeftParen, "`(`")?;
+        let mut parameters = Vec::new();
+        if !self.eat(&TokenKind::RightParen) {
+            loop {
+                let ty = match self.ty() {
+                    Some(ty) => ty,
+
+                    None => return self.error("parameter type"),
+                };
+                let name = self.identifier("parameter name")?;
+                parameters.push(Parameter { ty, name });
+                if self.eat(&TokenKind::RightParen) {
+                    break;
+                }
+                self.expect(TokenKind::Comma, "`,` or `)`")?;
+            }
+        }
+        let returns = self.ty();
+        if !self.check(&TokenKind::LeftBrace) {
+            return self.error("return type or `{`");
+        }
+        Ok((name, parameters, returns))
+    }
+
+    fn block(&mut self) -> Parsed<Block> {
+        let start = self.expect(TokenKind::LeftBrace, "`{`")?;
+        let mut statements = Vec::new();
+        loop {
+            match self.peek() {
+                Some(&TokenKind::RightBrace) => {
+                    self.position += 1;
+                    break;
+                }
+
+                None | Some(&TokenKind::Func) => return self.error("`}`"),
+
+                _ => match self.statement() {
+                    Ok(statement) => statements.push(statement),
+
+                    Err(Recorded) => self.skip_statement(),
+                },
+            }
+        }
+        Ok(Block { statements, span: start.to(&self.previous_span()) })
+    }
+
+    fn statement(&mut self) -> Parsed<Statement> {
+        let start = self.span();
+        let kind = match self.peek() {
+            Some(&TokenKind::Int) | Some(&TokenKind::List) | Some(&TokenKind::List2) => self.declaration()?,
+
+            Some(&TokenKind::While) => {
+                self.position += 1;
+                let condition = self.condition()?;
+                let body = self.block()?;
+                StatementKind::While { condition, body }
+            }
+
+            Some(&TokenKind::If) => self.if_statement()?,
+
+            Some(&TokenKind::Return) => {
+                self.position += 1;
+                let value = if self.check(&TokenKind::Semicolon) {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+                self.expect(TokenKind::Semicolon, "`;`")?;
+                StatementKind::Return(value)
+            }
+
+            Some(&TokenKind::Print) => {
+                self.position += 1;
+                self.expect(TokenKind::LeftParen, "`(`")?;
+                let value = self.expression()?;
+                self.expect(TokenKind::RightParen, "`)`")?;
+                self.expect(TokenKind::Semicolon, "`;`")?;
+                StatementKind::Print(value)
+            }
+
+            _ => self.assignment_or_expression()?,
+        };
+        Ok(Statement { kind, span: start.to(&self.previous_span()) })
+    }
+
+    fn declaration(&mut self) -> Parsed<StatementKind> {
+        let ty = self.ty().expect("a type starting a declaration");
+        let name = self.identifier("variable name")?;
+        let value = if self.eat(&TokenKind::Assign) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Semicolon, "`=` or `;`")?;
+        Ok(StatementKind::Declaration { ty, name, value })
+    }
+
+    fn if_statement(&mut self) -> Parsed<StatementKind> {
+        self.position += 1;
+        let condition = self.condition()?;
+        let then = self.block()?;
+        let otherwise = if self.eat(&TokenKind::Else) {
+            if self.check(&TokenKind::If) {
+                let start = self.span();
+                let kind = self.if_statement()?;
+                let span = start.to(&self.previous_span());
+                Some(Block { statements: vec![Statement { kind, span }], span })
+            } else {
+                Some(self.block()?)
+            }
+        } else {
+            None
+        };
+        Ok(StatementKind::If { condition, then, otherwise })
+    }
+
+    fn condition(&mut self) -> Parsed<Expression> {
+        self.expect(TokenKind::LeftParen, "`(`")?;
+        let condition = self.expression()?;
+        self.expect(TokenKind::RightParen, "`)`")?;
+        Ok(condition)
+    }
+
+    fn assignment_or_expression(&mut self) -> Parsed<StatementKind> {
+        let expression = self.expression()?;
+        if self.eat(&TokenKind::Assign) {
+            let (target, indices) = match into_target(expression) {
+                Ok(target) => target,
+
+                Err(span) => {
+                    self.errors.push(ParseError { span, kind: ParseErrorKind::InvalidAssignmentTarget });
+                    return Err(Recorded);
+                }
+            };
+            let value = self.expression()?;
+            self.expect(TokenKind::Semicolon, "`;`")?;
+            Ok(StatementKind::Assignment { target, indices, value })
+        } else {
+            self.expect(TokenKind::Semicolon, "`=` or `;`")?;
+            Ok(StatementKind::Expression(expression))
+        }
+    }
+
+    fn expression(&mut self) -> Parsed<Expression> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Parsed<Expression> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let operator = match self.peek().and_then(|kind| operator(LEVELS[level], kind)) {
+                Some(operator) => operator,
+
+                None => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            let span = left.span.to(&right.span);
+            left = Expression::new(ExpressionKind::Binary(operator, Box::new(left), Box::new(right)), span);
+        }
+    }
+
+    fn unary(&mut self) -> Parsed<Expression> {
+        if self.check(&TokenKind::Not) {
+            let start = self.span();
+            self.position += 1;
+            let operand = self.unary()?;
+            let span = start.to(&operand.span);
+            return Ok(Expression::new(ExpressionKind::Unary(UnaryOperator::Not, Box::new(operand)), span));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Parsed<Expression> {
+        let mut expression = self.primary()?;
+        while self.eat(&TokenKind::LeftBracket) {
+            let index = self.expression()?;
+            let end = self.expect(TokenKind::RightBracket, "`]`")?;
+            let span = expression.span.to(&end);
+            expression = Expression::new(ExpressionKind::Index(Box::new(expression), Box::new(index)), span);
+        }
+        Ok(expression)
+    }
+
+    fn primary(&mut self) -> Parsed<Expression> {
+        let start = self.span();
+        match self.peek().cloned() {
+            Some(TokenKind::Integer(digits)) => {
+                self.position += 1;
+                Ok(Expression::new(ExpressionKind::Integer(digits), start))
+            }
+
+            Some(TokenKind::Identifier(name)) => {
+                self.position += 1;
+                if self.check(&TokenKind::LeftParen) {
+                    let arguments = self.arguments(TokenKind::LeftParen, TokenKind::RightParen, "`,` or `)`")?;
+                    let span = start.to(&self.previous_span());
+                    Ok(Expression::new(ExpressionKind::Call(Identifier { name, span: start }, arguments), span))
+                } else {
+                    Ok(Expression::new(ExpressionKind::Variable(name), start))
+                }
+            }
+
+            Some(TokenKind::LeftBracket) => {
+                let elements = self.arguments(TokenKind::LeftBracket, TokenKind::RightBracket, "`,` or `]`")?;
+                Ok(Expression::new(ExpressionKind::List(elements), start.to(&self.previous_span())))
+            }
+
+            Some(TokenKind::LeftParen) => {
+                self.position += 1;
+                let mut expression = self.expression()?;
+                let end = self.expect(TokenKind::RightParen, "`)`")?;
+                expression.span = start.to(&end);
+                Ok(expression)
+            }
+
+            _ => self.error("expression"),
+        }
+    }
+
+    fn arguments(&mut self, open: TokenKind, close: TokenKind, expected: &'static str) -> Parsed<Vec<Expression>> {
+        self.advance().filter(|token| token.kind == open).expect("an opening token");
+        let mut arguments = Vec::new();
+        if self.eat(&close) {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.expression()?);
+            if self.eat(&close) {
+                return Ok(arguments);
+            }
+            self.expect(TokenKind::Comma, expected)?;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Level {
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Additive,
+    Multiplicative,
+}
+
+const LEVELS: [Level; 6] = [Level::Or, Level::And, Level::Equality, Level::Comparison, Level::Additive, Level::Multiplicative];
+
+fn operator(level: Level, kind: &TokenKind) -> Option<BinaryOperator> {
+    match (level, kind) {
+        (Level::Or, &TokenKind::Or) => Some(BinaryOperator::Or),
+
+        (Level::And, &TokenKind::And) => Some(BinaryOperator::And),
+
+        (Level::Equality, &TokenKind::EqualEqual) => Some(BinaryOperator::Equal),
+        (Level::Equality, &TokenKind::NotEqual) => Some(BinaryOperator::NotEqual),
+
+        (Level::Comparison, &TokenKind::Less) => Some(BinaryOperator::Less),
+        (Level::Comparison, &TokenKind::LessEqual) => Some(BinaryOperator::LessEqual),
+        (Level::Comparison, &TokenKind::Greater) => Some(BinaryOperator::Greater),
+        (Level::Comparison, &TokenKind::GreaterEqual) => Some(BinaryOperator::GreaterEqual),
+
+        (Level::Additive, &TokenKind::Plus) => Some(BinaryOperator::Add),
+        (Level::Additive, &TokenKind::Minus) => Some(BinaryOperator::Subtract),
+
+        (Level::Multiplicative, &TokenKind::Star) => Some(BinaryOperator::Multiply),
+        (Level::Multiplicative, &TokenKind::Slash) => Some(BinaryOperator::Divide),
+        (Level::Multiplicative, &TokenKind::Percent) => Some(BinaryOperator::Remainder),
+
+        _ => None,
+    }
+}
+
+/// Turn the left hand side of an assignment into the assigned variable and
+/// the indices, or the span of the offending expression.
+fn into_target(expression: Expression) -> Result<(Identifier, Vec<Expression>), Span> {
+    match expression.kind {
+        ExpressionKind::Variable(name) => Ok((Identifier { name, span: expression.span }, Vec::new())),
+
+        ExpressionKind::Index(list, index) => {
+            let (target, mut indices) = into_target(*list)?;
+            indices.push(*index);
+            Ok((target, indices))
+        }
+
+        _ => Err(expression.span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(source: &str) -> Expression {
+        let program = parse(&format!("func main() {{ {}; }}", source)).expect("a valid program");
+        match program.functions[0].body.statements[0].kind {
+            StatementKind::Expression(ref expression) => expression.clone(),
+
+            ref kind => panic!("not an expression: {:?}", kind),
+        }
+    }
+
+    /// Write an expression with explicit parentheses, to check its structure.
+    fn show(expression: &Expression) -> String {
+        match expression.kind {
+            ExpressionKind::Integer(ref digits) => digits.clone(),
+
+            ExpressionKind::Variable(ref name) => name.clone(),
+
+            ExpressionKind::List(ref elements) =>
+                format!("[{}]", elements.iter().map(show).collect::<Vec<_>>().join(", ")),
+
+            ExpressionKind::Binary(operator, ref left, ref right) =>
+                format!("({} {} {})", show(left), operator, show(right)),
+
+            ExpressionKind::Unary(UnaryOperator::Not, ref operand) => format!("!{}", show(operand)),
+
+            ExpressionKind::Call(ref name, ref arguments) =>
+                format!("{}({})", name.name, arguments.iter().map(show).collect::<Vec<_>>().join(", ")),
+
+            ExpressionKind::Index(ref list, ref index) => format!("{}[{}]", show(list), show(index)),
+        }
+    }
+
+    #[test]
+    fn should_respect_operator_precedence() {
+        assert_eq!(show(&expression("1 + 2 * 3 - 4")), "((1 + (2 * 3)) - 4)");
+        assert_eq!(show(&expression("a < b + 1 == c || !d && e")), "(((a < (b + 1)) == c) || (!d && e))");
+        assert_eq!(show(&expression("(1 + 2) * f(x, l[i][j + 1]) % 7")), "(((1 + 2) * f(x, l[i][(j + 1)])) % 7)");
+        assert_eq!(show(&expression("[1, [a], []] + g()")), "([1, [a], []] + g())");
+    }
+
+    #[test]
+    fn should_track_expression_spans() {
+        let expression = expression("(a + b) * c");
+
+        assert_eq!(expression.span.start, 14);
+        assert_eq!(expression.span.end, 25);
+        assert_eq!(expression.span.column, 15);
+    }
+
+    #[test]
+    fn should_parse_functions_and_statements() {
+        let program = parse("
+            func sum(list l, int n) int {
+                int total;
+                int i = 0;
+                while (i < n) {
+                    total = total + l[i];
+                    i = i + 1;
+                }
+                return total;
+            }
+
+            func main() {
+                list2 m = [[1], [2, 3]];
+                m[1][0] = 4;
+                if (sum(m[1], 2) > 5) {
+                    print(1);
+                } else if (1) {
+                    print(2);
+                } else {
+                    return;
+                }
+            }
+        ").expect("a valid program");
+
+        assert_eq!(program.functions.len(), 2);
+        let sum = program.function("sum").expect("a function sum");
+        assert_eq!(sum.returns, Some(Type::Int));
+        assert_eq!(sum.parameters.iter().map(|parameter| parameter.ty).collect::<Vec<_>>(), vec![Type::List, Type::Int]);
+        assert_eq!(sum.body.statements.len(), 4);
+
+        let main = program.function("main").expect("a function main");
+        match main.body.statements[1].kind {
+            StatementKind::Assignment { ref target, ref indices, .. } => {
+                assert_eq!(target.name, "m");
+                assert_eq!(indices.iter().map(show).collect::<Vec<_>>(), vec!["1", "0"]);
+            }
+
+            ref kind => panic!("not an assignment: {:?}", kind),
+        }
+        match main.body.statements[2].kind {
+            StatementKind::If { otherwise: Some(ref otherwise), .. } => match otherwise.statements[0].kind {
+                StatementKind::If { otherwise: Some(_), .. } => (),
+
+                ref kind => panic!("not an else if: {:?}", kind),
+            },
+
+            ref kind => panic!("not an if with else: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn should_report_several_problems() {
+        let errors = parse("
+func main() {
+    int x = ;
+    x = x + 1;
+    y + = 2;
+    print(x)
+}
+
+func broken( {
+}
+
+func other() {
+    3 = x;
+}
+").unwrap_err();
+
+        let reported: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(reported, vec![
+            "3:13: expected expression, found `;`",
+            "5:9: expected expression, found `=`",
+            "7:1: expected `;`, found `}`",
+            "9:14: expected parameter type, found `{`",
+            "13:5: can only assign to a variable or an element of one",
+        ]);
+    }
+
+    #[test]
+    fn should_report_lexical_problems() {
+        let errors = parse("func main() { int x = 1 $ 2; }").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "1:25: invalid character '$'");
+        assert_eq!(errors[1].to_string(), "1:27: expected `=` or `;`, found integer `2`");
+    }
+
+    #[test]
+    fn should_report_a_missing_brace_at_the_end() {
+        let errors = parse("func main() {\n  print(1);\n").unwrap_err();
+
+        assert_eq!(errors[0].to_string(), "3:1: expected `}`, found end of source");
+    }
+
+    #[test]
+    fn should_count_the_end_of_source_in_characters() {
+        let errors = parse("func main() {\n// héé").unwrap_err();
+
+        assert_eq!(errors[0].to_string(), "2:7: expected `}`, found end of source");
+    }
+}
@@ -0,0 +1,259 @@
+//! Report attributes the states of a compiled Laconic program to where they
+//! came from: the Laconic function, the TMD function and the stage of the
+//! compiler.
+//!
+//! The TMD function of a state is the function with the command the state was
+//! generated for. Its Laconic function is the nearest function in the chain of
+//! calls that is not part of the library, so the states of the library
+//! functions a Laconic function uses count towards that function. States of
+//! the program as a whole, like those initializing the tape, belong to no
+//! function.
+
+use std::collections::HashMap;
+use std::fmt;
+use tmd::compile::{Compilation, Stage};
+use super::codegen::is_library;
+
+/// The number of states that came from each place.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Report {
+    /// The number of states of the machine.
+    pub states: usize,
+    /// The states by Laconic function, TMD function and stage, in the order
+    /// the functions were first generated and the order of the stages.
+    pub rows: Vec<Row>,
+}
+
+/// The number of states that came from one place.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Row {
+    /// The Laconic function, or `None` for the program as a whole.
+    pub laconic: Option<String>,
+    /// The TMD function, or `None` for the program as a whole.
+    pub tmd: Option<String>,
+    /// The stage of the compiler.
+    pub stage: Stage,
+    /// The number of states.
+    pub states: usize,
+}
+
+/// Attribute the states of `compilation` to where they came from.
+pub fn report(compilation: &Compilation) -> Report {
+    let mut counts: HashMap<(Option<&str>, Option<&str>, Stage), usize> = HashMap::new();
+    let mut order: HashMap<Option<&str>, usize> = HashMap::new();
+    for origin in &compilation.origins {
+        let tmd = origin.calls.last().map(String::as_str);
+        let laconic = origin.calls.iter().rev().map(String::as_str).find(|call| !is_library(call));
+        for name in [laconic, tmd] {
+            let next = order.len();
+            order.entry(name).or_insert(next);
+        }
+        *counts.entry((laconic, tmd, origin.stage)).or_insert(0) += 1;
+    }
+
+    let stage = |stage: Stage| Stage::ALL.iter().position(|&known| known == stage);
+    let mut rows: Vec<_> = counts.into_iter().collect();
+    rows.sort_by_key(|&((laconic, tmd, known), _)| (order[&laconic], order[&tmd], stage(known)));
+    let rows = rows.into_iter().map(|((laconic, tmd, stage), states)| Row {
+        laconic: laconic.map(String::from),
+        tmd: tmd.map(String::from),
+        stage,
+        states,
+    }).collect();
+    Report { states: compilation.states, rows }
+}
+
+impl Report {
+    /// The states by Laconic function.
+    pub fn by_laconic(&self) -> Vec<(Option<String>, usize)> {
+        total(self.rows.iter().map(|row| (row.laconic.clone(), row.states)))
+    }
+
+    /// The states by TMD function.
+    pub fn by_tmd(&self) -> Vec<(Option<String>, usize)> {
+        total(self.rows.iter().map(|row| (row.tmd.clone(), row.states)))
+    }
+
+    /// The states by stage, leaving out the stages without states.
+    pub fn by_stage(&self) -> Vec<(Stage, usize)> {
+        Stage::ALL.iter().map(|&stage| (stage, self.rows.iter().filter(|row| row.stage == stage).map(|row| row.states).sum()))
+            .filter(|&(_, states)| states > 0)
+            .collect()
+    }
+
+    /// The report as a JSON object, with the number of states, the totals by
+    /// Laconic function, TMD function and stage, and all rows.
+    pub fn to_json(&self) -> String {
+        let name = |name: &Option<String>| name.as_ref().map(|name| quote(name)).unwrap_or_else(|| "null".to_string());
+        let totals = |totals: Vec<(String, usize)>| {
+            let totals: Vec<String> = totals.into_iter().map(|(name, states)| format!("{{\"name\":{},\"states\":{}}}", name, states)).collect();
+            totals.join(",")
+        };
+        let rows: Vec<String> = self.rows.iter().map(|row| {
+            format!("{{\"laconic\":{},\"tmd\":{},\"stage\":{},\"states\":{}}}", name(&row.laconic), name(&row.tmd), quote(&row.stage.to_string()), row.states)
+        }).collect();
+
+        format!("{{\"states\":{},\"laconic\":[{}],\"tmd\":[{}],\"stage\":[{}],\"rows\":[{}]}}",
+            self.states,
+            totals(self.by_laconic().iter().map(|(laconic, states)| (name(laconic), *states)).collect()),
+            totals(self.by_tmd().iter().map(|(tmd, states)| (name(tmd), *states)).collect()),
+            totals(self.by_stage().into_iter().map(|(stage, states)| (quote(&stage.to_string()), states)).collect()),
+            rows.join(","))
+    }
+}
+
+/// Shows the rows as a table, followed by the totals by Laconic function, TMD
+/// function and stage.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "-".to_string());
+        let mut rows: Vec<Vec<String>> = self.rows.iter()
+            .map(|row| vec![name(&row.laconic), name(&row.tmd), row.stage.to_string(), row.states.to_string()])
+            .collect();
+        rows.push(vec!["total".to_string(), String::new(), String::new(), self.states.to_string()]);
+        table(f, &["laconic", "tmd", "stage", "states"], &rows)?;
+
+        let laconic: Vec<Vec<String>> = self.by_laconic().iter().map(|(laconic, states)| vec![name(laconic), states.to_string()]).collect();
+        writeln!(f)?;
+        table(f, &["laconic", "states"], &laconic)?;
+
+        let tmd: Vec<Vec<String>> = self.by_tmd().iter().map(|(tmd, states)| vec![name(tmd), states.to_string()]).collect();
+        writeln!(f)?;
+        table(f, &["tmd", "states"], &tmd)?;
+
+        let stages: Vec<Vec<String>> = self.by_stage().iter().map(|(stage, states)| vec![stage.to_string(), states.to_string()]).collect();
+        writeln!(f)?;
+        table(f, &["stage", "states"], &stages)
+    }
+}
+
+/// Sum the states by name, keeping the names in order of first appearance.
+fn total<I>(states: I) -> Vec<(Option<String>, usize)> where I: Iterator<Item = (Option<String>, usize)> {
+    let mut totals: Vec<(Option<String>, usize)> = Vec::new();
+    for (name, count) in states {
+        match totals.iter_mut().find(|(known, _)| *known == name) {
+            Some((_, total)) => *total += count,
+
+            None => totals.push((name, count)),
+        }
+    }
+    totals
+}
+
+/// Write `rows` under `headers`, with the columns aligned and the last one,
+/// holding numbers, to the right.
+fn table(f: &mut fmt::Formatter, headers: &[&str], rows: &[Vec<String>]) -> fmt::Result {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let last = headers.len() - 1;
+    let lines = Some(headers.iter().map(|header| header.to_string()).collect()).into_iter().chain(rows.iter().cloned());
+    for cells in lines {
+        let cells: Vec<String> = cells.iter().enumerate().map(|(column, cell)| {
+            if column == last {
+                format!("{:>width$}", cell, width = widths[column])
+            } else {
+                format!("{:width$}", cell, width = widths[column])
+            }
+        }).collect();
+        writeln!(f, "{}", cells.join("  "))?;
+    }
+    Ok(())
+}
+
+/// `text` as a JSON string.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+
+            '\\' => quoted.push_str("\\\\"),
+
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tmd;
+    use super::super::{check, codegen, parser};
+
+    fn compiled(source: &str) -> Compilation {
+        let mut program = parser::parse(source).expect("a valid Laconic program");
+        assert_eq!(check::check(&mut program), vec![]);
+        let program = tmd::parser::parse(&codegen::generate(&program)).expect("a valid TMD program");
+        tmd::compile::compile(&program).expect("a program without recursion")
+    }
+
+    #[test]
+    fn should_attribute_every_state() {
+        let compilation = compiled("func double(int n) int { return n + n; } func main() { print(double(3)); }");
+
+        let report = report(&compilation);
+
+        assert_eq!(report.rows.iter().map(|row| row.states).sum::<usize>(), compilation.states);
+        assert_eq!(report.by_laconic().iter().map(|(laconic, _)| laconic.clone()).collect::<Vec<_>>(),
+            vec![None, Some("main".to_string()), Some("double".to_string())]);
+        assert!(report.rows.iter().all(|row| row.laconic.is_none() == row.tmd.is_none()));
+        assert!(report.rows.iter().any(|row| row.laconic.as_deref() == Some("double") && row.tmd.as_deref() == Some("lib.append")));
+        assert!(report.rows.iter().filter(|row| row.tmd.as_deref() == Some("double")).all(|row| row.laconic.as_deref() == Some("double")));
+        assert_eq!(report.by_stage().iter().map(|&(_, states)| states).sum::<usize>(), compilation.states);
+        assert_eq!(report.by_tmd().iter().map(|&(_, states)| states).sum::<usize>(), compilation.states);
+    }
+
+    #[test]
+    fn should_show_a_table_and_json() {
+        let report = Report {
+            states: 10,
+            rows: vec![
+                Row { laconic: None, tmd: None, stage: Stage::Initialize, states: 3 },
+                Row { laconic: Some("main".to_string()), tmd: Some("main".to_string()), stage: Stage::Seek, states: 4 },
+                Row { laconic: Some("main".to_string()), tmd: Some("lib.rewind".to_string()), stage: Stage::Seek, states: 2 },
+                Row { laconic: Some("main".to_string()), tmd: Some("lib.rewind".to_string()), stage: Stage::Move, states: 1 },
+            ],
+        };
+
+        assert_eq!(report.to_string(), "\
+laconic  tmd         stage       states
+-        -           initialize       3
+main     main        seek             4
+main     lib.rewind  seek             2
+main     lib.rewind  move             1
+total                                10
+
+laconic  states
+-             3
+main          7
+
+tmd         states
+-                3
+main             4
+lib.rewind       3
+
+stage       states
+initialize       3
+seek             6
+move             1
+");
+        assert_eq!(report.to_json(), concat!(
+            "{\"states\":10,",
+            "\"laconic\":[{\"name\":null,\"states\":3},{\"name\":\"main\",\"states\":7}],",
+            "\"tmd\":[{\"name\":null,\"states\":3},{\"name\":\"main\",\"states\":4},{\"name\":\"lib.rewind\",\"states\":3}],",
+            "\"stage\":[{\"name\":\"initialize\",\"states\":3},{\"name\":\"seek\",\"states\":6},{\"name\":\"move\",\"states\":1}],",
+            "\"rows\":[{\"laconic\":null,\"tmd\":null,\"stage\":\"initialize\",\"states\":3},",
+            "{\"laconic\":\"main\",\"tmd\":\"main\",\"stage\":\"seek\",\"states\":4},",
+            "{\"laconic\":\"main\",\"tmd\":\"lib.rewind\",\"stage\":\"seek\",\"states\":2},",
+            "{\"laconic\":\"main\",\"tmd\":\"lib.rewind\",\"stage\":\"move\",\"states\":1}]}"));
+        assert_eq!(quote("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
@@ -1,3 +1,9 @@
+extern crate num_bigint;
+extern crate num_traits;
+
+#[cfg(test)]
+extern crate proptest;
+
 pub mod laconic;
 pub mod tm;
 pub mod tmd;
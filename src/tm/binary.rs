@@ -0,0 +1,360 @@
+//! Binary rewrites a machine over any finite alphabet into a two-symbol
+//! machine.
+//!
+//! Every symbol is encoded as a block of the same number of bits: its position
+//! in the alphabet, written as a binary number with the most significant bit
+//! first. The blank is the first symbol of the alphabet, so a blank cell
+//! encodes to blank bits.
+//!
+//! Between transitions the head is on the first bit of a block. A state of the
+//! original machine reads the block from left to right, remembering the bits in
+//! its state. After the last bit it knows the symbol, writes the new block from
+//! right to left and walks to the first bit of the block it moves to. Writing
+//! and walking only depend on what is written, the movement and the next
+//! state, so these states are shared between transitions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use super::Bit;
+use super::inspect::Inspect;
+use super::modifiable::tape::{ConcreteTape, TapeBuilder};
+use super::movement::Movement;
+use super::transition::{HashTransitions, Lookup, Next, Transitions, TransitionKey, TransitionValue};
+
+/// A machine rewritten to use two symbols.
+#[derive(Clone, Debug)]
+pub struct Binary<Q, S> {
+    /// The transitions of the two-symbol machine.
+    pub transitions: Transitions<usize, Bit>,
+    /// The number of bits in a block.
+    pub width: usize,
+    /// The symbols of the original machine, in the order of their codes.
+    pub alphabet: Vec<S>,
+    /// Where the states came from.
+    pub report: Report,
+    states: Vec<Q>,
+}
+
+impl <Q, S> Binary<Q, S> where Q: Eq, S: Clone + PartialEq {
+    /// The state of the two-symbol machine that stands for `q`, reading a
+    /// block from its first bit.
+    pub fn state(&self, q: &Q) -> Option<usize> {
+        self.states.iter().position(|state| state == q)
+    }
+
+    /// Encode `tape` into blocks of bits. Cell `i` becomes the cells `i *
+    /// width` up to `(i + 1) * width`, so the head is on the first bit of its
+    /// block.
+    ///
+    /// # Panics
+    /// When the tape holds a symbol outside of the alphabet.
+    pub fn encode<T: Inspect<S>>(&self, tape: &T) -> ConcreteTape<Bit> {
+        let start = tape.extent().start;
+        let mut bits = Vec::new();
+        for symbol in tape.to_vec() {
+            let code = self.alphabet.iter().position(|known| *known == symbol).expect("a symbol of the alphabet");
+            bits.extend((0..self.width).rev().map(|bit| code >> bit & 1 == 1));
+        }
+        let width = self.width as i64;
+        let head = ((tape.head_position() - start) * width) as usize;
+        TapeBuilder::with_blank(false)
+            .with_left_tape(bits[..head].iter().rev().cloned().collect())
+            .with_current(bits[head])
+            .with_right_tape(bits[head + 1..].to_vec())
+            .with_head_position(tape.head_position() * width)
+            .build()
+    }
+
+    /// Decode a tape of bits into the cells they encode, the inverse of
+    /// `encode`. The head is on the block it is in.
+    ///
+    /// Returns `None` when a block holds a number that is not the code of a
+    /// symbol.
+    pub fn decode(&self, tape: &ConcreteTape<Bit>) -> Option<ConcreteTape<S>> {
+        let width = self.width as i64;
+        let extent = tape.extent();
+        let first = extent.start.div_euclid(width);
+        let last = (extent.end - 1).div_euclid(width);
+        let bits = tape.to_vec();
+        let bit = |position: i64| position >= extent.start && position < extent.end && bits[(position - extent.start) as usize];
+
+        let mut cells = Vec::new();
+        for block in first..=last {
+            let code = (0..width).fold(0, |code, offset| 2 * code + bit(block * width + offset) as usize);
+            cells.push(self.alphabet.get(code)?.clone());
+        }
+        let head = tape.head_position().div_euclid(width);
+        let index = (head - first) as usize;
+        Some(TapeBuilder::with_blank(self.alphabet[0].clone())
+            .with_left_tape(cells[..index].iter().rev().cloned().collect())
+            .with_current(cells[index].clone())
+            .with_right_tape(cells[index + 1..].to_vec())
+            .with_head_position(head)
+            .build())
+    }
+}
+
+/// How many states a rewrite took, and what for.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Report {
+    /// The number of states of the original machine.
+    pub states: usize,
+    /// The number of symbols of the original machine.
+    pub symbols: usize,
+    /// The number of bits in a block.
+    pub width: usize,
+    /// The states that read a block, including those of the original states.
+    pub reading: usize,
+    /// The states that write a block and walk to the next one.
+    pub writing: usize,
+}
+
+impl Report {
+    /// The number of states of the two-symbol machine.
+    pub fn total(&self) -> usize {
+        self.reading + self.writing
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} states over {} symbols became {} states over 2 symbols: {} reading and {} writing {} bit(s) per symbol",
+            self.states, self.symbols, self.total(), self.reading, self.writing, self.width)
+    }
+}
+
+/// Rewrite `transitions` into an equivalent two-symbol machine.
+///
+/// The first symbol of the alphabet is the blank. A key with several
+/// transitions is read as described on `Transitions`. Where the original machine has no transition, neither has the rewritten
+/// machine, which then gets stuck after reading the block.
+pub fn to_binary<Q, S>(transitions: &Transitions<Q, S>, alphabet: &[S]) -> Binary<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    let mut width = 1;
+    while 1 << width < alphabet.len() {
+        width += 1;
+    }
+
+    let mut states: Vec<Q> = Vec::new();
+    let original: Vec<_> = transitions.iter().collect();
+    for &(key, value) in original.iter().rev() {
+        for state in Some(&key.state).into_iter().chain(match value.next { Next::State(ref next) => Some(next), _ => None }) {
+            if !states.contains(state) {
+                states.push(state.clone());
+            }
+        }
+    }
+
+    let table = HashTransitions::from(transitions);
+    let mut assembler = Assembler { width, rows: (0..states.len()).map(|_| [None, None]).collect(), writers: HashMap::new(), walks: HashMap::new() };
+    let number = |q: &Q| states.iter().position(|state| state == q).expect("a known state");
+
+    for (index, q) in states.iter().enumerate() {
+        // The transitions by code, and the tree of states reading the bits of
+        // a block, numbered in reading order.
+        let defined: Vec<Option<TransitionValue<usize, usize>>> = alphabet.iter().map(|symbol| {
+            table.lookup(&TransitionKey::new(q.clone(), symbol.clone())).map(|value| TransitionValue {
+                next: value.next.clone().map(|next| number(&next)),
+                symbol: alphabet.iter().position(|known| *known == value.symbol).expect("a symbol of the alphabet"),
+                movement: value.movement,
+            })
+        }).collect();
+        let mut level = vec![(index, 0)];
+        for depth in 0..width {
+            let mut following = Vec::new();
+            for (state, prefix) in level {
+                for bit in [false, true] {
+                    let prefix = 2 * prefix + bit as usize;
+                    let remaining = width - depth - 1;
+                    if depth + 1 == width {
+                        if let Some(Some(value)) = defined.get(prefix) {
+                            let value = assembler.write(value.symbol, value.movement, value.next, width - 1);
+                            assembler.rows[state][bit as usize] = Some(value);
+                        }
+                    } else if defined.iter().enumerate().any(|(code, value)| value.is_some() && code >> remaining == prefix) {
+                        let child = assembler.fresh();
+                        assembler.rows[state][bit as usize] = Some(TransitionValue::new(child, bit, Movement::Right));
+                        following.push((child, prefix));
+                    }
+                }
+            }
+            level = following;
+        }
+    }
+
+    let writing = assembler.writers.len() + assembler.walks.len();
+    let report = Report {
+        states: states.len(),
+        symbols: alphabet.len(),
+        width,
+        reading: assembler.rows.len() - writing,
+        writing,
+    };
+
+    let mut result = Transitions::new();
+    for (state, row) in assembler.rows.into_iter().enumerate().rev() {
+        for (read, value) in row.iter().enumerate().rev() {
+            if let Some(ref value) = *value {
+                result = result.insert(TransitionKey::new(state, read == 1), value.clone());
+            }
+        }
+    }
+    Binary { transitions: result, width, alphabet: alphabet.to_vec(), report, states }
+}
+
+struct Assembler {
+    width: usize,
+    rows: Vec<[Option<TransitionValue<usize, Bit>>; 2]>,
+    /// The states writing bit `offset` of a code, by code, movement, next
+    /// state and offset.
+    writers: HashMap<(usize, Movement, Next<usize>, usize), usize>,
+    /// The states walking a number of cells, by direction, next state and
+    /// number of cells.
+    walks: HashMap<(Movement, Next<usize>, usize), usize>,
+}
+
+impl Assembler {
+    fn fresh(&mut self) -> usize {
+        self.rows.push([None, None]);
+        self.rows.len() - 1
+    }
+
+    /// The transition writing bit `offset` of `code`, going on to write the
+    /// bits left of it, then moving and continuing in `next`.
+    fn write(&mut self, code: usize, movement: Movement, next: Next<usize>, offset: usize) -> TransitionValue<usize, Bit> {
+        let symbol = code >> (self.width - 1 - offset) & 1 == 1;
+        if offset > 0 {
+            let key = (code, movement, next, offset - 1);
+            let state = match self.writers.get(&key) {
+                Some(&state) => state,
+
+                None => {
+                    let state = self.fresh();
+                    let value = self.write(code, movement, next, offset - 1);
+                    self.rows[state] = [Some(value.clone()), Some(value)];
+                    self.writers.insert(key, state);
+                    state
+                }
+            };
+            return TransitionValue::new(state, symbol, Movement::Left);
+        }
+
+        // The head leaves the first bit of the block for the first bit of the
+        // block it moves to.
+        match movement {
+            Movement::Left | Movement::Right => TransitionValue { next: self.walk(movement, next, self.width - 1), symbol, movement },
+
+            Movement::Stay => TransitionValue { next: self.walk(Movement::Left, next, 1), symbol, movement: Movement::Right },
+        }
+    }
+
+    /// A state moving `count` cells in `direction` before continuing in
+    /// `next`.
+    fn walk(&mut self, direction: Movement, next: Next<usize>, count: usize) -> Next<usize> {
+        if count == 0 {
+            return next;
+        }
+        if let Some(&state) = self.walks.get(&(direction, next, count)) {
+            return Next::State(state);
+        }
+        let state = self.fresh();
+        let following = self.walk(direction, next, count - 1);
+        self.rows[state] = [
+            Some(TransitionValue { next: following, symbol: false, movement: direction }),
+            Some(TransitionValue { next: following, symbol: true, movement: direction }),
+        ];
+        self.walks.insert((direction, next, count), state);
+        Next::State(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use proptest::prelude::*;
+    use super::super::modifiable::turing::Machine;
+    use super::super::run::RunOutcome;
+    use super::super::strategies::{movement_or_stay, next, transitions};
+
+    /// Run the original machine from state 0 and its binary rewrite, and check
+    /// that they end in the same way, on the same tape.
+    fn compare(transitions: Transitions<usize, u8>, alphabet: &[u8], tape: ConcreteTape<u8>, limit: u64) -> Binary<usize, u8> {
+        let binary = to_binary(&transitions, alphabet);
+        let mut original = Machine::new(0, tape.clone(), Rc::new(HashTransitions::from(&transitions)));
+        let start = binary.state(&0).unwrap_or(binary.report.total());
+        let mut rewritten = Machine::new(start, binary.encode(&tape), Rc::new(HashTransitions::from(&binary.transitions)));
+
+        let expected = original.run(limit);
+        let found = rewritten.run(limit * (binary.width as u64 + 1) * 3);
+        match (expected, found) {
+            (RunOutcome::Halted { state: expected, .. }, RunOutcome::Halted { state: found, .. }) =>
+                assert_eq!(found.map(|_| ()), expected.map(|_| ())),
+
+            (RunOutcome::UndefinedTransition { .. }, RunOutcome::UndefinedTransition { .. }) => (),
+
+            (RunOutcome::StepLimitReached, RunOutcome::StepLimitReached) => return binary,
+
+            (expected, found) => panic!("expected {:?}, found {:?}", expected, found),
+        }
+
+        let decoded = binary.decode(rewritten.tape()).expect("blocks of symbols");
+        let cells = |tape: &ConcreteTape<u8>| {
+            let start = tape.extent().start;
+            let cells: Vec<(i64, u8)> = tape.to_vec().into_iter().enumerate()
+                .filter(|&(_, symbol)| symbol != 0)
+                .map(|(index, symbol)| (start + index as i64, symbol))
+                .collect();
+            (cells, tape.head_position())
+        };
+        assert_eq!(cells(&decoded), cells(original.tape()));
+        binary
+    }
+
+    #[test]
+    fn should_run_a_three_symbol_busy_beaver() {
+        let transitions = Transitions::from_standard("1RB2LB1RZ_2LA2RB1LB").expect("a valid machine");
+
+        let binary = compare(transitions, &[0, 1, 2], ConcreteTape::empty(0), 100);
+
+        assert_eq!(binary.width, 2);
+        assert_eq!(binary.report.to_string(), "2 states over 3 symbols became 16 states over 2 symbols: 6 reading and 10 writing 2 bit(s) per symbol");
+    }
+
+    #[test]
+    fn should_encode_and_decode_tapes() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new('a', 'x'), TransitionValue::new('b', 'z', Movement::Stay));
+        let binary = to_binary(&transitions, &['_', 'x', 'y', 'z', 'w']);
+        let tape = TapeBuilder::with_blank('_').with_left_tape(vec!['x']).with_current('w').with_right_tape(vec!['_', 'y']).build();
+
+        let encoded = binary.encode(&tape);
+
+        assert_eq!(binary.width, 3);
+        assert_eq!(encoded.head_position(), 0);
+        assert_eq!(encoded.to_vec(), vec![false, false, true, true, false, false, false, false, false, false, true, false]);
+        let decoded = binary.decode(&encoded).expect("blocks of symbols");
+        assert_eq!(decoded.to_vec(), tape.to_vec());
+        assert_eq!(decoded.extent(), tape.extent());
+        assert!(binary.decode(&TapeBuilder::with_blank(false).with_current(true).with_right_tape(vec![true, true]).build()).is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn binary_machines_should_behave_like_the_original(
+                (symbols, transitions) in (1u8..6).prop_flat_map(|symbols| (Just(symbols), transitions(0usize..4, 0..symbols, next(0usize..4), movement_or_stay(), 0..20))),
+                left in prop::collection::vec(0u8..5, 0..4),
+                current in 0u8..5,
+                right in prop::collection::vec(0u8..5, 0..4)) {
+            let symbol = |symbol: u8| symbol % symbols;
+            let tape = TapeBuilder::with_blank(0)
+                .with_left_tape(left.into_iter().map(symbol).collect())
+                .with_current(symbol(current))
+                .with_right_tape(right.into_iter().map(symbol).collect())
+                .build();
+            let alphabet: Vec<u8> = (0..symbols).collect();
+
+            compare(transitions, &alphabet, tape, 50);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Inspect looks at a tape without moving its read/write head.
+//!
+//! Cells are numbered relative to the position the read/write head started
+//! in, which is cell 0. Cells to the left have negative numbers.
+
+use std::ops::Range;
+
+/// Contract for tapes that can report on their contents.
+pub trait Inspect<S> {
+    /// The cell the read/write head is scanning.
+    fn head_position(&self) -> i64;
+
+    /// The cells that have been visited or were initially loaded.
+    ///
+    /// Every cell outside of the extent is blank.
+    fn extent(&self) -> Range<i64>;
+
+    /// The symbols in the cells of the extent, from left to right.
+    fn to_vec(&self) -> Vec<S>;
+
+    /// The number of cells that do not contain the blank symbol.
+    fn non_blank_count(&self) -> usize;
+}
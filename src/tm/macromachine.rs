@@ -0,0 +1,301 @@
+//! Macromachine simulates a two-symbol machine on blocks of cells.
+//!
+//! The tape is cut into blocks of `k` cells, each stored as the bits of a
+//! `u64`, with the leftmost cell in the lowest bit. A macro step runs the
+//! machine from the moment its head enters a block, at the left or the right
+//! edge, until it leaves the block again. Macro steps are computed the first
+//! time they are needed and cached by state, edge and block.
+//!
+//! The blocks are kept on a `RunLengthTape`. When a macro step leaves a block
+//! in the state it entered it, at the opposite edge, the machine does the
+//! same to every neighbouring block that holds the same symbols, so the
+//! whole run of those blocks is taken in one go. That lets busy beavers run
+//! for trillions of steps.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use super::Bit;
+use super::inspect::Inspect;
+use super::modifiable::runlength::RunLengthTape;
+use super::modifiable::tape::Tape;
+use super::movement::Movement;
+use super::run::RunOutcome;
+use super::transition::{HashTransitions, Next, Transitions};
+use super::unmodifiable::tape::{Tape as UnmodifiableTape, TapeBuilder};
+use super::unmodifiable::turing::Machine;
+
+/// The edge of a block the head enters it at.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Edge {
+    Left,
+    Right,
+}
+
+/// What the machine does in a block, from entering it until it leaves.
+#[derive(Clone, Debug)]
+enum MacroStep<Q> {
+    /// The machine leaves the block in `state`, moving to the neighbouring
+    /// block on the side of `movement`.
+    Leave { state: Q, block: u64, movement: Movement, steps: u64 },
+    /// The machine halts.
+    Halt { state: Next<Q>, block: u64, steps: u64 },
+    /// There is no transition for `state` and `symbol`.
+    Undefined { state: Q, symbol: Bit, block: u64, steps: u64 },
+    /// The machine never leaves the block. After `start` steps it goes round
+    /// a cycle of `length` steps.
+    Loop { start: u64, length: u64 },
+    /// The machine is still in the block after the steps it was allowed.
+    Limit { block: u64 },
+}
+
+/// A two-symbol machine that runs on blocks of cells.
+pub struct MacroMachine<Q> where Q: Clone + Eq + Hash {
+    transitions: Rc<HashTransitions<Q, Bit>>,
+    size: usize,
+    cache: HashMap<(Q, Edge, u64), MacroStep<Q>>,
+}
+
+/// How a run of a `MacroMachine` ended.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MacroRun<Q> {
+    /// Why the run ended, as the plain machine would tell.
+    pub outcome: RunOutcome<Q, Bit>,
+    /// The number of cells holding a 1 when the run ended.
+    pub ones: u64,
+}
+
+impl <Q> MacroMachine<Q> where Q: Clone + Eq + Hash {
+    /// Create a machine that runs `transitions` on blocks of `size` cells.
+    ///
+    /// # Panics
+    ///
+    /// When `size` is not between 1 and 64.
+    pub fn new(transitions: &Transitions<Q, Bit>, size: usize) -> MacroMachine<Q> {
+        assert!((1..=64).contains(&size), "blocks hold between 1 and 64 cells");
+        MacroMachine {
+            transitions: Rc::new(HashTransitions::from(transitions)),
+            size,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The number of cells in a block.
+    pub fn block_size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of macro steps computed so far.
+    pub fn cached(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Run the machine from `start` on an empty tape for at most `limit`
+    /// steps of the original machine.
+    pub fn run(&mut self, start: Q, limit: u64) -> MacroRun<Q> {
+        let mut tape = RunLengthTape::empty(0u64);
+        let mut state = start;
+        let mut edge = Edge::Left;
+        let mut steps = 0;
+        let outcome = loop {
+            if steps == limit {
+                break RunOutcome::StepLimitReached;
+            }
+            let remaining = limit - steps;
+            let block = tape.read();
+            match self.step(&state, edge, block) {
+                MacroStep::Leave { state: next, block: written, movement, steps: taken } if taken <= remaining => {
+                    let entered = if movement == Movement::Left { Edge::Right } else { Edge::Left };
+                    let count = if next == state && entered == edge { tape.run_length(movement, remaining / taken) } else { 1 };
+                    tape.skip(written, movement, count);
+                    steps += count * taken;
+                    state = next;
+                    edge = entered;
+                }
+
+                MacroStep::Halt { state, block: written, steps: taken } if taken <= remaining => {
+                    tape.write(written);
+                    break RunOutcome::Halted { steps: steps + taken, state };
+                }
+
+                MacroStep::Undefined { state, symbol, block: written, steps: taken } if taken < remaining => {
+                    tape.write(written);
+                    break RunOutcome::UndefinedTransition { state, symbol };
+                }
+
+                step => {
+                    // The limit is reached inside this block, after going
+                    // round its cycle as often as fits when it loops.
+                    let within = match step {
+                        MacroStep::Loop { start, length } if remaining > start => start + (remaining - start) % length,
+
+                        _ => remaining,
+                    };
+                    if let MacroStep::Limit { block: written } = self.simulate(&state, edge, block, Some(within)) {
+                        tape.write(written);
+                    }
+                    break RunOutcome::StepLimitReached;
+                }
+            }
+        };
+        let ones = tape.iter_runs().map(|(block, length)| u64::from(block.count_ones()) * length).sum();
+        MacroRun { outcome, ones }
+    }
+
+    /// The macro step for entering `block` at `edge` in `state`, from the
+    /// cache when it was computed before.
+    fn step(&mut self, state: &Q, edge: Edge, block: u64) -> MacroStep<Q> {
+        let key = (state.clone(), edge, block);
+        if let Some(step) = self.cache.get(&key) {
+            return step.clone();
+        }
+        let step = self.simulate(state, edge, block, None);
+        self.cache.insert(key, step.clone());
+        step
+    }
+
+    /// Run the machine in `block` from `edge` in `state` until it leaves the
+    /// block, or for at most `limit` steps. Without a limit, a machine that
+    /// comes back to where it was in the block loops, from the step it was
+    /// there first.
+    fn simulate(&self, state: &Q, edge: Edge, block: u64, limit: Option<u64>) -> MacroStep<Q> {
+        let offset = match edge {
+            Edge::Left => 0,
+
+            Edge::Right => self.size - 1,
+        };
+        let cells: Vec<Bit> = (0..self.size).map(|i| block >> i & 1 == 1).collect();
+        let tape = TapeBuilder::with_blank(false)
+            .with_left_tape(cells[..offset].iter().rev().cloned().collect())
+            .with_current(cells[offset])
+            .with_right_tape(cells[offset + 1..].to_vec())
+            .build();
+        let mut machine = Machine::new(state.clone(), tape, self.transitions.clone());
+        let inside = -(offset as i64)..(self.size - offset) as i64;
+        let contents = |machine: &Machine<Q, Bit, Rc<HashTransitions<Q, Bit>>>| {
+            let tape = machine.tape();
+            let extent = tape.extent();
+            let cells = tape.to_vec();
+            inside.clone().filter(|position| extent.contains(position) && cells[(position - extent.start) as usize])
+                .fold(0u64, |block, position| block | 1 << (position - inside.start))
+        };
+
+        let mut seen = HashMap::new();
+        let mut steps = 0;
+        loop {
+            if limit == Some(steps) {
+                return MacroStep::Limit { block: contents(&machine) };
+            }
+            if limit.is_none() {
+                let configuration = (machine.state().clone(), machine.tape().head_position(), contents(&machine));
+                if let Some(&start) = seen.get(&configuration) {
+                    return MacroStep::Loop { start, length: steps - start };
+                }
+                seen.insert(configuration, steps);
+            }
+            let next = match machine.step() {
+                Some(next) => next,
+
+                None => {
+                    let state = match *machine.state() {
+                        Next::State(ref q) => q.clone(),
+
+                        Next::Halt | Next::Error => unreachable!("a machine in a block is in a state"),
+                    };
+                    return MacroStep::Undefined { state, symbol: machine.tape().read(), block: contents(&machine), steps };
+                }
+            };
+            machine = next;
+            steps += 1;
+            match machine.state().clone() {
+                Next::State(q) => {
+                    let head = machine.tape().head_position();
+                    if !inside.contains(&head) {
+                        let movement = if head < inside.start { Movement::Left } else { Movement::Right };
+                        return MacroStep::Leave { state: q, block: contents(&machine), movement, steps };
+                    }
+                }
+
+                state => return MacroStep::Halt { state, block: contents(&machine), steps },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use super::super::strategies::{movement, next, transitions};
+    use super::super::transition::{TransitionKey, TransitionValue};
+
+    /// The two-symbol machine in standard notation `source`.
+    fn bits(source: &str) -> Transitions<usize, Bit> {
+        let transitions: Vec<_> = Transitions::from_standard(source).expect("a valid machine").iter()
+            .map(|(key, value)| (TransitionKey::new(key.state, key.symbol == 1), TransitionValue { next: value.next, symbol: value.symbol == 1, movement: value.movement }))
+            .collect();
+        transitions.into_iter().rev().fold(Transitions::new(), |transitions, (key, value)| transitions.insert(key, value))
+    }
+
+    /// The outcome and the number of ones of the plain machine.
+    fn plain(transitions: &Transitions<usize, Bit>, limit: u64) -> MacroRun<usize> {
+        let machine = Machine::new(0, TapeBuilder::with_blank(false).build(), Rc::new(HashTransitions::from(transitions)));
+        let (machine, outcome) = machine.run(limit);
+        MacroRun { outcome, ones: machine.tape().non_blank_count() as u64 }
+    }
+
+    #[test]
+    fn should_run_like_the_plain_machine() {
+        let transitions = bits("1RB1LB_1LA0LC_1RZ1LD_1RD0RA");
+
+        for size in 1..=8 {
+            let mut machine = MacroMachine::new(&transitions, size);
+            assert_eq!(machine.block_size(), size);
+            assert_eq!(machine.run(0, 1_000), MacroRun { outcome: RunOutcome::Halted { steps: 107, state: Next::Halt }, ones: 13 });
+            for limit in 0..107 {
+                assert_eq!(machine.run(0, limit), plain(&transitions, limit));
+            }
+        }
+    }
+
+    #[test]
+    fn should_run_a_busy_beaver_quickly() {
+        let mut machine = MacroMachine::new(&bits("1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA"), 6);
+
+        assert_eq!(machine.run(0, 100_000_000), MacroRun { outcome: RunOutcome::Halted { steps: 47_176_870, state: Next::Halt }, ones: 4098 });
+        assert!(machine.cached() < 1_000);
+    }
+
+    #[test]
+    fn should_count_the_steps_of_a_machine_that_runs_forever() {
+        let transitions = bits("1RA---");
+        let mut machine = MacroMachine::new(&transitions, 4);
+
+        assert_eq!(machine.run(0, 1_000_000_000_003), MacroRun { outcome: RunOutcome::StepLimitReached, ones: 1_000_000_000_003 });
+        assert_eq!(machine.cached(), 1);
+    }
+
+    #[test]
+    fn should_skip_around_loops_inside_a_block() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new(0, false), TransitionValue::new(1, true, Movement::Right))
+            .insert(TransitionKey::new(0, true), TransitionValue::new(1, true, Movement::Right))
+            .insert(TransitionKey::new(1, false), TransitionValue::new(0, false, Movement::Left));
+        let mut machine = MacroMachine::new(&transitions, 4);
+
+        for limit in 0..20 {
+            assert_eq!(machine.run(0, limit), plain(&transitions, limit));
+        }
+        assert_eq!(machine.run(0, 1_000_000_000_000), MacroRun { outcome: RunOutcome::StepLimitReached, ones: 1 });
+    }
+
+    proptest! {
+        #[test]
+        fn macro_machines_should_behave_like_plain_machines(
+                transitions in transitions(0usize..4, any::<bool>(), next(0usize..4), movement(), 0..8),
+                size in 1usize..7,
+                limit in 0u64..300) {
+            prop_assert_eq!(MacroMachine::new(&transitions, size).run(0, limit), plain(&transitions, limit));
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Minimize merges the states of a machine that behave the same.
+//!
+//! Like the minimization of a DFA, it starts with all states in one class and
+//! refines the classes until they are stable. In every round, the states of a
+//! class stay together when, for every symbol, either both have no transition,
+//! or both write the same symbol, move the same way and continue in the same
+//! class. HALT and ERROR are classes of their own, so a state that halts is
+//! never merged with one that stops with an error. After the first round, only
+//! the classes of states that continue in a state that changed class are
+//! looked at again.
+//!
+//! Every class is merged into its state that was mentioned first. The merges
+//! are logged, and the log can be checked against the original machine: it
+//! proves the merged machine equivalent when every merged state has the same
+//! transitions as the state it was merged into, after renaming both rows.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use super::movement::Movement;
+use super::transition::{Next, Transitions, TransitionValue};
+
+/// The transitions of a state, by the symbol read.
+type Row<'a, Q, S> = Vec<(&'a S, &'a TransitionValue<Q, S>)>;
+
+/// What tells the states in a class apart: for every symbol, what the
+/// transition writes, how it moves and the class it continues in.
+type Signature<'a, S> = Vec<Option<(&'a S, Movement, Next<usize>)>>;
+
+/// A machine with its equivalent states merged.
+#[derive(Clone, Debug)]
+pub struct Minimization<Q, S> where Q: Clone + Eq + Hash, S: Clone {
+    /// The transitions of the states that were kept, in the order of the
+    /// original.
+    pub transitions: Transitions<Q, S>,
+    /// The merged states, in the order they were first mentioned.
+    pub merges: Vec<Merge<Q>>,
+    /// The number of rounds it took the classes to become stable.
+    pub rounds: usize,
+    representatives: HashMap<Q, Q>,
+}
+
+/// A state that was merged into an equivalent state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Merge<Q> {
+    /// The state that is gone.
+    pub state: Q,
+    /// The state that replaces it.
+    pub into: Q,
+}
+
+impl <Q, S> Minimization<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    /// The state that replaces `q`, which is `q` itself when it was kept.
+    pub fn representative(&self, q: &Q) -> Q {
+        self.representatives.get(q).unwrap_or(q).clone()
+    }
+
+    /// Check the merges against the `original` transitions: whether every
+    /// merged state was merged into a state that was kept, with the same
+    /// transitions once merged states are replaced.
+    pub fn verify(&self, original: &Transitions<Q, S>) -> bool {
+        let rows = rows(original);
+        let renamed = |state: &Q| -> HashMap<&S, (&S, Movement, Next<Q>)> {
+            rows.get(state).map(|row| row.iter().map(|&(symbol, value)| {
+                (symbol, (&value.symbol, value.movement, value.next.clone().map(|next| self.representative(&next))))
+            }).collect()).unwrap_or_default()
+        };
+        self.merges.iter().all(|merge| {
+            !self.representatives.contains_key(&merge.into) && renamed(&merge.state) == renamed(&merge.into)
+        })
+    }
+}
+
+/// Merge the equivalent states of `transitions`.
+///
+/// A key with several transitions is read as described on `Transitions`.
+pub fn minimize<Q, S>(transitions: &Transitions<Q, S>) -> Minimization<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    let original: Vec<_> = transitions.iter().collect();
+    let mut states: Vec<&Q> = Vec::new();
+    let mut numbers: HashMap<&Q, usize> = HashMap::new();
+    let mut symbols: Vec<&S> = Vec::new();
+    for &(key, value) in original.iter().rev() {
+        for state in Some(&key.state).into_iter().chain(match value.next { Next::State(ref next) => Some(next), _ => None }) {
+            if !numbers.contains_key(state) {
+                numbers.insert(state, states.len());
+                states.push(state);
+            }
+        }
+        if !symbols.contains(&&key.symbol) {
+            symbols.push(&key.symbol);
+        }
+    }
+
+    let rows = rows(transitions);
+    let table: Vec<Vec<Option<&TransitionValue<Q, S>>>> = states.iter().map(|state| {
+        symbols.iter().map(|symbol| {
+            rows.get(*state).and_then(|row| row.iter().find(|&&(known, _)| known == *symbol)).map(|&(_, value)| value)
+        }).collect()
+    }).collect();
+
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); states.len()];
+    for (state, row) in table.iter().enumerate() {
+        for value in row.iter().flatten() {
+            if let Next::State(ref next) = value.next {
+                predecessors[numbers[next]].push(state);
+            }
+        }
+    }
+
+    // Only the classes with a state that continues in a state that changed
+    // class can split in the next round.
+    let mut classes = vec![0; states.len()];
+    let mut members: Vec<Vec<usize>> = vec![(0..states.len()).collect()];
+    let mut pending = vec![0];
+    let mut rounds = 0;
+    while !pending.is_empty() {
+        rounds += 1;
+        pending.sort_unstable();
+        pending.dedup();
+        let mut moved = Vec::new();
+        for class in pending {
+            let mut parts: Vec<Vec<usize>> = Vec::new();
+            let mut signatures: HashMap<Signature<S>, usize> = HashMap::new();
+            for &state in &members[class] {
+                let signature = table[state].iter().map(|value| {
+                    value.map(|value| (&value.symbol, value.movement, value.next.clone().map(|next| classes[numbers[&next]])))
+                }).collect();
+                let next = parts.len();
+                let part = *signatures.entry(signature).or_insert(next);
+                if part == parts.len() {
+                    parts.push(Vec::new());
+                }
+                parts[part].push(state);
+            }
+            let mut parts = parts.into_iter();
+            members[class] = parts.next().unwrap_or_default();
+            for part in parts {
+                for &state in &part {
+                    classes[state] = members.len();
+                    moved.push(state);
+                }
+                members.push(part);
+            }
+        }
+        pending = moved.iter().flat_map(|&state| predecessors[state].iter().map(|&predecessor| classes[predecessor])).collect();
+    }
+
+    let mut kept: Vec<Option<&Q>> = vec![None; members.len()];
+    let mut merges = Vec::new();
+    let mut representatives = HashMap::new();
+    for (state, &class) in states.iter().zip(&classes) {
+        match kept[class] {
+            Some(into) => {
+                merges.push(Merge { state: (*state).clone(), into: into.clone() });
+                representatives.insert((*state).clone(), into.clone());
+            }
+
+            None => kept[class] = Some(state),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let effective: Vec<_> = original.iter().filter(|&&(key, _)| seen.insert((&key.state, &key.symbol))).collect();
+    let mut result = Transitions::new();
+    for &&(key, value) in effective.iter().rev() {
+        if !representatives.contains_key(&key.state) {
+            let value = TransitionValue {
+                next: value.next.clone().map(|next| representatives.get(&next).unwrap_or(&next).clone()),
+                symbol: value.symbol.clone(),
+                movement: value.movement,
+            };
+            result = result.insert(key.clone(), value);
+        }
+    }
+
+    Minimization { transitions: result, merges, rounds, representatives }
+}
+
+/// The transitions a machine takes, by state.
+fn rows<Q, S>(transitions: &Transitions<Q, S>) -> HashMap<&Q, Row<'_, Q, S>> where Q: Clone + Eq + Hash, S: Clone + Eq {
+    let mut rows: HashMap<&Q, Row<Q, S>> = HashMap::new();
+    for (key, value) in transitions.iter() {
+        let row = rows.entry(&key.state).or_default();
+        if !row.iter().any(|&(symbol, _)| *symbol == key.symbol) {
+            row.push((&key.symbol, value));
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use proptest::prelude::*;
+    use tmd;
+    use super::super::inspect::Inspect;
+    use super::super::modifiable::tape::ConcreteTape;
+    use super::super::modifiable::turing::Machine;
+    use super::super::run::RunOutcome;
+    use super::super::strategies::{movement, next, transitions};
+    use super::super::transition::{HashTransitions, TransitionKey};
+
+    #[test]
+    fn should_merge_states_that_behave_the_same() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new('a', '_'), TransitionValue::new('b', '1', Movement::Right))
+            .insert(TransitionKey::new('a', '1'), TransitionValue::new('c', '1', Movement::Right))
+            .insert(TransitionKey::new('b', '_'), TransitionValue::new('d', '1', Movement::Left))
+            .insert(TransitionKey::new('c', '_'), TransitionValue::new('e', '1', Movement::Left))
+            .insert(TransitionKey::new('d', '_'), TransitionValue::halt('_', Movement::Left))
+            .insert(TransitionKey::new('e', '_'), TransitionValue::halt('_', Movement::Left))
+            .insert(TransitionKey::new('f', '_'), TransitionValue::error('_', Movement::Left))
+            .insert(TransitionKey::new('g', '_'), TransitionValue::new('f', '1', Movement::Left));
+
+        let minimization = minimize(&transitions);
+
+        assert_eq!(minimization.merges, vec![Merge { state: 'c', into: 'b' }, Merge { state: 'e', into: 'd' }]);
+        assert_eq!(minimization.representative(&'e'), 'd');
+        assert_eq!(minimization.representative(&'f'), 'f');
+        assert_eq!(minimization.rounds, 2);
+        assert!(minimization.verify(&transitions));
+        assert_eq!(minimization.transitions.iter().map(|(key, value)| (key.clone(), value.clone())).collect::<Vec<_>>(), vec![
+            (TransitionKey::new('g', '_'), TransitionValue::new('f', '1', Movement::Left)),
+            (TransitionKey::new('f', '_'), TransitionValue::error('_', Movement::Left)),
+            (TransitionKey::new('d', '_'), TransitionValue::halt('_', Movement::Left)),
+            (TransitionKey::new('b', '_'), TransitionValue::new('d', '1', Movement::Left)),
+            (TransitionKey::new('a', '1'), TransitionValue::new('b', '1', Movement::Right)),
+            (TransitionKey::new('a', '_'), TransitionValue::new('b', '1', Movement::Right)),
+        ]);
+    }
+
+    #[test]
+    fn should_reject_a_wrong_log() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new(0, '_'), TransitionValue::new(1, '1', Movement::Right))
+            .insert(TransitionKey::new(1, '_'), TransitionValue::halt('1', Movement::Right))
+            .insert(TransitionKey::new(2, '_'), TransitionValue::error('1', Movement::Right));
+        let mut minimization = minimize(&transitions);
+        assert!(minimization.merges.is_empty());
+
+        minimization.merges.push(Merge { state: 2, into: 1 });
+        minimization.representatives.insert(2, 1);
+
+        assert!(!minimization.verify(&transitions));
+    }
+
+    #[test]
+    fn should_shrink_compiled_programs() {
+        let program = tmd::parser::parse("func main(x, y)\n    write x 1\n    if x 1 goto same\n    write y 1\n    goto done\nsame:\n    write y 1\ndone:\n    move y R\nend\n").expect("a valid program");
+        let compilation = tmd::compile::compile(&program).expect("a program without recursion");
+
+        let minimization = minimize(&compilation.transitions);
+        let states = compilation.states - minimization.merges.len();
+
+        assert!(minimization.verify(&compilation.transitions));
+        assert!(states < compilation.states);
+        assert_eq!(minimization.transitions.len(), 2 * states);
+        let run = |transitions: &Transitions<usize, bool>| {
+            let mut machine = Machine::new(0, ConcreteTape::empty(false), Rc::new(HashTransitions::from(transitions)));
+            let outcome = machine.run(100_000);
+            (outcome, machine.tape().to_vec())
+        };
+        let (outcome, tape) = run(&minimization.transitions);
+        assert!(matches!(outcome, RunOutcome::Halted { state: Next::Halt, .. }));
+        assert_eq!((outcome, tape), run(&compilation.transitions));
+    }
+
+    proptest! {
+        #[test]
+        fn minimized_machines_should_behave_like_the_original(
+                transitions in transitions(0u8..4, any::<bool>(), prop_oneof![4 => next(0u8..4), 1 => Just(Next::Error)], movement(), 0..24)) {
+            let minimization = minimize(&transitions);
+            prop_assert!(minimization.verify(&transitions));
+
+            let start = minimization.representative(&0);
+            let mut original = Machine::new(0, ConcreteTape::empty(false), Rc::new(HashTransitions::from(&transitions)));
+            let mut merged = Machine::new(start, ConcreteTape::empty(false), Rc::new(HashTransitions::from(&minimization.transitions)));
+            for _ in 0..50 {
+                let state = original.step().map(|next| next.map(|q| minimization.representative(&q)));
+                prop_assert_eq!(state, merged.step());
+                prop_assert_eq!(original.tape().to_vec(), merged.tape().to_vec());
+            }
+        }
+    }
+}
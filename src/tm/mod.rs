@@ -16,6 +16,23 @@
 //! The tape alphabet symbols correspond to the symbols that can be written on
 //! the Turing machine's infinite tape.
 
+pub mod binary;
+pub mod inspect;
+pub mod macromachine;
 pub mod movement;
+pub mod multitape;
+pub mod minimize;
+pub mod modifiable;
 pub mod unmodifiable;
 pub mod transition;
+pub mod prune;
+pub mod run;
+pub mod stay;
+#[cfg(test)]
+mod strategies;
+pub mod standard;
+pub mod text;
+
+/// The symbols of a two-symbol machine: `false` is the blank `_` and `true`
+/// is `1`.
+pub type Bit = bool;
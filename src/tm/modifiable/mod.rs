@@ -1,4 +1,5 @@
 //! Variant of a Turing machine that does change internals.
 
+pub mod runlength;
 pub mod tape;
 pub mod turing;
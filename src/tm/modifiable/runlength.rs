@@ -0,0 +1,312 @@
+//! RunLength is a tape that stores runs of the same symbol as a single block.
+//!
+//! A tape that is mostly long stretches of the same symbol, as the tapes of
+//! busy beavers are, takes little memory this way. More importantly, moving
+//! the head over a whole run, writing a symbol to every cell, takes constant
+//! time. The `Machine` uses that for transitions that stay in their state.
+
+use std::ops::Range;
+use super::super::inspect::Inspect;
+use super::super::movement::Movement;
+use super::tape::{ConcreteTape, Tape};
+
+/// A tape of runs of the same symbol.
+#[derive(Clone, Debug)]
+pub struct RunLengthTape<S> {
+    blank: S,
+    left: Runs<S>,
+    current: S,
+    right: Runs<S>,
+    position: i64,
+}
+
+/// One side of the tape, as runs of the same symbol with their lengths. The
+/// run closest to the read/write head is on top, and neighbouring runs hold
+/// different symbols. Beyond the last run the side is blank.
+#[derive(Clone, Debug)]
+struct Runs<S> {
+    runs: Vec<(S, u64)>,
+    cells: u64,
+}
+
+impl<S: Clone + PartialEq> Runs<S> {
+    fn empty() -> Runs<S> {
+        Runs { runs: Vec::new(), cells: 0 }
+    }
+
+    /// Put `count` cells holding `symbol` next to the head.
+    fn push(&mut self, symbol: S, count: u64) {
+        self.cells += count;
+        match self.runs.last_mut() {
+            Some(&mut (ref top, ref mut length)) if *top == symbol => *length += count,
+
+            _ => self.runs.push((symbol, count)),
+        }
+    }
+
+    /// Take the cell next to the head, which is blank beyond the last run.
+    fn pop(&mut self, blank: &S) -> S {
+        let symbol = match self.runs.last() {
+            Some((symbol, _)) => symbol.clone(),
+
+            None => blank.clone(),
+        };
+        self.take(1);
+        symbol
+    }
+
+    /// Remove `count` cells next to the head, all of which are in the top run
+    /// or beyond the last run.
+    fn take(&mut self, count: u64) {
+        if let Some(&mut (_, ref mut length)) = self.runs.last_mut() {
+            let taken = count.min(*length);
+            *length -= taken;
+            self.cells -= taken;
+            if *length == 0 {
+                self.runs.pop();
+            }
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> RunLengthTape<S> {
+    /// Create an empty tape with `blank` as its blank symbol.
+    pub fn empty(blank: S) -> RunLengthTape<S> {
+        RunLengthTape {
+            blank: blank.clone(),
+            left: Runs::empty(),
+            current: blank,
+            right: Runs::empty(),
+            position: 0,
+        }
+    }
+
+    /// The number of runs stored, a measure of the memory the tape takes.
+    pub fn runs(&self) -> usize {
+        self.left.runs.len() + 1 + self.right.runs.len()
+    }
+
+    /// The runs of the extent from left to right, with their lengths. The
+    /// scanned cell is a run of its own.
+    pub fn iter_runs(&self) -> impl Iterator<Item = (&S, u64)> + '_ {
+        self.left.runs.iter()
+            .map(|(symbol, length)| (symbol, *length))
+            .chain(Some((&self.current, 1)))
+            .chain(self.right.runs.iter().rev().map(|(symbol, length)| (symbol, *length)))
+    }
+
+    /// Write `symbol` to `count` cells from the head onwards and move the
+    /// head past them, where all but the scanned cell are in the run next to
+    /// the head.
+    fn shift(&mut self, count: u64, symbol: S, movement: Movement) {
+        let (behind, ahead) = match movement {
+            Movement::Left => (&mut self.right, &mut self.left),
+
+            Movement::Right => (&mut self.left, &mut self.right),
+
+            Movement::Stay => {
+                self.current = symbol;
+                return;
+            }
+        };
+        ahead.take(count - 1);
+        behind.push(symbol, count);
+        // The symbol just beyond the skipped cells, which is blank when the
+        // run of blanks reached past the last run.
+        self.current = ahead.pop(&self.blank);
+        let count = count as i64;
+        self.position += if movement == Movement::Left { -count } else { count };
+    }
+}
+
+impl<'a, S: Clone + PartialEq> From<&'a ConcreteTape<S>> for RunLengthTape<S> {
+    fn from(tape: &'a ConcreteTape<S>) -> RunLengthTape<S> {
+        let mut result = RunLengthTape::empty(tape.blank());
+        let extent = tape.extent();
+        let cells = tape.to_vec();
+        let head = (tape.head_position() - extent.start) as usize;
+        for symbol in cells[..head].iter() {
+            result.left.push(symbol.clone(), 1);
+        }
+        for symbol in cells[head + 1..].iter().rev() {
+            result.right.push(symbol.clone(), 1);
+        }
+        result.current = cells[head].clone();
+        result.position = tape.head_position();
+        result
+    }
+}
+
+impl<S: Clone + PartialEq> Tape<S> for RunLengthTape<S> {
+    fn read(&self) -> S {
+        self.current.clone()
+    }
+
+    fn write(&mut self, symbol: S) {
+        self.current = symbol;
+    }
+
+    fn blank(&self) -> S {
+        self.blank.clone()
+    }
+
+    fn left(&mut self) {
+        let current = self.current.clone();
+        self.shift(1, current, Movement::Left);
+    }
+
+    fn right(&mut self) {
+        let current = self.current.clone();
+        self.shift(1, current, Movement::Right);
+    }
+
+    fn run_length(&self, movement: Movement, limit: u64) -> u64 {
+        let ahead = match movement {
+            Movement::Left => &self.left,
+
+            Movement::Right => &self.right,
+
+            Movement::Stay => return 1.min(limit),
+        };
+        let length = match ahead.runs.split_last() {
+            Some((&(ref symbol, length), rest)) if *symbol == self.current => {
+                if rest.is_empty() && *symbol == self.blank { u64::MAX } else { 1 + length }
+            }
+
+            Some(_) => 1,
+
+            None => if self.current == self.blank { u64::MAX } else { 1 },
+        };
+        length.min(limit)
+    }
+
+    fn skip(&mut self, symbol: S, movement: Movement, count: u64) {
+        if count > 0 {
+            self.shift(count, symbol, movement);
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> Inspect<S> for RunLengthTape<S> {
+    fn head_position(&self) -> i64 {
+        self.position
+    }
+
+    fn extent(&self) -> Range<i64> {
+        (self.position - self.left.cells as i64)..(self.position + self.right.cells as i64 + 1)
+    }
+
+    fn to_vec(&self) -> Vec<S> {
+        let mut symbols = Vec::new();
+        for &(ref symbol, length) in &self.left.runs {
+            symbols.extend((0..length).map(|_| symbol.clone()));
+        }
+        symbols.push(self.current.clone());
+        for &(ref symbol, length) in self.right.runs.iter().rev() {
+            symbols.extend((0..length).map(|_| symbol.clone()));
+        }
+        symbols
+    }
+
+    fn non_blank_count(&self) -> usize {
+        let runs = self.left.runs.iter().chain(self.right.runs.iter());
+        let count: u64 = runs.filter(|(symbol, _)| *symbol != self.blank).map(|&(_, length)| length).sum();
+        count as usize + (self.current != self.blank) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use proptest::prelude::*;
+    use super::super::tape::TapeBuilder;
+    use super::super::super::strategies::movement_or_stay;
+    use super::super::turing::Machine;
+    use super::super::super::run::RunOutcome;
+    use super::super::super::transition::{HashTransitions, Next, Transitions, TransitionKey, TransitionValue};
+
+    #[test]
+    fn should_skip_over_a_run() {
+        let mut t = RunLengthTape::from(&TapeBuilder::with_blank('_').with_current('a').with_right_tape(vec!['a', 'a', 'b']).build());
+
+        assert_eq!(t.run_length(Movement::Right, 10), 3);
+        assert_eq!(t.run_length(Movement::Right, 2), 2);
+        assert_eq!(t.run_length(Movement::Left, 10), 1);
+        t.skip('c', Movement::Right, 3);
+
+        assert_eq!(t.read(), 'b');
+        assert_eq!(t.head_position(), 3);
+        assert_eq!(t.to_vec(), vec!['c', 'c', 'c', 'b']);
+        assert_eq!(t.runs(), 2);
+        assert_eq!(t.iter_runs().collect::<Vec<_>>(), vec![(&'c', 3), (&'b', 1)]);
+        assert_eq!(t.run_length(Movement::Left, 10), 1);
+        t.left();
+        assert_eq!(t.run_length(Movement::Left, 10), 3);
+    }
+
+    #[test]
+    fn blanks_should_run_on_forever() {
+        let mut t = RunLengthTape::empty('_');
+
+        assert_eq!(t.run_length(Movement::Left, 1_000_000_000_000), 1_000_000_000_000);
+        t.skip('1', Movement::Left, 1_000_000_000_000);
+
+        assert_eq!(t.read(), '_');
+        assert_eq!(t.head_position(), -1_000_000_000_000);
+        assert_eq!(t.extent(), -1_000_000_000_000..1);
+        assert_eq!(t.non_blank_count(), 1_000_000_000_000);
+        assert_eq!(t.runs(), 2);
+    }
+
+    #[test]
+    fn should_run_a_busy_beaver_quickly() {
+        let transitions = Rc::new(HashTransitions::from(&Transitions::from_standard("1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA").expect("a valid machine")));
+        let mut machine = Machine::new(0, RunLengthTape::empty(0), transitions);
+
+        assert_eq!(machine.run(100_000_000), RunOutcome::Halted { steps: 47_176_870, state: Next::Halt });
+        assert_eq!(machine.tape().non_blank_count(), 4098);
+    }
+
+    #[test]
+    fn skipping_should_stop_at_the_step_limit() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new(0, 0), TransitionValue::new(0, 1, Movement::Right));
+        let mut machine = Machine::new(0, RunLengthTape::empty(0), transitions);
+
+        assert_eq!(machine.run(1_000_000_000_000), RunOutcome::StepLimitReached);
+        assert_eq!(machine.tape().head_position(), 1_000_000_000_000);
+        assert_eq!(machine.run(5), RunOutcome::StepLimitReached);
+        assert_eq!(machine.tape().non_blank_count(), 1_000_000_000_005);
+    }
+
+    proptest! {
+        #[test]
+        fn run_length_tapes_should_behave_like_concrete_tapes(
+                left in prop::collection::vec(0u8..3, 0..6),
+                current in 0u8..3,
+                right in prop::collection::vec(0u8..3, 0..6),
+                operations in prop::collection::vec((0u8..3, movement_or_stay(), 0u64..8), 0..40)) {
+            let mut concrete = TapeBuilder::with_blank(0).with_left_tape(left).with_current(current).with_right_tape(right).build();
+            let mut runs = RunLengthTape::from(&concrete);
+            for (symbol, movement, count) in operations {
+                let mut length = 0;
+                let mut probe = concrete.clone();
+                while length < 8 && probe.read() == concrete.read() && (length == 0 || movement != Movement::Stay) {
+                    length += 1;
+                    probe.move_head(movement);
+                }
+                prop_assert_eq!(runs.run_length(movement, 8), length);
+                let count = count.min(length);
+
+                concrete.skip(symbol, movement, count);
+                runs.skip(symbol, movement, count);
+                prop_assert_eq!(runs.read(), concrete.read());
+                prop_assert_eq!(runs.head_position(), concrete.head_position());
+                prop_assert_eq!(runs.non_blank_count(), concrete.non_blank_count());
+                prop_assert_eq!(runs.extent(), concrete.extent());
+                prop_assert_eq!(runs.to_vec(), concrete.to_vec());
+            }
+        }
+    }
+}
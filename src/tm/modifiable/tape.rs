@@ -2,6 +2,10 @@
 //!
 //! You can read from it, write to it and move it left and right.
 
+use std::ops::Range;
+use super::super::inspect::Inspect;
+use super::super::movement::Movement;
+
 /// Contract a concrete Tape should adhere to
 pub trait Tape<S: Clone> {
     /// Reads the symbol at the location of the head of the Turing machine.
@@ -18,26 +22,316 @@ pub trait Tape<S: Clone> {
 
     /// Move the head of the Turing machine to the right on the tape.
     fn right(&mut self);
+
+    /// Move the head of the Turing machine according to `movement`.
+    fn move_head(&mut self, movement: Movement) {
+        match movement {
+            Movement::Left => self.left(),
+
+            Movement::Right => self.right(),
+
+            Movement::Stay => (),
+        }
+    }
+
+    /// The number of consecutive cells that hold the scanned symbol, starting
+    /// at the head and going in the direction of `movement`, but at most
+    /// `limit`. Tapes that can not tell cheaply count only the scanned cell.
+    fn run_length(&self, _movement: Movement, _limit: u64) -> u64 {
+        1
+    }
+
+    /// Write `symbol` and move according to `movement`, `count` times. The
+    /// count should not exceed the `run_length` in the same direction.
+    fn skip(&mut self, symbol: S, movement: Movement, count: u64) {
+        for _ in 0..count {
+            self.write(symbol.clone());
+            self.move_head(movement);
+        }
+    }
 }
 
 /// A type implementing the `Tape` trait
-#[derive(Debug)]
-pub struc ConcreteTape<S> {
+#[derive(Clone, Debug)]
+pub struct ConcreteTape<S> {
     blank: S,
     left: HalfTape<S>,
     current: S,
     right: HalfTape<S>,
+    position: i64,
 }
 
-#[derive(Debug)]
-enum HalfTape<S> {
-    Cell(S, Box<HalfTape<S>>),
-    Empty,
+/// One side of the tape. The cell closest to the read/write head is on top,
+/// so moving the head only pushes and pops at the end of the vector.
+#[derive(Clone, Debug)]
+struct HalfTape<S> {
+    cells: Vec<S>,
 }
 
 impl<S> HalfTape<S> {
     fn empty() -> HalfTape<S> {
-        HalfTape::Empty
+        HalfTape { cells: Vec::new() }
+    }
+
+    fn from_nearest_first(mut symbols: Vec<S>) -> HalfTape<S> {
+        symbols.reverse();
+        HalfTape { cells: symbols }
+    }
+
+    fn pop(&mut self) -> Option<S> {
+        self.cells.pop()
+    }
+
+    fn push(&mut self, symbol: S) {
+        self.cells.push(symbol)
+    }
+}
+
+impl<S: Clone> ConcreteTape<S> {
+    /// Create an empty `ConcreteTape`. The argument will be used to as the
+    /// blank symbol for this tape. This is necessary when the read/write head
+    /// moves into unexplored territory. If there is nothing on the tape yet, it
+    /// will provide the blank symbol.
+    pub fn empty(blank: S) -> ConcreteTape<S> {
+        ConcreteTape {
+            blank: blank.clone(),
+            left: HalfTape::empty(),
+            current: blank,
+            right: HalfTape::empty(),
+            position: 0,
+        }
+    }
+}
+
+impl<S: Clone> Tape<S> for ConcreteTape<S> {
+    fn read(&self) -> S {
+        self.current.clone()
+    }
+
+    fn write(&mut self, symbol: S) {
+        self.current = symbol;
+    }
+
+    fn blank(&self) -> S {
+        self.blank.clone()
+    }
+
+    fn left(&mut self) {
+        let symbol = self.left.pop().unwrap_or_else(|| self.blank.clone());
+        let previous = ::std::mem::replace(&mut self.current, symbol);
+        self.right.push(previous);
+        self.position -= 1;
+    }
+
+    fn right(&mut self) {
+        let symbol = self.right.pop().unwrap_or_else(|| self.blank.clone());
+        let previous = ::std::mem::replace(&mut self.current, symbol);
+        self.left.push(previous);
+        self.position += 1;
+    }
+}
+
+impl<S: Clone + PartialEq> Inspect<S> for ConcreteTape<S> {
+    fn head_position(&self) -> i64 {
+        self.position
+    }
+
+    fn extent(&self) -> Range<i64> {
+        (self.position - self.left.cells.len() as i64)..(self.position + self.right.cells.len() as i64 + 1)
+    }
+
+    fn to_vec(&self) -> Vec<S> {
+        let mut symbols = self.left.cells.clone();
+        symbols.push(self.current.clone());
+        symbols.extend(self.right.cells.iter().rev().cloned());
+        symbols
+    }
+
+    fn non_blank_count(&self) -> usize {
+        self.left.cells.iter()
+            .chain(Some(&self.current))
+            .chain(self.right.cells.iter())
+            .filter(|symbol| **symbol != self.blank)
+            .count()
+    }
+}
+
+/// Builder for `ConcreteTape`.
+///
+/// # Example
+/// The builder can be used to construct a `ConcreteTape`.
+///
+/// ```
+/// use parsimony::tm::modifiable::tape::TapeBuilder;
+///
+/// let tape = TapeBuilder::with_blank("_")
+///     .with_current("a")
+///     .with_right_tape(vec!["b", "c"])
+///     .build();
+/// ```
+pub struct TapeBuilder<S: Clone> {
+    tape: ConcreteTape<S>,
+}
+
+impl<S: Clone> TapeBuilder<S> {
+    /// Set the blank symbol for this tape.
+    pub fn with_blank(blank: S) -> TapeBuilder<S> {
+        TapeBuilder { tape: ConcreteTape::empty(blank) }
+    }
+
+    /// Set the symbol the read/write head is currently scanning
+    pub fn with_current(mut self, symbol: S) -> TapeBuilder<S> {
+        self.tape.write(symbol);
+        self
     }
 
+    /// Sets the tape to the right of the read/write head.
+    pub fn with_right_tape(mut self, symbols: Vec<S>) -> TapeBuilder<S> {
+        self.tape.right = HalfTape::from_nearest_first(symbols);
+        self
+    }
+
+    /// Sets the tape to the left of the read/write head.
+    pub fn with_left_tape(mut self, symbols: Vec<S>) -> TapeBuilder<S> {
+        self.tape.left = HalfTape::from_nearest_first(symbols);
+        self
+    }
+
+    /// Sets the position of the read/write head, which is 0 otherwise.
+    pub fn with_head_position(mut self, position: i64) -> TapeBuilder<S> {
+        self.tape.position = position;
+        self
+    }
+
+    /// Build the described tape.
+    pub fn build(self) -> ConcreteTape<S> {
+        self.tape
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_tape_should_read_head() {
+        let t = ConcreteTape::empty("_");
+
+        let symbol = t.read();
+
+        assert_eq!(symbol, t.blank());
+    }
+
+    #[test]
+    fn empty_tape_should_introduce_blanks_when_moved_left() {
+        let mut t = ConcreteTape::empty("_");
+        t.left();
+
+        let symbol = t.read();
+
+        assert_eq!(symbol, t.blank());
+    }
+
+    #[test]
+    fn empty_tape_should_introduce_blanks_when_moved_right() {
+        let mut t = ConcreteTape::empty("_");
+        t.right();
+
+        let symbol = t.read();
+
+        assert_eq!(symbol, t.blank());
+    }
+
+    #[test]
+    fn tape_should_remember_written_symbols() {
+        let mut t = ConcreteTape::empty("_");
+        t.write("a");
+        t.right();
+        t.write("b");
+        t.left();
+
+        assert_eq!(t.read(), "a");
+
+        t.right();
+        assert_eq!(t.read(), "b");
+    }
+
+    #[test]
+    fn tape_should_move_head_according_to_movement() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_right_tape(vec!["b"])
+            .build();
+
+        t.move_head(Movement::Stay);
+        assert_eq!(t.read(), "a");
+
+        t.move_head(Movement::Right);
+        assert_eq!(t.read(), "b");
+
+        t.move_head(Movement::Left);
+        assert_eq!(t.read(), "a");
+    }
+
+    #[test]
+    fn tape_should_report_head_position_and_extent() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b"])
+            .with_right_tape(vec!["c", "_"])
+            .build();
+
+        assert_eq!(t.head_position(), 0);
+        assert_eq!(t.extent(), -1..3);
+
+        t.left();
+        t.left();
+        t.write("d");
+        assert_eq!(t.head_position(), -2);
+        assert_eq!(t.extent(), -2..3);
+        assert_eq!(t.to_vec(), vec!["d", "b", "a", "c", "_"]);
+        assert_eq!(t.non_blank_count(), 4);
+    }
+
+    #[test]
+    fn tape_should_be_loaded_with_initial_symbols_on_the_right() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_right_tape(vec!["b", "c", "d"])
+            .build();
+
+        let mut symbol = t.read();
+        assert_eq!(symbol, "a");
+
+        t.right();
+        symbol = t.read();
+        assert_eq!(symbol, "b");
+    }
+
+    #[test]
+    fn tape_should_be_loaded_with_initial_symbols_on_the_left() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b", "c", "d"])
+            .build();
+
+        let mut symbol = t.read();
+        assert_eq!(symbol, "a");
+
+        t.left();
+        symbol = t.read();
+        assert_eq!(symbol, "b");
+    }
+
+    #[test]
+    fn tape_should_be_built_with_the_head_anywhere() {
+        let t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b"])
+            .with_head_position(5)
+            .build();
+
+        assert_eq!(t.head_position(), 5);
+        assert_eq!(t.extent(), 4..6);
+    }
 }
@@ -0,0 +1,248 @@
+//! The actual Turing machine
+//!
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use super::super::movement::Movement;
+use super::super::transition::{Lookup, Next, Transitions, TransitionKey};
+use super::super::run::RunOutcome;
+use super::tape::{Tape, ConcreteTape};
+
+/// The actual Turing machine
+///
+/// The transitions can be any `Lookup`, e.g. a `HashTransitions` shared with
+/// other machines behind an `Rc` or an `Arc`. The tape can be any `Tape`; a
+/// `RunLengthTape` lets `run` take a transition that stays in its state over a
+/// whole run of the symbol it reads at once.
+///
+/// The machine halts when a transition leads to HALT or ERROR, or when it
+/// enters one of the states declared with `with_halting_states`.
+#[derive(Debug)]
+pub struct Machine<Q, S, L = Transitions<Q, S>, T = ConcreteTape<S>> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash, L: Lookup<Q, S>, T: Tape<S> {
+    state: Next<Q>,
+    tape: T,
+    transitions: L,
+    halting: HashSet<Q>,
+    symbols: PhantomData<S>,
+}
+
+impl <Q, S, L, T> Machine<Q, S, L, T> where S: Clone + Eq + Hash, Q: Clone + Eq + Hash, L: Lookup<Q, S>, T: Tape<S> {
+    /// Create a Turing machine from a initial state, a tape and transistions
+    pub fn new(q: Q, tape: T, transitions: L) -> Machine<Q, S, L, T> {
+        Machine {
+            state: Next::State(q),
+            tape,
+            transitions,
+            halting: HashSet::new(),
+            symbols: PhantomData,
+        }
+    }
+
+    /// Declare the states in which the machine halts when running.
+    pub fn with_halting_states<I>(mut self, states: I) -> Machine<Q, S, L, T> where I: IntoIterator<Item = Q> {
+        self.halting = states.into_iter().collect();
+        self
+    }
+
+    /// The state the machine is currently in.
+    pub fn state(&self) -> &Next<Q> {
+        &self.state
+    }
+
+    /// The transitions the machine follows.
+    pub fn transitions(&self) -> &L {
+        &self.transitions
+    }
+
+    /// The tape the machine is operating on.
+    pub fn tape(&self) -> &T {
+        &self.tape
+    }
+
+    /// Whether the machine is in one of its halting states.
+    pub fn is_halted(&self) -> bool {
+        match self.state {
+            Next::State(ref q) => self.halting.contains(q),
+
+            Next::Halt | Next::Error => true,
+        }
+    }
+
+    /// Step the machine through one transition, in place, and report where
+    /// the transition led.
+    ///
+    /// Returns `None`, leaving the machine untouched, when the machine is in
+    /// HALT or ERROR, or when there is no transition for the current state and
+    /// symbol.
+    pub fn step(&mut self) -> Option<Next<Q>> {
+        self.advance(1).map(|(next, _)| next)
+    }
+
+    /// Take the transition for the current state and symbol. When it stays in
+    /// the same state and moves, it is taken again for every cell of the run
+    /// of the same symbol, at most `limit` times in total.
+    ///
+    /// Returns where the transition led and how often it was taken.
+    fn advance(&mut self, limit: u64) -> Option<(Next<Q>, u64)> {
+        let (next, count) = match self.state {
+            Next::State(ref q) => {
+                let key = TransitionKey::new(q.clone(), self.tape.read());
+                let next = self.transitions.lookup(&key)?;
+                let count = if next.movement != Movement::Stay && next.next == self.state {
+                    self.tape.run_length(next.movement, limit)
+                } else {
+                    1
+                };
+                (next, count)
+            }
+
+            Next::Halt | Next::Error => return None,
+        };
+        self.tape.skip(next.symbol.clone(), next.movement, count);
+        self.state = next.next.clone();
+        Some((self.state.clone(), count))
+    }
+
+    /// Run the machine in place for at most `limit` steps.
+    pub fn run(&mut self, limit: u64) -> RunOutcome<Q, S> {
+        let mut steps = 0;
+        loop {
+            if self.is_halted() {
+                return RunOutcome::Halted { steps, state: self.state.clone() };
+            }
+            if steps == limit {
+                return RunOutcome::StepLimitReached;
+            }
+            match self.advance(limit - steps) {
+                Some((_, count)) => steps += count,
+
+                None => if let Next::State(ref state) = self.state {
+                    return RunOutcome::UndefinedTransition {
+                        state: state.clone(),
+                        symbol: self.tape.read(),
+                    };
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::movement::Movement;
+    use super::super::tape::TapeBuilder;
+    use std::sync::Arc;
+    use super::super::super::transition::{Next, Transitions, DenseTransitions, TransitionKey, TransitionValue};
+
+    fn successor() -> Machine<i32, &'static str> {
+        Machine::new(
+            0,
+            TapeBuilder::with_blank("_")
+                .with_current("I")
+                .with_right_tape(vec!["I", "I"])
+                .build(),
+            Transitions::new()
+                .insert(
+                    TransitionKey::new(0, "I"),
+                    TransitionValue::new(0, "I", Movement::Right))
+                .insert(
+                    TransitionKey::new(0, "_"),
+                    TransitionValue::new(1, "I", Movement::Left))
+                .insert(
+                    TransitionKey::new(1, "I"),
+                    TransitionValue::new(1, "I", Movement::Left))
+                .insert(
+                    TransitionKey::new(1, "_"),
+                    TransitionValue::new(2, "_", Movement::Right))
+        )
+    }
+
+    #[test]
+    fn should_step_through_a_program() {
+        let mut m = successor();
+
+        assert_eq!(m.step(), Some(Next::State(0)));
+        assert_eq!(*m.state(), Next::State(0));
+        assert_eq!(m.tape().read(), "I");
+    }
+
+    #[test]
+    fn should_stop_when_no_transition_applies() {
+        let mut m = successor();
+
+        let mut steps = 0;
+        while m.step().is_some() {
+            steps += 1;
+        }
+
+        assert_eq!(steps, 8);
+        assert_eq!(*m.state(), Next::State(2));
+        assert_eq!(m.tape().read(), "I");
+    }
+
+    #[test]
+    fn should_run_until_a_halting_state() {
+        let mut m = successor().with_halting_states(vec![2]);
+
+        assert_eq!(m.run(100), RunOutcome::Halted { steps: 8, state: Next::State(2) });
+        assert_eq!(m.run(100), RunOutcome::Halted { steps: 0, state: Next::State(2) });
+    }
+
+    #[test]
+    fn should_report_an_undefined_transition() {
+        let mut m = successor();
+
+        assert_eq!(m.run(100), RunOutcome::UndefinedTransition { state: 2, symbol: "I" });
+    }
+
+    #[test]
+    fn should_stop_at_the_step_limit() {
+        let mut m = successor().with_halting_states(vec![2]);
+
+        assert_eq!(m.run(5), RunOutcome::StepLimitReached);
+        assert_eq!(m.run(3), RunOutcome::Halted { steps: 3, state: Next::State(2) });
+    }
+
+    #[test]
+    fn should_report_reaching_halt_and_error() {
+        let transitions = Transitions::new()
+            .insert(
+                TransitionKey::new("start", "_"),
+                TransitionValue::new("check", "I", Movement::Right))
+            .insert(
+                TransitionKey::new("check", "_"),
+                TransitionValue::halt("_", Movement::Left))
+            .insert(
+                TransitionKey::new("check", "I"),
+                TransitionValue::error("I", Movement::Left));
+
+        let mut m = Machine::new("start", TapeBuilder::with_blank("_").build(), transitions.clone());
+        assert_eq!(m.step(), Some(Next::State("check")));
+        assert_eq!(m.step(), Some(Next::Halt));
+        assert_eq!(m.step(), None);
+        assert_eq!(m.tape().read(), "I");
+
+        let mut m = Machine::new("check", TapeBuilder::with_blank("_").with_current("I").build(), transitions);
+        assert_eq!(m.run(10), RunOutcome::Halted { steps: 1, state: Next::Error });
+    }
+
+    #[test]
+    fn should_run_on_a_shared_dense_table() {
+        let transitions = Arc::new(DenseTransitions::new(2)
+            .insert(
+                TransitionKey::new(0usize, false),
+                TransitionValue::new(1, true, Movement::Right))
+            .insert(
+                TransitionKey::new(1, false),
+                TransitionValue::new(2, true, Movement::Left)));
+        let mut m = Machine::new(0, TapeBuilder::with_blank(false).build(), transitions.clone());
+
+        assert!(m.step().is_some());
+        assert!(m.step().is_some());
+        assert!(m.step().is_none());
+        assert_eq!(*m.state(), Next::State(2));
+        assert!(m.tape().read());
+    }
+}
@@ -1,11 +1,16 @@
 //! Movement describes the movement of the read/write head of a Turing machine.
 
-/// The ways the read/write head can move is restricted to Left and Right. The movement is performed after a state
-/// change.
-#[derive(Clone, Debug)]
+/// The ways the read/write head can move is restricted to Left, Right and Stay. The movement is performed after a
+/// state change.
+///
+/// Stay is not part of the formal definition, but it is convenient when describing machines. The `stay` module
+/// rewrites a machine that uses it into an equivalent one that only moves Left and Right.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Movement {
     /// Moves the tape left.
     Left,
     /// Moves the tape right.
     Right,
+    /// Keeps the tape where it is.
+    Stay,
 }
@@ -0,0 +1,621 @@
+//! Multitape describes Turing machines with k tapes, each with its own
+//! read/write head.
+//!
+//! A transition depends on the state and the symbols under all heads, and
+//! writes a symbol and moves the head on every tape. Such a machine can be
+//! converted into a single-tape machine over columns: a cell of the single
+//! tape holds the cells of all k tapes at the same position, together with a
+//! mark for every head that is there.
+//!
+//! The single-tape machine carries out a transition in two sweeps. It starts
+//! left of all heads and moves right, collecting the symbols under the heads,
+//! until it has seen all of them. It then knows the transition and sweeps back
+//! left, writing the symbols and moving the marks of the heads it passes. When
+//! all heads are done, it is left of all of them again.
+
+use std::array;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+use super::inspect::Inspect;
+use super::modifiable;
+use super::movement::Movement;
+use super::run::RunOutcome;
+use super::transition::{HashTransitions, Next, Transitions, TransitionKey, TransitionValue};
+use super::unmodifiable;
+use super::unmodifiable::tape::Tape;
+
+/// `MultiKey` describes the context of a k-tape machine: its state and the
+/// symbols under its heads.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MultiKey<Q, S, const K: usize> {
+    /// The state the machine is in.
+    pub state: Q,
+    /// The symbols the heads are scanning, one for every tape.
+    pub symbols: [S; K],
+}
+
+impl <Q, S, const K: usize> MultiKey<Q, S, K> {
+    /// Create a `MultiKey`
+    pub fn new(state: Q, symbols: [S; K]) -> MultiKey<Q, S, K> {
+        MultiKey { state, symbols }
+    }
+}
+
+/// `MultiValue` describes what a k-tape machine does in a context.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MultiValue<Q, S, const K: usize> {
+    /// Where the machine will be after the transition.
+    pub next: Next<Q>,
+    /// The symbols to write, one for every tape.
+    pub symbols: [S; K],
+    /// How the heads move after writing, one for every tape.
+    pub movements: [Movement; K],
+}
+
+impl <Q, S, const K: usize> MultiValue<Q, S, K> {
+    /// Create a `MultiValue` that continues in `state`
+    pub fn new(state: Q, symbols: [S; K], movements: [Movement; K]) -> MultiValue<Q, S, K> {
+        MultiValue { next: Next::State(state), symbols, movements }
+    }
+
+    /// Create a `MultiValue` that halts after writing and moving
+    pub fn halt(symbols: [S; K], movements: [Movement; K]) -> MultiValue<Q, S, K> {
+        MultiValue { next: Next::Halt, symbols, movements }
+    }
+}
+
+/// The transitions of a k-tape machine, stored in a hash map.
+///
+/// When a key is inserted twice, the last value wins.
+#[derive(Clone, Debug)]
+pub struct MultiTransitions<Q, S, const K: usize> where Q: Eq + Hash, S: Eq + Hash {
+    table: HashMap<MultiKey<Q, S, K>, MultiValue<Q, S, K>>,
+}
+
+impl <Q, S, const K: usize> MultiTransitions<Q, S, K> where Q: Eq + Hash, S: Eq + Hash {
+    /// Create transitions
+    pub fn new() -> MultiTransitions<Q, S, K> {
+        MultiTransitions { table: HashMap::new() }
+    }
+
+    /// Insert a transition and return the new transitions
+    pub fn insert(mut self, key: MultiKey<Q, S, K>, value: MultiValue<Q, S, K>) -> MultiTransitions<Q, S, K> {
+        self.table.insert(key, value);
+        self
+    }
+
+    /// The number of transitions
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether there are no transitions at all
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Lookup the transition for a context.
+    pub fn lookup(&self, key: &MultiKey<Q, S, K>) -> Option<&MultiValue<Q, S, K>> {
+        self.table.get(key)
+    }
+}
+
+impl <Q, S, const K: usize> Default for MultiTransitions<Q, S, K> where Q: Eq + Hash, S: Eq + Hash {
+    fn default() -> MultiTransitions<Q, S, K> {
+        MultiTransitions::new()
+    }
+}
+
+/// What a k-tape machine needs from a tape. Both the modifiable and the
+/// unmodifiable `ConcreteTape` provide it.
+pub trait Head<S> {
+    /// Reads the symbol under the read/write head.
+    fn read(&self) -> S;
+
+    /// The blank symbol of the tape.
+    fn blank(&self) -> S;
+
+    /// Write `symbol` under the read/write head and move it.
+    fn update(&mut self, symbol: S, movement: Movement);
+}
+
+impl <S: Clone> Head<S> for modifiable::tape::ConcreteTape<S> {
+    fn read(&self) -> S {
+        modifiable::tape::Tape::read(self)
+    }
+
+    fn blank(&self) -> S {
+        modifiable::tape::Tape::blank(self)
+    }
+
+    fn update(&mut self, symbol: S, movement: Movement) {
+        modifiable::tape::Tape::write(self, symbol);
+        modifiable::tape::Tape::move_head(self, movement);
+    }
+}
+
+impl <S: Clone> Head<S> for unmodifiable::tape::ConcreteTape<S> {
+    fn read(&self) -> S {
+        Tape::read(self)
+    }
+
+    fn blank(&self) -> S {
+        Tape::blank(self)
+    }
+
+    fn update(&mut self, symbol: S, movement: Movement) {
+        *self = Tape::write(self, symbol).move_head(movement);
+    }
+}
+
+/// A Turing machine with `K` tapes of type `T`.
+#[derive(Debug)]
+pub struct Machine<Q, S, T, const K: usize> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash, T: Head<S> {
+    state: Next<Q>,
+    tapes: [T; K],
+    transitions: Rc<MultiTransitions<Q, S, K>>,
+}
+
+impl <Q, S, T, const K: usize> Machine<Q, S, T, K> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash, T: Head<S> {
+    /// Create a machine from an initial state, its tapes and transitions.
+    pub fn new(q: Q, tapes: [T; K], transitions: Rc<MultiTransitions<Q, S, K>>) -> Machine<Q, S, T, K> {
+        Machine { state: Next::State(q), tapes, transitions }
+    }
+
+    /// The state the machine is in.
+    pub fn state(&self) -> &Next<Q> {
+        &self.state
+    }
+
+    /// The tapes the machine is operating on.
+    pub fn tapes(&self) -> &[T; K] {
+        &self.tapes
+    }
+
+    /// Whether the machine is in HALT or ERROR.
+    pub fn is_halted(&self) -> bool {
+        self.state.is_final()
+    }
+
+    /// Step the machine through one transition, in place, and report where
+    /// the transition led.
+    ///
+    /// Returns `None`, leaving the machine untouched, when the machine is in
+    /// HALT or ERROR, or when there is no transition for the current state and
+    /// symbols.
+    pub fn step(&mut self) -> Option<Next<Q>> {
+        let value = match self.state {
+            Next::State(ref q) => {
+                let key = MultiKey::new(q.clone(), array::from_fn(|tape| self.tapes[tape].read()));
+                self.transitions.lookup(&key)?.clone()
+            }
+
+            Next::Halt | Next::Error => return None,
+        };
+        for (tape, (symbol, movement)) in self.tapes.iter_mut().zip(value.symbols.iter().cloned().zip(value.movements.iter().cloned())) {
+            tape.update(symbol, movement);
+        }
+        self.state = value.next;
+        Some(self.state.clone())
+    }
+
+    /// Run the machine in place for at most `limit` steps.
+    pub fn run(&mut self, limit: u64) -> RunOutcome<Q, [S; K]> {
+        let mut steps = 0;
+        loop {
+            if self.is_halted() {
+                return RunOutcome::Halted { steps, state: self.state.clone() };
+            }
+            if steps == limit {
+                return RunOutcome::StepLimitReached;
+            }
+            if self.step().is_none() {
+                if let Next::State(ref state) = self.state {
+                    return RunOutcome::UndefinedTransition {
+                        state: state.clone(),
+                        symbol: array::from_fn(|tape| self.tapes[tape].read()),
+                    };
+                }
+            }
+            steps += 1;
+        }
+    }
+}
+
+impl <Q, S, T, const K: usize> Machine<Q, S, T, K> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash, T: Head<S> + Inspect<S> {
+    /// An equivalent single-tape machine, in the same state and with the tapes
+    /// interleaved, or `None` when the machine is in HALT or ERROR.
+    ///
+    /// The alphabet should contain every symbol that can be on the tapes,
+    /// including the blank.
+    pub fn to_single_tape(&self, alphabet: &[S]) -> Option<SingleTape<Q, S, K>> {
+        match self.state {
+            Next::State(ref q) => {
+                let transitions = single_tape(&self.transitions, q.clone(), alphabet);
+                Some(unmodifiable::turing::Machine::new(
+                    Simulation::Read(q.clone(), array::from_fn(|_| None)),
+                    interleave(&self.tapes),
+                    Rc::new(HashTransitions::from(&transitions))))
+            }
+
+            Next::Halt | Next::Error => None,
+        }
+    }
+}
+
+/// A cell of the single tape: the cells of all tapes at one position and
+/// whether their heads are there.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Column<S, const K: usize> {
+    /// The symbols, one for every tape.
+    pub symbols: [S; K],
+    /// Whether the head of a tape is in this cell, one for every tape.
+    pub heads: [bool; K],
+}
+
+/// The single-tape machine simulating a k-tape machine.
+pub type SingleTape<Q, S, const K: usize> = unmodifiable::turing::Machine<Simulation<Q, S, K>, Column<S, K>, Rc<HashTransitions<Simulation<Q, S, K>, Column<S, K>>>>;
+
+/// The writes and moves of the heads that still have to be carried out.
+pub type Pending<S, const K: usize> = [Option<(S, Movement)>; K];
+
+/// The states of the single-tape machine simulating a k-tape machine in state
+/// `Q`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Simulation<Q, S, const K: usize> {
+    /// Sweeping right in state `Q`, with the symbols seen under the heads so
+    /// far.
+    Read(Q, [Option<S>; K]),
+    /// Sweeping left to carry out a transition to `Next<Q>`, with the heads
+    /// still to do and the heads that moved left onto this cell.
+    Write(Next<Q>, Pending<S, K>, [bool; K]),
+    /// Placing the heads that moved right onto this cell, before turning back.
+    Mark(Next<Q>, Pending<S, K>, [bool; K], [bool; K]),
+    /// Stepping back over the cell that was just done.
+    Skip(Next<Q>, Pending<S, K>, [bool; K]),
+}
+
+/// Convert the transitions of a k-tape machine into transitions of an
+/// equivalent single-tape machine starting in `Simulation::Read(start, [None;
+/// K])`, on a tape prepared with `interleave`.
+///
+/// Only the states reachable from the start are generated. The alphabet should
+/// contain every symbol that can be on the tapes, including the blank. When
+/// the k-tape machine has no transition, neither has the single-tape machine,
+/// but it gets stuck in a `Read` state after collecting the symbols.
+pub fn single_tape<Q, S, const K: usize>(transitions: &MultiTransitions<Q, S, K>, start: Q, alphabet: &[S]) -> Transitions<Simulation<Q, S, K>, Column<S, K>> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    let columns = columns(alphabet);
+    let start = Simulation::Read(start, array::from_fn(|_| None));
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start.clone());
+    queue.push_back(start);
+
+    let mut result = Transitions::new();
+    while let Some(state) = queue.pop_front() {
+        for column in &columns {
+            if let Some(value) = simulate(transitions, &state, column) {
+                if let Next::State(ref next) = value.next {
+                    if seen.insert(next.clone()) {
+                        queue.push_back(next.clone());
+                    }
+                }
+                result = result.insert(TransitionKey::new(state.clone(), column.clone()), value);
+            }
+        }
+    }
+    result
+}
+
+/// Every column over `alphabet`.
+fn columns<S, const K: usize>(alphabet: &[S]) -> Vec<Column<S, K>> where S: Clone {
+    let mut columns = Vec::new();
+    let mut digits = [0; K];
+    loop {
+        for heads in 0..1usize << K {
+            columns.push(Column {
+                symbols: array::from_fn(|tape| alphabet[digits[tape]].clone()),
+                heads: array::from_fn(|tape| heads >> tape & 1 == 1),
+            });
+        }
+        match digits.iter().position(|&digit| digit + 1 < alphabet.len()) {
+            Some(tape) => {
+                digits[tape] += 1;
+                for digit in &mut digits[..tape] {
+                    *digit = 0;
+                }
+            }
+
+            None => return columns,
+        }
+    }
+}
+
+/// The transition of the single-tape machine in `state` reading `column`.
+fn simulate<Q, S, const K: usize>(transitions: &MultiTransitions<Q, S, K>, state: &Simulation<Q, S, K>, column: &Column<S, K>) -> Option<TransitionValue<Simulation<Q, S, K>, Column<S, K>>> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    match *state {
+        Simulation::Read(ref q, ref seen) => {
+            let seen: [Option<S>; K] = array::from_fn(|tape| match seen[tape] {
+                None if column.heads[tape] => Some(column.symbols[tape].clone()),
+
+                ref symbol => symbol.clone(),
+            });
+            if seen.iter().any(Option::is_none) {
+                return Some(TransitionValue::new(Simulation::Read(q.clone(), seen), column.clone(), Movement::Right));
+            }
+            let key = MultiKey::new(q.clone(), array::from_fn(|tape| seen[tape].clone().expect("a symbol for every head")));
+            let value = transitions.lookup(&key)?;
+            let pending = array::from_fn(|tape| Some((value.symbols[tape].clone(), value.movements[tape])));
+            Some(apply(value.next.clone(), pending, [false; K], column))
+        }
+
+        Simulation::Write(ref next, ref pending, arrived) => Some(apply(next.clone(), pending.clone(), arrived, column)),
+
+        Simulation::Mark(ref next, ref pending, left, right) => {
+            let mut column = column.clone();
+            for (head, &arrived) in column.heads.iter_mut().zip(&right) {
+                *head |= arrived;
+            }
+            Some(TransitionValue::new(Simulation::Skip(next.clone(), pending.clone(), left), column, Movement::Left))
+        }
+
+        Simulation::Skip(ref next, ref pending, left) =>
+            Some(TransitionValue { next: leave(next.clone(), pending.clone(), left), symbol: column.clone(), movement: Movement::Left }),
+    }
+}
+
+/// Carry out the pending writes and moves of the heads in `column`, after
+/// placing the heads that `arrived` from the right.
+fn apply<Q, S, const K: usize>(next: Next<Q>, mut pending: Pending<S, K>, arrived: [bool; K], column: &Column<S, K>) -> TransitionValue<Simulation<Q, S, K>, Column<S, K>> where Q: Clone, S: Clone {
+    let mut column = column.clone();
+    let mut left = [false; K];
+    let mut right = [false; K];
+    for tape in 0..K {
+        column.heads[tape] |= arrived[tape];
+        if !column.heads[tape] || arrived[tape] {
+            continue;
+        }
+        if let Some((symbol, movement)) = pending[tape].take() {
+            column.symbols[tape] = symbol;
+            match movement {
+                Movement::Left => {
+                    column.heads[tape] = false;
+                    left[tape] = true;
+                }
+
+                Movement::Right => {
+                    column.heads[tape] = false;
+                    right[tape] = true;
+                }
+
+                Movement::Stay => (),
+            }
+        }
+    }
+
+    if right.contains(&true) {
+        TransitionValue::new(Simulation::Mark(next, pending, left, right), column, Movement::Right)
+    } else {
+        TransitionValue { next: leave(next, pending, left), symbol: column, movement: Movement::Left }
+    }
+}
+
+/// Where to go after moving left off a cell: on with the sweep when there is
+/// more to do, or to the next transition.
+fn leave<Q, S, const K: usize>(next: Next<Q>, pending: Pending<S, K>, left: [bool; K]) -> Next<Simulation<Q, S, K>> {
+    if pending.iter().all(Option::is_none) && !left.contains(&true) {
+        next.map(|q| Simulation::Read(q, array::from_fn(|_| None)))
+    } else {
+        Next::State(Simulation::Write(next, pending, left))
+    }
+}
+
+/// Interleave `tapes` into a single tape of columns, keeping the positions of
+/// the cells. The head of the single tape is on the leftmost head.
+pub fn interleave<S, T, const K: usize>(tapes: &[T; K]) -> unmodifiable::tape::ConcreteTape<Column<S, K>> where S: Clone + PartialEq, T: Head<S> + Inspect<S> {
+    let start = tapes.iter().map(|tape| tape.extent().start).min().unwrap_or(0).min(0);
+    let end = tapes.iter().map(|tape| tape.extent().end).max().unwrap_or(1).max(1);
+    let contents: Vec<Vec<S>> = tapes.iter().map(Inspect::to_vec).collect();
+    let cells: Vec<Column<S, K>> = (start..end).map(|position| Column {
+        symbols: array::from_fn(|tape| {
+            let extent = tapes[tape].extent();
+            if extent.contains(&position) {
+                contents[tape][(position - extent.start) as usize].clone()
+            } else {
+                tapes[tape].blank()
+            }
+        }),
+        heads: array::from_fn(|tape| tapes[tape].head_position() == position),
+    }).collect();
+    let head = tapes.iter().map(Inspect::head_position).min().unwrap_or(0);
+    let blank = Column { symbols: array::from_fn(|tape| tapes[tape].blank()), heads: [false; K] };
+    let index = (head - start) as usize;
+    unmodifiable::tape::TapeBuilder::with_blank(blank)
+        .with_left_tape(cells[..index].iter().rev().cloned().collect())
+        .with_current(cells[index].clone())
+        .with_right_tape(cells[index + 1..].to_vec())
+        .with_head_position(head)
+        .build()
+}
+
+/// Split a tape of columns back into the tapes it interleaves.
+///
+/// # Panics
+/// When the head of a tape is not marked on the visited part of the tape.
+pub fn split<S, const K: usize>(tape: &unmodifiable::tape::ConcreteTape<Column<S, K>>) -> [unmodifiable::tape::ConcreteTape<S>; K] where S: Clone + PartialEq {
+    let start = tape.extent().start;
+    let columns = tape.to_vec();
+    let blank = Tape::blank(tape);
+    array::from_fn(|track| {
+        let head = columns.iter().position(|column| column.heads[track]).expect("a head on every tape");
+        let cells: Vec<S> = columns.iter().map(|column| column.symbols[track].clone()).collect();
+        unmodifiable::tape::TapeBuilder::with_blank(blank.symbols[track].clone())
+            .with_left_tape(cells[..head].iter().rev().cloned().collect())
+            .with_current(cells[head].clone())
+            .with_right_tape(cells[head + 1..].to_vec())
+            .with_head_position(start + head as i64)
+            .build()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use super::super::modifiable::tape::TapeBuilder;
+    use super::super::strategies::movement_or_stay;
+
+    /// Copies the ones on the first tape to the second, then halts.
+    fn copy() -> MultiTransitions<u32, char, 2> {
+        MultiTransitions::new()
+            .insert(
+                MultiKey::new(0, ['1', '_']),
+                MultiValue::new(0, ['1', '1'], [Movement::Right, Movement::Right]))
+            .insert(
+                MultiKey::new(0, ['_', '_']),
+                MultiValue::new(1, ['_', '_'], [Movement::Left, Movement::Stay]))
+            .insert(
+                MultiKey::new(1, ['1', '_']),
+                MultiValue::halt(['1', '_'], [Movement::Stay, Movement::Left]))
+    }
+
+    #[test]
+    fn should_run_on_modifiable_tapes() {
+        let mut machine = Machine::new(
+            0,
+            [TapeBuilder::with_blank('_').with_current('1').with_right_tape(vec!['1', '1']).build(), modifiable::tape::ConcreteTape::empty('_')],
+            Rc::new(copy()));
+
+        assert_eq!(machine.run(100), RunOutcome::Halted { steps: 5, state: Next::Halt });
+        assert_eq!(machine.tapes()[1].to_vec(), vec!['1', '1', '1', '_']);
+        assert_eq!(machine.tapes()[1].head_position(), 2);
+    }
+
+    #[test]
+    fn should_run_on_unmodifiable_tapes() {
+        let mut machine = Machine::new(
+            0,
+            [unmodifiable::tape::TapeBuilder::with_blank('_').with_current('1').build(), unmodifiable::tape::ConcreteTape::empty('_')],
+            Rc::new(copy()));
+
+        assert_eq!(machine.step(), Some(Next::State(0)));
+        assert_eq!(machine.run(100), RunOutcome::Halted { steps: 2, state: Next::Halt });
+        assert_eq!(machine.tapes()[1].to_vec(), vec!['1', '_']);
+    }
+
+    #[test]
+    fn should_report_an_undefined_transition() {
+        let mut machine = Machine::new(
+            0,
+            [modifiable::tape::ConcreteTape::empty('_'), TapeBuilder::with_blank('_').with_current('1').build()],
+            Rc::new(copy()));
+
+        assert_eq!(machine.run(100), RunOutcome::UndefinedTransition { state: 0, symbol: ['_', '1'] });
+    }
+
+    #[test]
+    fn single_tape_machine_should_copy() {
+        let machine = Machine::new(
+            0,
+            [TapeBuilder::with_blank('_').with_current('1').with_right_tape(vec!['1', '1']).build(), modifiable::tape::ConcreteTape::empty('_')],
+            Rc::new(copy()));
+
+        let single = machine.to_single_tape(&['_', '1']).expect("a running machine");
+        let (single, outcome) = single.run(1000);
+
+        assert!(matches!(outcome, RunOutcome::Halted { state: Next::Halt, .. }));
+        let ones = vec![(0, '1'), (1, '1'), (2, '1')];
+        assert_eq!(split(single.tape()).iter().map(contents).collect::<Vec<_>>(), vec![(ones.clone(), 2), (ones, 2)]);
+    }
+
+    /// The symbols that are not blank, with their positions, and the head.
+    fn contents<S, T>(tape: &T) -> (Vec<(i64, S)>, i64) where S: Clone + PartialEq, T: Head<S> + Inspect<S> {
+        let start = tape.extent().start;
+        let blank = Head::blank(tape);
+        let cells = tape.to_vec().into_iter().enumerate()
+            .filter(|(_, symbol)| *symbol != blank)
+            .map(|(index, symbol)| (start + index as i64, symbol))
+            .collect();
+        (cells, tape.head_position())
+    }
+
+    fn transitions() -> impl Strategy<Value = MultiTransitions<u8, u8, 2>> {
+        let value = (0u8..4, [0u8..2, 0u8..2], [movement_or_stay(), movement_or_stay()]).prop_map(|(next, symbols, movements)| {
+            let next = if next == 3 { Next::Halt } else { Next::State(next) };
+            MultiValue { next, symbols, movements }
+        });
+        prop::collection::vec(((0u8..3, [0u8..2, 0u8..2]), value), 0..40).prop_map(|entries| {
+            entries.into_iter().fold(MultiTransitions::new(), |transitions, ((state, symbols), value)| {
+                transitions.insert(MultiKey::new(state, symbols), value)
+            })
+        })
+    }
+
+    fn tape() -> impl Strategy<Value = unmodifiable::tape::ConcreteTape<u8>> {
+        (prop::collection::vec(0u8..2, 0..4), 0u8..2, prop::collection::vec(0u8..2, 0..4)).prop_map(|(left, current, right)| {
+            unmodifiable::tape::TapeBuilder::with_blank(0).with_left_tape(left).with_current(current).with_right_tape(right).build()
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+        #[test]
+        fn single_tape_machine_should_follow_every_step(transitions in transitions(), tapes in [tape(), tape()]) {
+            let mut machine = Machine::new(0, tapes, Rc::new(transitions));
+            let mut single = machine.to_single_tape(&[0, 1]).expect("a running machine");
+
+            for _ in 0..20 {
+                let stepped = machine.step();
+                // One step of the k-tape machine is a sweep to the right until
+                // all heads are seen, and a sweep back until reading starts
+                // again.
+                let mut seen = false;
+                loop {
+                    let completes = match *single.state() {
+                        Next::State(Simulation::Read(_, ref symbols)) =>
+                            (0..2).all(|tape| symbols[tape].is_some() || Tape::read(single.tape()).heads[tape]),
+
+                        _ => false,
+                    };
+                    match single.step() {
+                        Some(next) => single = next,
+
+                        None => break,
+                    }
+                    seen |= completes;
+                    match *single.state() {
+                        Next::State(Simulation::Read(..)) if seen => break,
+
+                        Next::Halt | Next::Error => break,
+
+                        _ => (),
+                    }
+                }
+
+                match stepped {
+                    Some(next) => {
+                        prop_assert!(seen);
+                        let state = single.state().clone().map(|state| match state {
+                            Simulation::Read(q, _) => Some(q),
+
+                            _ => None,
+                        });
+                        prop_assert_eq!(state, next.map(Some));
+                        for (tape, expected) in split(single.tape()).iter().zip(machine.tapes()) {
+                            prop_assert_eq!(contents(tape), contents(expected));
+                        }
+                    }
+
+                    None => {
+                        prop_assert!(!seen);
+                        break;
+                    }
+                }
+                if machine.is_halted() {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Prune removes the states of a machine that can not be reached from its
+//! initial state, and numbers the remaining states densely.
+//!
+//! States are numbered in the order a breadth-first search from the initial
+//! state finds them, so the initial state becomes 0. Transitions that are
+//! shadowed by a more recently inserted transition for the same key are
+//! dropped as well, since a machine never takes them.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use super::transition::{Next, Transitions, TransitionKey, TransitionValue};
+
+/// How the states of a pruned machine relate to those of the original.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Renumbering<Q> where Q: Eq + Hash {
+    states: Vec<Q>,
+    numbers: HashMap<Q, usize>,
+}
+
+impl <Q> Renumbering<Q> where Q: Eq + Hash {
+    /// The new number of original state `q`, or `None` when it was pruned.
+    pub fn number(&self, q: &Q) -> Option<usize> {
+        self.numbers.get(q).cloned()
+    }
+
+    /// The original state with the new number `n`.
+    pub fn state(&self, n: usize) -> Option<&Q> {
+        self.states.get(n)
+    }
+
+    /// The original states, in the order of their new numbers.
+    pub fn states(&self) -> &[Q] {
+        &self.states
+    }
+
+    /// The number of states that were kept.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether no state was kept, which never happens as the initial state is.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+/// Keep the transitions reachable from `start`, with the states numbered
+/// densely from 0 for `start`.
+///
+/// Iterating the result lists the transitions by the number of their state.
+pub fn prune<Q, S>(transitions: &Transitions<Q, S>, start: &Q) -> (Transitions<usize, S>, Renumbering<Q>) where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    let mut seen: HashSet<(&Q, &S)> = HashSet::new();
+    let mut rows: HashMap<&Q, Vec<_>> = HashMap::new();
+    let original: Vec<_> = transitions.iter().collect();
+    for &(key, value) in &original {
+        if seen.insert((&key.state, &key.symbol)) {
+            rows.entry(&key.state).or_default().push((key, value));
+        }
+    }
+    for row in rows.values_mut() {
+        row.reverse();
+    }
+
+    let mut renumbering = Renumbering { states: vec![start.clone()], numbers: HashMap::new() };
+    renumbering.numbers.insert(start.clone(), 0);
+    let mut pending = VecDeque::new();
+    pending.push_back(start);
+    let mut result = Vec::new();
+    while let Some(state) = pending.pop_front() {
+        for &(key, value) in rows.get(state).map(Vec::as_slice).unwrap_or(&[]) {
+            let next = match value.next {
+                Next::State(ref next) => {
+                    if !renumbering.numbers.contains_key(next) {
+                        renumbering.numbers.insert(next.clone(), renumbering.states.len());
+                        renumbering.states.push(next.clone());
+                        pending.push_back(next);
+                    }
+                    Next::State(renumbering.numbers[next])
+                }
+
+                Next::Halt => Next::Halt,
+
+                Next::Error => Next::Error,
+            };
+            result.push((
+                TransitionKey::new(renumbering.numbers[&key.state], key.symbol.clone()),
+                TransitionValue { next, symbol: value.symbol.clone(), movement: value.movement },
+            ));
+        }
+    }
+
+    let transitions = result.into_iter().rev().fold(Transitions::new(), |transitions, (key, value)| transitions.insert(key, value));
+    (transitions, renumbering)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use proptest::prelude::*;
+    use super::super::inspect::Inspect;
+    use super::super::modifiable::tape::ConcreteTape;
+    use super::super::modifiable::turing::Machine;
+    use super::super::movement::Movement;
+    use super::super::strategies::{movement, next, transitions};
+    use super::super::transition::{HashTransitions, Lookup};
+
+    #[test]
+    fn should_drop_unreachable_states() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new('a', '_'), TransitionValue::new('c', '1', Movement::Right))
+            .insert(TransitionKey::new('b', '_'), TransitionValue::new('a', '1', Movement::Right))
+            .insert(TransitionKey::new('c', '_'), TransitionValue::new('a', '1', Movement::Left))
+            .insert(TransitionKey::new('c', '1'), TransitionValue::new('b', '1', Movement::Left))
+            .insert(TransitionKey::new('c', '1'), TransitionValue::halt('_', Movement::Left))
+            .insert(TransitionKey::new('d', '1'), TransitionValue::error('1', Movement::Left));
+
+        let (pruned, renumbering) = prune(&transitions, &'a');
+
+        assert_eq!(renumbering.states(), &['a', 'c']);
+        assert_eq!(renumbering.number(&'c'), Some(1));
+        assert_eq!(renumbering.number(&'b'), None);
+        assert_eq!(renumbering.state(1), Some(&'c'));
+        assert_eq!(pruned.iter().map(|(key, value)| (key.clone(), value.clone())).collect::<Vec<_>>(), vec![
+            (TransitionKey::new(0, '_'), TransitionValue::new(1, '1', Movement::Right)),
+            (TransitionKey::new(1, '_'), TransitionValue::new(0, '1', Movement::Left)),
+            (TransitionKey::new(1, '1'), TransitionValue::halt('_', Movement::Left)),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_a_start_without_transitions() {
+        let transitions = Transitions::new()
+            .insert(TransitionKey::new(1, '_'), TransitionValue::new(2, '1', Movement::Right));
+
+        let (pruned, renumbering) = prune(&transitions, &7);
+
+        assert!(pruned.is_empty());
+        assert_eq!(renumbering.states(), &[7]);
+    }
+
+    proptest! {
+        #[test]
+        fn pruned_machines_should_behave_like_the_original(
+                transitions in transitions(0u8..6, any::<bool>(), next(0u8..6), movement(), 0..20)) {
+            let (pruned, renumbering) = prune(&transitions, &0);
+            prop_assert!(pruned.len() <= 2 * renumbering.len());
+
+            let mut original = Machine::new(0, ConcreteTape::empty(false), Rc::new(HashTransitions::from(&transitions)));
+            let mut rewritten = Machine::new(0, ConcreteTape::empty(false), Rc::new(HashTransitions::from(&pruned)));
+            for _ in 0..50 {
+                let state = original.step();
+                prop_assert_eq!(state.map(|next| next.map(|q| renumbering.number(&q))), rewritten.step().map(|next| next.map(Some)));
+                prop_assert_eq!(original.tape().to_vec(), rewritten.tape().to_vec());
+            }
+            for (key, value) in pruned.iter() {
+                let key = TransitionKey::new(*renumbering.state(key.state).expect("a kept state"), key.symbol);
+                prop_assert_eq!(transitions.lookup(&key).map(|value| value.symbol), Some(value.symbol));
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Running a Turing machine until it stops.
+//!
+//! A run ends when the machine enters one of its halting states, when there is
+//! no transition for the current state and symbol, or when the step limit is
+//! reached.
+
+use super::transition::Next;
+
+/// The reason a run of a Turing machine ended.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RunOutcome<Q, S> {
+    /// The machine entered one of its halting states: HALT, ERROR or one of
+    /// the states declared as halting.
+    Halted {
+        /// The number of steps taken before halting.
+        steps: u64,
+        /// The halting state the machine entered.
+        state: Next<Q>,
+    },
+    /// There is no transition for the state and the symbol being scanned.
+    UndefinedTransition {
+        /// The state the machine got stuck in.
+        state: Q,
+        /// The symbol the read/write head was scanning.
+        symbol: S,
+    },
+    /// The machine was still running when the step limit was reached.
+    StepLimitReached,
+}
@@ -0,0 +1,321 @@
+//! Standard reads and writes machines in the bbchallenge "standard TM format".
+//!
+//! The busy beaver community shares machines as compact strings like
+//! `1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA`. Every state, `A` being the start
+//! state, `B` the next and so on, is described by one group of transitions.
+//! Groups are separated by `_`. A group has one transition for every symbol,
+//! starting with the blank `0`. A transition is the symbol to write, the
+//! direction to move and the next state. The next state `Z` or `-` halts the
+//! machine and `---` marks a missing transition.
+//!
+//! States are numbered from 0 for `A` and symbols are the digits they are
+//! written as. Because states are letters, `Z` among them, and symbols are
+//! digits, the format itself can not describe more than 25 states or 10
+//! symbols.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use super::movement::Movement;
+use super::modifiable;
+use super::transition::{Next, Transitions, TransitionKey, TransitionValue};
+use super::unmodifiable;
+
+const STATES: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl Transitions<usize, u8> {
+    /// Parse transitions from the standard TM format.
+    pub fn from_standard(source: &str) -> Result<Transitions<usize, u8>, StandardError> {
+        let bytes = source.trim().as_bytes();
+        let offset = source.len() - source.trim_start().len();
+        let error = |position: usize, kind: StandardErrorKind| StandardError { position: offset + position, kind };
+
+        let rows: Vec<&[u8]> = bytes.split(|&b| b == b'_').collect();
+        let width = rows[0].len();
+        let symbols = width / 3;
+        if symbols == 0 || 3 * symbols != width {
+            return Err(error(width, StandardErrorKind::Expected("a transition of three characters")));
+        }
+        if rows.len() >= STATES.len() || symbols > 10 {
+            return Err(error(0, StandardErrorKind::TooLarge));
+        }
+
+        let mut transitions = Transitions::new();
+        let mut position = 0;
+        for (state, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(error(position + row.len().min(width), StandardErrorKind::RowLength));
+            }
+            for (symbol, triple) in row.chunks(3).enumerate() {
+                let at = position + 3 * symbol;
+                if triple == b"---" {
+                    continue;
+                }
+                let written = match triple[0] {
+                    digit @ b'0' ..= b'9' if ((digit - b'0') as usize) < symbols => digit - b'0',
+
+                    _ => return Err(error(at, StandardErrorKind::Expected("a symbol to write"))),
+                };
+                let movement = match triple[1] {
+                    b'L' => Movement::Left,
+
+                    b'R' => Movement::Right,
+
+                    _ => return Err(error(at + 1, StandardErrorKind::Expected("`L` or `R`"))),
+                };
+                let next = match triple[2] {
+                    letter @ b'A' ..= b'Z' if ((letter - b'A') as usize) < rows.len() => Next::State((letter - b'A') as usize),
+
+                    b'Z' | b'-' => Next::Halt,
+
+                    _ => return Err(error(at + 2, StandardErrorKind::Expected("a state letter"))),
+                };
+                transitions = transitions.insert(
+                    TransitionKey::new(state, symbol as u8),
+                    TransitionValue { next, symbol: written, movement });
+            }
+            position += width + 1;
+        }
+        Ok(transitions)
+    }
+
+    /// Write the transitions in the standard TM format. HALT is written as
+    /// `Z`.
+    ///
+    /// Fails for machines with more than 25 states or more than 10 symbols,
+    /// and for ERROR and `Stay` transitions, which the format can not express.
+    pub fn to_standard(&self) -> Result<String, StandardError> {
+        let mut states = 1;
+        let mut symbols = 2;
+        for (key, value) in self.iter() {
+            states = states.max(key.state + 1);
+            if let Next::State(next) = value.next {
+                states = states.max(next + 1);
+            }
+            symbols = symbols.max(key.symbol as usize + 1).max(value.symbol as usize + 1);
+        }
+        if states >= STATES.len() || symbols > 10 {
+            return Err(StandardError { position: 0, kind: StandardErrorKind::TooLarge });
+        }
+
+        let mut table = vec![None; states * symbols];
+        for (key, value) in self.iter() {
+            let cell = &mut table[key.state * symbols + key.symbol as usize];
+            if cell.is_none() {
+                *cell = Some(value);
+            }
+        }
+
+        let mut result = String::new();
+        for (index, value) in table.into_iter().enumerate() {
+            let (state, symbol) = (index / symbols, index % symbols);
+            if state > 0 && symbol == 0 {
+                result.push('_');
+            }
+            let value = match value {
+                Some(value) => value,
+
+                None => {
+                    result.push_str("---");
+                    continue;
+                }
+            };
+            let next = match value.next {
+                Next::State(next) => STATES[next],
+
+                Next::Halt => b'Z',
+
+                Next::Error => return Err(StandardError { position: result.len(), kind: StandardErrorKind::Inexpressible }),
+            };
+            let movement = match value.movement {
+                Movement::Left => 'L',
+
+                Movement::Right => 'R',
+
+                Movement::Stay => return Err(StandardError { position: result.len(), kind: StandardErrorKind::Inexpressible }),
+            };
+            result.push_str(&format!("{}{}{}", value.symbol, movement, next as char));
+        }
+        Ok(result)
+    }
+}
+
+impl FromStr for Transitions<usize, u8> {
+    type Err = StandardError;
+
+    fn from_str(source: &str) -> Result<Transitions<usize, u8>, StandardError> {
+        Transitions::from_standard(source)
+    }
+}
+
+impl modifiable::turing::Machine<usize, u8> {
+    /// Create a machine from the standard TM format. It starts in state `A` on
+    /// a blank tape.
+    pub fn from_standard(source: &str) -> Result<modifiable::turing::Machine<usize, u8>, StandardError> {
+        let transitions = Transitions::from_standard(source)?;
+        Ok(modifiable::turing::Machine::new(0, modifiable::tape::ConcreteTape::empty(0), transitions))
+    }
+
+    /// Write the transitions of the machine in the standard TM format, see
+    /// `Transitions::to_standard`.
+    pub fn to_standard(&self) -> Result<String, StandardError> {
+        self.transitions().to_standard()
+    }
+}
+
+impl unmodifiable::turing::Machine<usize, u8> {
+    /// Create a machine from the standard TM format. It starts in state `A` on
+    /// a blank tape.
+    pub fn from_standard(source: &str) -> Result<unmodifiable::turing::Machine<usize, u8>, StandardError> {
+        let transitions = Transitions::from_standard(source)?;
+        Ok(unmodifiable::turing::Machine::new(0, unmodifiable::tape::ConcreteTape::empty(0), transitions))
+    }
+
+    /// Write the transitions of the machine in the standard TM format, see
+    /// `Transitions::to_standard`.
+    pub fn to_standard(&self) -> Result<String, StandardError> {
+        self.transitions().to_standard()
+    }
+}
+
+/// Problem encountered while reading or writing the standard TM format.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StandardError {
+    /// The byte offset of the problem in the source, or in the output when
+    /// writing.
+    pub position: usize,
+    /// What the problem is.
+    pub kind: StandardErrorKind,
+}
+
+/// The kinds of problems encountered while reading or writing the standard TM
+/// format.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StandardErrorKind {
+    /// Something else was found than what was expected.
+    Expected(&'static str),
+    /// A state does not have a transition for every symbol.
+    RowLength,
+    /// More states or symbols than the format can express.
+    TooLarge,
+    /// A transition to ERROR or one that stays, which the format can not
+    /// express.
+    Inexpressible,
+}
+
+impl fmt::Display for StandardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}: ", self.position)?;
+        match self.kind {
+            StandardErrorKind::Expected(what) => write!(f, "expected {}", what),
+
+            StandardErrorKind::RowLength => write!(f, "every state needs a transition for every symbol"),
+
+            StandardErrorKind::TooLarge => write!(f, "the format has at most 25 states and 10 symbols"),
+
+            StandardErrorKind::Inexpressible => write!(f, "ERROR and staying can not be written"),
+        }
+    }
+}
+
+impl Error for StandardError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::transition::Lookup;
+    use super::super::run::RunOutcome;
+    use super::super::inspect::Inspect;
+
+    #[test]
+    fn should_parse_the_standard_format() {
+        let transitions = Transitions::from_standard("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").expect("a valid machine");
+
+        assert_eq!(transitions.len(), 8);
+        assert_eq!(
+            transitions.lookup(&TransitionKey::new(1, 1)),
+            Some(&TransitionValue::new(2, 0, Movement::Left)));
+        assert_eq!(
+            transitions.lookup(&TransitionKey::new(2, 0)),
+            Some(&TransitionValue::halt(1, Movement::Right)));
+    }
+
+    #[test]
+    fn should_skip_missing_transitions() {
+        let transitions: Transitions<usize, u8> = "1RB2LA1RA_2LA---1RA".parse().expect("a valid machine");
+
+        assert_eq!(transitions.len(), 5);
+        assert_eq!(transitions.lookup(&TransitionKey::new(1, 1)), None);
+        assert_eq!(
+            transitions.lookup(&TransitionKey::new(0, 1)),
+            Some(&TransitionValue::new(0, 2, Movement::Left)));
+    }
+
+    #[test]
+    fn should_round_trip() {
+        for source in &["1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA", "1RB2LA1RA_2LA---1RA", "1RB---_0LA1RZ"] {
+            let transitions = Transitions::from_standard(source).expect("a valid machine");
+
+            assert_eq!(transitions.to_standard(), Ok(source.to_string()));
+        }
+    }
+
+    #[test]
+    fn should_reject_what_the_format_can_not_express() {
+        let error = Transitions::new()
+            .insert(TransitionKey::new(0, 0), TransitionValue::new(1, 1, Movement::Right))
+            .insert(TransitionKey::new(1, 1), TransitionValue::error(1, Movement::Left));
+        let stay = Transitions::new()
+            .insert(TransitionKey::new(0, 1), TransitionValue::new(0, 1, Movement::Stay));
+        let large = Transitions::new()
+            .insert(TransitionKey::new(0, 0), TransitionValue::new(25, 1, Movement::Right));
+
+        assert_eq!(error.to_standard(), Err(StandardError { position: 10, kind: StandardErrorKind::Inexpressible }));
+        assert_eq!(stay.to_standard(), Err(StandardError { position: 3, kind: StandardErrorKind::Inexpressible }));
+        assert_eq!(large.to_standard(), Err(StandardError { position: 0, kind: StandardErrorKind::TooLarge }));
+        assert_eq!(
+            Transitions::from_standard(&vec!["1RA1RA"; 26].join("_")),
+            Err(StandardError { position: 0, kind: StandardErrorKind::TooLarge }));
+        assert!(Transitions::from_standard(&vec!["1RA1RA"; 25].join("_")).is_ok());
+    }
+
+    #[test]
+    fn should_report_the_position_of_errors() {
+        assert_eq!(
+            Transitions::from_standard("1RB1LB_1LA0XC"),
+            Err(StandardError { position: 11, kind: StandardErrorKind::Expected("`L` or `R`") }));
+        assert_eq!(
+            Transitions::from_standard("1RB1LB_1LA"),
+            Err(StandardError { position: 10, kind: StandardErrorKind::RowLength }));
+        assert_eq!(
+            Transitions::from_standard("2RB1LB_1LA0LA"),
+            Err(StandardError { position: 0, kind: StandardErrorKind::Expected("a symbol to write") }));
+        assert_eq!(
+            Transitions::from_standard("1RB1LC_1LA0LZ"),
+            Err(StandardError { position: 5, kind: StandardErrorKind::Expected("a state letter") }));
+    }
+
+    #[test]
+    fn should_halt_on_z_and_dash() {
+        let transitions = Transitions::from_standard("1RB1LZ_1LA0L-").expect("a valid machine");
+
+        assert_eq!(transitions.lookup(&TransitionKey::new(0, 1)), Some(&TransitionValue::halt(1, Movement::Left)));
+        assert_eq!(transitions.lookup(&TransitionKey::new(1, 1)), Some(&TransitionValue::halt(0, Movement::Left)));
+    }
+
+    #[test]
+    fn should_write_machines_in_the_standard_format() {
+        let source = "1RB1LC_1RC1RB_1RD0LE_1LA1LD_1RZ0LA";
+
+        assert_eq!(modifiable::turing::Machine::from_standard(source).expect("a valid machine").to_standard(), Ok(source.to_string()));
+        assert_eq!(unmodifiable::turing::Machine::from_standard(source).expect("a valid machine").to_standard(), Ok(source.to_string()));
+    }
+
+    #[test]
+    fn should_run_the_four_state_busy_beaver() {
+        let mut machine = modifiable::turing::Machine::from_standard("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").expect("a valid machine");
+
+        assert_eq!(machine.run(1000), RunOutcome::Halted { steps: 107, state: Next::Halt });
+        assert_eq!(machine.tape().non_blank_count(), 13);
+    }
+}
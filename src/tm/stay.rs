@@ -0,0 +1,136 @@
+//! Stay rewrites a machine that keeps its head in place into one that doesn't.
+//!
+//! A transition that moves `Stay` is replaced by a transition that moves
+//! `Right` into a helper state. The helper state reads whatever symbol is
+//! there, leaves it untouched and moves `Left`, continuing where the original
+//! transition went. Every target of a `Stay` move gets one helper state.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use super::movement::Movement;
+use super::transition::{Next, Transitions, TransitionKey, TransitionValue};
+
+/// The states of a machine without `Stay` moves.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum StayFree<Q> {
+    /// A state of the original machine.
+    Original(Q),
+    /// A helper state that steps back to the left and continues with the
+    /// original target of a `Stay` move.
+    StepBack(Next<Q>),
+}
+
+/// Rewrite `transitions` into equivalent transitions that only move `Left`
+/// and `Right`.
+///
+/// The alphabet is needed to let the helper states step back over any symbol.
+/// It must hold every symbol that can be on the tape: a helper state has no
+/// transition for a symbol that is missing, so where the original machine
+/// continues, the rewritten machine can get stuck on an undefined transition.
+pub fn remove_stay<Q, S>(transitions: &Transitions<Q, S>, alphabet: &[S]) -> Transitions<StayFree<Q>, S> where Q: Clone + Eq + Hash, S: Clone {
+    let mut helpers: Vec<Next<Q>> = Vec::new();
+    let mut seen: HashSet<Next<Q>> = HashSet::new();
+    let mut result = Transitions::new();
+
+    let original: Vec<_> = transitions.iter().collect();
+    for &(key, value) in original.iter().rev() {
+        let key = TransitionKey::new(StayFree::Original(key.state.clone()), key.symbol.clone());
+        let value = match value.movement {
+            Movement::Stay => {
+                if seen.insert(value.next.clone()) {
+                    helpers.push(value.next.clone());
+                }
+                TransitionValue {
+                    next: Next::State(StayFree::StepBack(value.next.clone())),
+                    symbol: value.symbol.clone(),
+                    movement: Movement::Right,
+                }
+            }
+
+            movement => TransitionValue {
+                next: value.next.clone().map(StayFree::Original),
+                symbol: value.symbol.clone(),
+                movement,
+            },
+        };
+        result = result.insert(key, value);
+    }
+
+    for next in helpers {
+        for symbol in alphabet {
+            result = result.insert(
+                TransitionKey::new(StayFree::StepBack(next.clone()), symbol.clone()),
+                TransitionValue {
+                    next: next.clone().map(StayFree::Original),
+                    symbol: symbol.clone(),
+                    movement: Movement::Left,
+                });
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::inspect::Inspect;
+    use super::super::modifiable::tape::TapeBuilder;
+    use super::super::modifiable::turing::Machine;
+    use super::super::run::RunOutcome;
+    use super::super::transition::Lookup;
+
+    fn with_stay() -> Transitions<u32, char> {
+        Transitions::new()
+            .insert(
+                TransitionKey::new(0, '_'),
+                TransitionValue::new(1, 'a', Movement::Stay))
+            .insert(
+                TransitionKey::new(1, 'a'),
+                TransitionValue::new(2, 'b', Movement::Right))
+            .insert(
+                TransitionKey::new(2, '_'),
+                TransitionValue::new(3, 'c', Movement::Left))
+            .insert(
+                TransitionKey::new(3, 'b'),
+                TransitionValue::halt('d', Movement::Stay))
+    }
+
+    #[test]
+    fn should_only_move_left_and_right() {
+        let transitions = remove_stay(&with_stay(), &['_', 'a', 'b', 'c', 'd']);
+
+        assert!(transitions.iter().all(|(_, value)| value.movement != Movement::Stay));
+        assert_eq!(transitions.len(), 4 + 2 * 5);
+        assert_eq!(
+            transitions.lookup(&TransitionKey::new(StayFree::StepBack(Next::State(1)), 'c')),
+            Some(&TransitionValue::new(StayFree::Original(1), 'c', Movement::Left)));
+    }
+
+    #[test]
+    fn should_behave_like_the_original_machine() {
+        let mut original = Machine::new(0, TapeBuilder::with_blank('_').build(), with_stay());
+        let mut rewritten = Machine::new(
+            StayFree::Original(0),
+            TapeBuilder::with_blank('_').build(),
+            remove_stay(&with_stay(), &['_', 'a', 'b', 'c', 'd']));
+
+        assert_eq!(original.run(100), RunOutcome::Halted { steps: 4, state: Next::Halt });
+        assert_eq!(rewritten.run(100), RunOutcome::Halted { steps: 6, state: Next::Halt });
+
+        assert_eq!(original.tape().to_vec(), rewritten.tape().to_vec());
+        assert_eq!(original.tape().head_position(), rewritten.tape().head_position());
+    }
+
+    #[test]
+    fn should_get_stuck_on_symbols_missing_from_the_alphabet() {
+        let mut original = Machine::new(0, TapeBuilder::with_blank('_').build(), with_stay());
+        let mut rewritten = Machine::new(
+            StayFree::Original(0),
+            TapeBuilder::with_blank('_').build(),
+            remove_stay(&with_stay(), &['_', 'a', 'b', 'd']));
+
+        assert_eq!(original.run(100), RunOutcome::Halted { steps: 4, state: Next::Halt });
+        assert_eq!(rewritten.run(100), RunOutcome::UndefinedTransition { state: StayFree::StepBack(Next::Halt), symbol: 'c' });
+    }
+}
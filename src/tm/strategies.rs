@@ -0,0 +1,38 @@
+//! Strategies shared by the property tests of the `tm` modules.
+
+use std::fmt::Debug;
+use std::ops::Range;
+use proptest::prelude::*;
+use super::movement::Movement;
+use super::transition::{Next, Transitions, TransitionKey, TransitionValue};
+
+/// Moving `Left` or `Right`.
+pub fn movement() -> impl Strategy<Value = Movement> {
+    prop_oneof![Just(Movement::Left), Just(Movement::Right)]
+}
+
+/// Moving `Left` or `Right`, or staying.
+pub fn movement_or_stay() -> impl Strategy<Value = Movement> {
+    prop_oneof![Just(Movement::Left), Just(Movement::Right), Just(Movement::Stay)]
+}
+
+/// One of the states of `state`, or now and then HALT.
+pub fn next<Q>(state: impl Strategy<Value = Q>) -> impl Strategy<Value = Next<Q>> where Q: Clone + Debug {
+    prop_oneof![4 => state.prop_map(Next::State), 1 => Just(Next::Halt)]
+}
+
+/// Transitions for a number of keys in `entries`, which may repeat. Each key
+/// is a state of `state` and a symbol of `symbol`, and goes to `next`,
+/// writing a symbol of `symbol` and moving as `movement` does.
+pub fn transitions<Q, S>(
+        state: impl Strategy<Value = Q>,
+        symbol: impl Strategy<Value = S> + Clone,
+        next: impl Strategy<Value = Next<Q>>,
+        movement: impl Strategy<Value = Movement>,
+        entries: Range<usize>) -> impl Strategy<Value = Transitions<Q, S>> where Q: Clone + Debug, S: Clone + Debug {
+    prop::collection::vec((state, symbol.clone(), next, symbol, movement), entries).prop_map(|entries| {
+        entries.into_iter().fold(Transitions::new(), |transitions, (state, read, next, written, movement)| {
+            transitions.insert(TransitionKey::new(state, read), TransitionValue { next, symbol: written, movement })
+        })
+    })
+}
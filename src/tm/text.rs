@@ -0,0 +1,585 @@
+//! Text reads and writes two-symbol machines in a plain state-table format.
+//!
+//! This is the format of the compiled `.tm` and `.tm2` files. A file starts
+//! with the number of states, followed by one block per state. The first block
+//! describes the start state.
+//!
+//! ```text
+//! States: 2
+//!
+//! a:
+//! _ -> b; R; 1
+//! 1 -> HALT; L; 1
+//!
+//! b:
+//! _ -> a; L; 1
+//! 1 -> ERROR; -; _
+//! ```
+//!
+//! Every transition line reads: the symbol being scanned, the next state, the
+//! direction to move (`L`, `R` or `-` to stay) and the symbol to write. The
+//! symbols are `_`, the blank, and `1`. `HALT` and `ERROR` are reserved for
+//! the halting states. Everything after a `#` is a comment. A state without
+//! transitions is written as a header without lines.
+//!
+//! State names that are not plain words of letters, digits and `_`, or that
+//! are `HALT` or `ERROR`, are written between double quotes, with `\\`, `\"`,
+//! `\n` and `\r` escaped.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use super::Bit;
+use super::movement::Movement;
+use super::transition::{Next, Transitions, TransitionsBuilder, TransitionError, TransitionKey, TransitionValue};
+
+/// A two-symbol machine as described by a state table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StateTable {
+    /// All states in the order they are described; the first is the start state.
+    pub states: Vec<String>,
+    /// The transitions of the machine.
+    pub transitions: Transitions<String, Bit>,
+}
+
+impl StateTable {
+    /// Create a `StateTable` for `transitions` that starts in `start`.
+    ///
+    /// The states are ordered by their first appearance in the transitions,
+    /// oldest first.
+    pub fn new(start: String, transitions: Transitions<String, Bit>) -> StateTable {
+        let mut states = vec![start];
+        {
+            let ordered: Vec<_> = transitions.iter().collect();
+            for &(key, value) in ordered.iter().rev() {
+                let targets = match value.next {
+                    Next::State(ref state) => vec![&key.state, state],
+
+                    Next::Halt | Next::Error => vec![&key.state],
+                };
+                for state in targets {
+                    if !states.contains(state) {
+                        states.push(state.clone());
+                    }
+                }
+            }
+        }
+        StateTable { states, transitions }
+    }
+
+    /// The state the machine starts in.
+    pub fn start(&self) -> &str {
+        &self.states[0]
+    }
+}
+
+impl fmt::Display for StateTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut values = HashMap::new();
+        for (key, value) in self.transitions.iter() {
+            values.entry((&key.state, key.symbol)).or_insert(value);
+        }
+        writeln!(f, "States: {}", self.states.len())?;
+        for state in &self.states {
+            writeln!(f)?;
+            writeln!(f, "{}:", name(state))?;
+            for &symbol in &[false, true] {
+                if let Some(value) = values.get(&(state, symbol)) {
+                    let next = match value.next {
+                        Next::State(ref state) => name(state),
+
+                        Next::Halt => Cow::from(HALT),
+
+                        Next::Error => Cow::from(ERROR),
+                    };
+                    let movement = match value.movement {
+                        Movement::Left => "L",
+
+                        Movement::Right => "R",
+
+                        Movement::Stay => "-",
+                    };
+                    writeln!(f, "{} -> {}; {}; {}", show(symbol), next, movement, show(value.symbol))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const HALT: &str = "HALT";
+const ERROR: &str = "ERROR";
+
+fn show(symbol: Bit) -> &'static str {
+    if symbol { "1" } else { "_" }
+}
+
+/// `state` as it is written in a state table, quoted when it is not a word.
+fn name(state: &str) -> Cow<'_, str> {
+    if !state.is_empty() && state.chars().all(is_word) && state != HALT && state != ERROR {
+        return Cow::from(state);
+    }
+    let mut quoted = String::from("\"");
+    for c in state.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+
+            '"' => quoted.push_str("\\\""),
+
+            '\n' => quoted.push_str("\\n"),
+
+            '\r' => quoted.push_str("\\r"),
+
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::from(quoted)
+}
+
+/// Problem encountered while parsing a state table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    /// The line of the problem, starting at 1.
+    pub line: usize,
+    /// The column of the problem, starting at 1.
+    pub column: usize,
+    /// What the problem is.
+    pub kind: ParseErrorKind,
+}
+
+/// The kinds of problems encountered while parsing a state table.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseErrorKind {
+    /// Something else was found than what was expected.
+    Expected(&'static str),
+    /// The header does not match the number of described states.
+    StateCount {
+        /// The number of states in the header.
+        declared: usize,
+        /// The number of states described.
+        described: usize,
+    },
+    /// A state is described twice.
+    DuplicateState(String),
+    /// A transition refers to a state that is not described.
+    UnknownState(String),
+    /// A state has two transitions for the same symbol.
+    DuplicateTransition,
+    /// `HALT` or `ERROR` is used as the name of a state.
+    ReservedName(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match self.kind {
+            ParseErrorKind::Expected(what) => write!(f, "expected {}", what),
+
+            ParseErrorKind::StateCount { declared, described } =>
+                write!(f, "{} states declared but {} described", declared, described),
+
+            ParseErrorKind::DuplicateState(ref state) => write!(f, "state {} is described twice", state),
+
+            ParseErrorKind::UnknownState(ref state) => write!(f, "state {} is not described", state),
+
+            ParseErrorKind::DuplicateTransition => write!(f, "symbol already has a transition in this state"),
+
+            ParseErrorKind::ReservedName(ref name) => write!(f, "{} can not be used as a state", name),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token<'a> {
+    Word(&'a str),
+    Quoted(String),
+    Colon,
+    Semicolon,
+    Arrow,
+    Dash,
+}
+
+struct Line<'a> {
+    number: usize,
+    tokens: Vec<(usize, Token<'a>)>,
+    end: usize,
+}
+
+impl<'a> Line<'a> {
+    fn tokenize(number: usize, text: &'a str) -> Result<Line<'a>, ParseError> {
+        let mut tokens = Vec::new();
+        let mut comment = text.len();
+        let mut chars = text.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            let token = match c {
+                '#' => {
+                    comment = index;
+                    break;
+                }
+
+                ':' => Token::Colon,
+
+                ';' => Token::Semicolon,
+
+                '-' if text[index + 1..].starts_with('>') => {
+                    chars.next();
+                    Token::Arrow
+                }
+
+                '-' => Token::Dash,
+
+                '"' => {
+                    let error = |kind| ParseError { line: number, column: column(text, index), kind };
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, '\\')) => name.push('\\'),
+
+                                Some((_, '"')) => name.push('"'),
+
+                                Some((_, 'n')) => name.push('\n'),
+
+                                Some((_, 'r')) => name.push('\r'),
+
+                                _ => return Err(error(ParseErrorKind::Expected("`\\\\`, `\\\"`, `\\n` or `\\r`"))),
+                            },
+
+                            Some((_, c)) => name.push(c),
+
+                            None => return Err(error(ParseErrorKind::Expected("closing `\"`"))),
+                        }
+                    }
+                    Token::Quoted(name)
+                }
+
+                c if is_word(c) => {
+                    let mut end = index + c.len_utf8();
+                    while let Some(&(next, c)) = chars.peek() {
+                        if !is_word(c) {
+                            break;
+                        }
+                        end = next + c.len_utf8();
+                        chars.next();
+                    }
+                    Token::Word(&text[index..end])
+                }
+
+                c if c.is_whitespace() => continue,
+
+                _ => return Err(ParseError { line: number, column: column(text, index), kind: ParseErrorKind::Expected("a word or punctuation") }),
+            };
+            tokens.push((column(text, index), token));
+        }
+        Ok(Line { number, tokens, end: column(text, text[..comment].trim_end().len()) })
+    }
+
+    fn error(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+        let column = self.tokens.get(position).map(|&(column, _)| column).unwrap_or(self.end);
+        ParseError { line: self.number, column, kind }
+    }
+
+    fn word(&self, position: usize, what: &'static str) -> Result<&'a str, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word(word))) => Ok(word),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected(what))),
+        }
+    }
+
+    /// The state name at `position`, a word or a quoted name.
+    fn name(&self, position: usize, what: &'static str) -> Result<String, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word(word))) => Ok(word.to_string()),
+
+            Some(&(_, Token::Quoted(ref name))) => Ok(name.clone()),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected(what))),
+        }
+    }
+
+    fn punctuation(&self, position: usize, expected: Token, what: &'static str) -> Result<(), ParseError> {
+        match self.tokens.get(position) {
+            Some((_, token)) if *token == expected => Ok(()),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected(what))),
+        }
+    }
+
+    fn symbol(&self, position: usize) -> Result<Bit, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word("_"))) => Ok(false),
+
+            Some(&(_, Token::Word("1"))) => Ok(true),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected("`_` or `1`"))),
+        }
+    }
+
+    fn movement(&self, position: usize) -> Result<Movement, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word("L"))) => Ok(Movement::Left),
+
+            Some(&(_, Token::Word("R"))) => Ok(Movement::Right),
+
+            Some(&(_, Token::Dash)) => Ok(Movement::Stay),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected("`L`, `R` or `-`"))),
+        }
+    }
+
+    fn end_of_line(&self, position: usize) -> Result<(), ParseError> {
+        if position < self.tokens.len() {
+            Err(self.error(position, ParseErrorKind::Expected("end of line")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The column of the character at byte `index` of `text`, starting at 1.
+fn column(text: &str, index: usize) -> usize {
+    text[..index].chars().count() + 1
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Parse a state table.
+pub fn parse(source: &str) -> Result<StateTable, ParseError> {
+    let mut lines = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = Line::tokenize(index + 1, text)?;
+        if !line.tokens.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    let mut lines = lines.into_iter();
+    let header = match lines.next() {
+        Some(header) => header,
+
+        None => return Err(ParseError { line: 1, column: 1, kind: ParseErrorKind::Expected("`States:` header") }),
+    };
+    if header.word(0, "`States:` header")? != "States" {
+        return Err(header.error(0, ParseErrorKind::Expected("`States:` header")));
+    }
+    header.punctuation(1, Token::Colon, "`:`")?;
+    let declared: usize = header.word(2, "number of states")?
+        .parse()
+        .map_err(|_| header.error(2, ParseErrorKind::Expected("number of states")))?;
+    header.end_of_line(3)?;
+
+    let mut states: Vec<String> = Vec::new();
+    let mut references: Vec<(String, ParseError)> = Vec::new();
+    let mut builder = TransitionsBuilder::new();
+    for line in lines {
+        if let Some(&(_, Token::Colon)) = line.tokens.get(1) {
+            if let Some(&(_, Token::Word(reserved @ (HALT | ERROR)))) = line.tokens.first() {
+                return Err(line.error(0, ParseErrorKind::ReservedName(reserved.to_string())));
+            }
+            let state = line.name(0, "state name")?;
+            if states.contains(&state) {
+                return Err(line.error(0, ParseErrorKind::DuplicateState(state)));
+            }
+            line.end_of_line(2)?;
+            states.push(state);
+            continue;
+        }
+
+        let state = match states.last() {
+            Some(state) => state.clone(),
+
+            None => return Err(line.error(0, ParseErrorKind::Expected("state header"))),
+        };
+        let symbol = line.symbol(0)?;
+        line.punctuation(1, Token::Arrow, "`->`")?;
+        let next = match line.tokens.get(2) {
+            Some(&(_, Token::Word(HALT))) => Next::Halt,
+
+            Some(&(_, Token::Word(ERROR))) => Next::Error,
+
+            _ => {
+                let target = line.name(2, "next state")?;
+                references.push((target.clone(), line.error(2, ParseErrorKind::UnknownState(target.clone()))));
+                Next::State(target)
+            }
+        };
+        line.punctuation(3, Token::Semicolon, "`;`")?;
+        let movement = line.movement(4)?;
+        line.punctuation(5, Token::Semicolon, "`;`")?;
+        let written = line.symbol(6)?;
+        line.end_of_line(7)?;
+
+        let value = TransitionValue { next, symbol: written, movement };
+        builder = builder
+            .with_transition(TransitionKey::new(state, symbol), value)
+            .map_err(|error| match error {
+                TransitionError::Duplicate(_) | TransitionError::Conflict(_) =>
+                    line.error(0, ParseErrorKind::DuplicateTransition),
+            })?;
+    }
+
+    for (target, error) in references {
+        if !states.contains(&target) {
+            return Err(error);
+        }
+    }
+    if states.is_empty() || states.len() != declared {
+        return Err(header.error(2, ParseErrorKind::StateCount { declared, described: states.len() }));
+    }
+
+    Ok(StateTable { states, transitions: builder.build() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use super::super::strategies::{movement_or_stay, transitions};
+    use super::super::transition::Lookup;
+
+    const EXAMPLE: &str = "States: 3
+
+a:
+_ -> b; R; 1
+1 -> HALT; L; 1
+
+b:
+_ -> a; L; 1
+1 -> c; -; _
+
+c:
+";
+
+    #[test]
+    fn should_parse_a_state_table() {
+        let table = parse(EXAMPLE).expect("a valid table");
+
+        assert_eq!(table.states, vec!["a", "b", "c"]);
+        assert_eq!(table.start(), "a");
+        assert_eq!(table.transitions.len(), 4);
+        assert_eq!(
+            table.transitions.lookup(&TransitionKey::new("b".to_string(), true)),
+            Some(&TransitionValue::new("c".to_string(), false, Movement::Stay)));
+        assert_eq!(
+            table.transitions.lookup(&TransitionKey::new("a".to_string(), true)),
+            Some(&TransitionValue::halt(true, Movement::Left)));
+    }
+
+    #[test]
+    fn should_round_trip() {
+        let table = parse(EXAMPLE).expect("a valid table");
+
+        assert_eq!(table.to_string(), EXAMPLE);
+        assert_eq!(parse(&table.to_string()), Ok(table));
+    }
+
+    #[test]
+    fn should_ignore_comments_and_whitespace() {
+        let table = parse("# a tiny machine\nStates: 1\n  start:   # the only state\n 1->ERROR ;R; _\n").expect("a valid table");
+
+        assert_eq!(table.to_string(), "States: 1\n\nstart:\n1 -> ERROR; R; _\n");
+    }
+
+    #[test]
+    fn should_describe_transitions_in_order_of_appearance() {
+        let transitions = Transitions::new()
+            .insert(
+                TransitionKey::new("x".to_string(), false),
+                TransitionValue::new("y".to_string(), true, Movement::Right))
+            .insert(
+                TransitionKey::new("y".to_string(), false),
+                TransitionValue::error(false, Movement::Left));
+
+        let table = StateTable::new("x".to_string(), transitions);
+
+        assert_eq!(table.to_string(), "States: 2\n\nx:\n_ -> y; R; 1\n\ny:\n_ -> ERROR; L; _\n");
+    }
+
+    #[test]
+    fn should_report_line_and_column() {
+        let error = parse("States: 1\n\na:\n_ -> a; X; 1\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 4, column: 9, kind: ParseErrorKind::Expected("`L`, `R` or `-`") });
+        assert_eq!(error.to_string(), "4:9: expected `L`, `R` or `-`");
+    }
+
+    #[test]
+    fn should_count_columns_in_characters() {
+        let error = parse("States: 1\n\"é\":\n_ -> \"é\"; X; 1\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 3, column: 11, kind: ParseErrorKind::Expected("`L`, `R` or `-`") });
+    }
+
+    #[test]
+    fn should_reject_unknown_states() {
+        let error = parse("States: 1\na:\n_ -> b; R; 1\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 3, column: 6, kind: ParseErrorKind::UnknownState("b".to_string()) });
+    }
+
+    #[test]
+    fn should_reject_duplicate_transitions() {
+        let error = parse("States: 1\na:\n_ -> a; R; 1\n_ -> a; L; 1\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 4, column: 1, kind: ParseErrorKind::DuplicateTransition });
+    }
+
+    #[test]
+    fn should_reject_wrong_state_count() {
+        let error = parse("States: 2\na:\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 1, column: 9, kind: ParseErrorKind::StateCount { declared: 2, described: 1 } });
+    }
+
+    #[test]
+    fn should_quote_names_that_are_not_words() {
+        let transitions = Transitions::new()
+            .insert(
+                TransitionKey::new("HALT".to_string(), false),
+                TransitionValue::new("a \"b\"\n#c".to_string(), true, Movement::Right));
+
+        let table = StateTable::new("HALT".to_string(), transitions);
+
+        assert_eq!(table.to_string(), "States: 2\n\n\"HALT\":\n_ -> \"a \\\"b\\\"\\n#c\"; R; 1\n\n\"a \\\"b\\\"\\n#c\":\n");
+        assert_eq!(parse(&table.to_string()), Ok(table));
+    }
+
+    #[test]
+    fn should_reject_unterminated_names() {
+        let error = parse("States: 1\n\"a:\n").unwrap_err();
+
+        assert_eq!(error, ParseError { line: 2, column: 1, kind: ParseErrorKind::Expected("closing `\"`") });
+    }
+
+    fn next() -> impl Strategy<Value = Next<String>> {
+        prop_oneof![Just(Next::Halt), Just(Next::Error), any::<String>().prop_map(Next::State)]
+    }
+
+    proptest! {
+        #[test]
+        fn printed_tables_should_parse_back(
+                start in any::<String>(),
+                transitions in transitions(any::<String>(), any::<bool>(), next(), movement_or_stay(), 0..8)) {
+            let table = StateTable::new(start, transitions);
+
+            let parsed = parse(&table.to_string()).expect("a printed table");
+            prop_assert_eq!(&parsed.states, &table.states);
+            for state in &table.states {
+                for &symbol in &[false, true] {
+                    let key = TransitionKey::new(state.clone(), symbol);
+                    prop_assert_eq!(parsed.transitions.lookup(&key), table.transitions.lookup(&key));
+                }
+            }
+            prop_assert_eq!(parsed.to_string(), table.to_string());
+        }
+    }
+}
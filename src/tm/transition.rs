@@ -4,12 +4,19 @@
 //! reading from the tape, a transition tells what state comes next, what symbol
 //! to write and which way to move the tape.
 
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
 use super::movement::Movement;
 
 /// `TransitionKey` describe the current context of the Turing machine. I.e. the
 /// state the Turing machine is in and the symbol the read/write head is
 /// scanning.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct TransitionKey<Q, S> {
     /// The state the Turing machine is in.
     pub state: Q,
@@ -20,17 +27,51 @@ pub struct TransitionKey<Q, S> {
 impl <Q, S> TransitionKey<Q, S> {
     /// Create a `TransitionKey`
     pub fn new(state: Q, symbol: S) -> TransitionKey<Q, S> {
-        TransitionKey { state: state, symbol: symbol }
+        TransitionKey { state, symbol }
+    }
+}
+
+/// `Next` tells where a transition leads: either another state of the Turing
+/// machine or one of the halting states HALT and ERROR.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Next<Q> {
+    /// Continue in the given state.
+    State(Q),
+    /// Halt, the computation finished.
+    Halt,
+    /// Halt, the computation failed.
+    Error,
+}
+
+impl <Q> Next<Q> {
+    /// Whether this is HALT or ERROR, i.e. one of the halting states.
+    pub fn is_final(&self) -> bool {
+        match *self {
+            Next::State(_) => false,
+
+            Next::Halt | Next::Error => true,
+        }
+    }
+
+    /// Apply `f` to the state, keeping HALT and ERROR as they are.
+    pub fn map<R, F>(self, f: F) -> Next<R> where F: FnOnce(Q) -> R {
+        match self {
+            Next::State(state) => Next::State(f(state)),
+
+            Next::Halt => Next::Halt,
+
+            Next::Error => Next::Error,
+        }
     }
 }
 
 /// `TransitionValue` describes the next context of the Turing machine, I.e. the
 /// state is should transition in, the symbol that is should write and the
 /// movement of the tape.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct TransitionValue<Q, S> where Q: Clone, S: Clone {
-    /// The state the Turing machine will be in after the transition.
-    pub state: Q,
+    /// Where the Turing machine will be after the transition.
+    pub next: Next<Q>,
     /// The symbol to write in the cell the read/write head is scanning.
     pub symbol: S,
     /// The direction the tape is moving in after the transition.
@@ -38,16 +79,28 @@ pub struct TransitionValue<Q, S> where Q: Clone, S: Clone {
 }
 
 impl <Q, S> TransitionValue<Q, S> where Q: Clone, S: Clone {
-    /// Create a `TransitionValue`
+    /// Create a `TransitionValue` that continues in `state`
     pub fn new(state: Q, symbol: S, movement: Movement) -> TransitionValue<Q, S> {
-        TransitionValue { state: state, symbol: symbol, movement: movement }
+        TransitionValue { next: Next::State(state), symbol, movement }
+    }
+
+    /// Create a `TransitionValue` that halts after writing and moving
+    pub fn halt(symbol: S, movement: Movement) -> TransitionValue<Q, S> {
+        TransitionValue { next: Next::Halt, symbol, movement }
+    }
+
+    /// Create a `TransitionValue` that errors after writing and moving
+    pub fn error(symbol: S, movement: Movement) -> TransitionValue<Q, S> {
+        TransitionValue { next: Next::Error, symbol, movement }
     }
 }
 
 /// Transitions are used to describe the entire operation of a Turing machine.
 ///
 /// A Turing machine is defined by the transitions it can make.
-#[derive(Clone, Debug)]
+///
+/// A key can be inserted more than once. Then the most recently inserted
+/// transition is the one that counts, and the one `lookup` finds.
 pub enum Transitions<Q, S> where Q: Clone, S: Clone {
     /// Head of the linked list
     Transition(TransitionKey<Q,S>, TransitionValue<Q,S>, Box<Transitions<Q,S>>),
@@ -68,10 +121,179 @@ impl <Q, S> Transitions<Q, S> where Q: Clone, S: Clone {
 
     /// The number of transitions
     pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Whether there are no transitions at all
+    pub fn is_empty(&self) -> bool {
+        match *self {
+            Transitions::NoTransition => true,
+
+            Transitions::Transition(..) => false,
+        }
+    }
+
+    /// Iterate over the transitions, most recently inserted first.
+    pub fn iter(&self) -> Iter<'_, Q, S> {
+        Iter { transitions: self }
+    }
+}
+
+impl <Q, S> Default for Transitions<Q, S> where Q: Clone, S: Clone {
+    fn default() -> Transitions<Q, S> {
+        Transitions::new()
+    }
+}
+
+/// Unlinks the transitions one at a time, so that dropping the long lists of
+/// generated machines does not overflow the stack.
+impl <Q, S> Drop for Transitions<Q, S> where Q: Clone, S: Clone {
+    fn drop(&mut self) {
+        let mut rest = match *self {
+            Transitions::Transition(_, _, ref mut next) => mem::replace(&mut **next, Transitions::NoTransition),
+
+            Transitions::NoTransition => return,
+        };
+        while let Transitions::Transition(_, _, ref mut next) = rest {
+            let following = mem::replace(&mut **next, Transitions::NoTransition);
+            rest = following;
+        }
+    }
+}
+
+/// Copies the transitions one at a time, for the same reason as `Drop`.
+impl <Q, S> Clone for Transitions<Q, S> where Q: Clone, S: Clone {
+    fn clone(&self) -> Transitions<Q, S> {
+        let entries: Vec<_> = self.iter().collect();
+        entries.into_iter().rev().fold(Transitions::new(), |transitions, (key, value)| transitions.insert(key.clone(), value.clone()))
+    }
+}
+
+impl <Q, S> PartialEq for Transitions<Q, S> where Q: Clone + PartialEq, S: Clone + PartialEq {
+    fn eq(&self, other: &Transitions<Q, S>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl <Q, S> Eq for Transitions<Q, S> where Q: Clone + Eq, S: Clone + Eq {}
+
+/// Lists the transitions, most recently inserted first.
+impl <Q, S> fmt::Debug for Transitions<Q, S> where Q: Clone + fmt::Debug, S: Clone + fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Builder for `Transitions` that guarantees the resulting machine is
+/// deterministic.
+///
+/// Unlike `Transitions::insert`, adding a transition for a key that is already
+/// present is an error. Use `overwrite` to replace a transition on purpose.
+///
+/// # Example
+/// ```
+/// use parsimony::tm::movement::Movement;
+/// use parsimony::tm::transition::{TransitionsBuilder, TransitionKey, TransitionValue, TransitionError};
+///
+/// let result = TransitionsBuilder::new()
+///     .with_transition(TransitionKey::new(0, "_"), TransitionValue::new(1, "I", Movement::Right))
+///     .and_then(|builder| builder.with_transition(
+///         TransitionKey::new(0, "_"), TransitionValue::new(0, "_", Movement::Left)));
+///
+/// assert_eq!(result.err(), Some(TransitionError::Conflict(TransitionKey::new(0, "_"))));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TransitionsBuilder<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    order: Vec<TransitionKey<Q, S>>,
+    table: HashMap<TransitionKey<Q, S>, TransitionValue<Q, S>>,
+}
+
+impl <Q, S> TransitionsBuilder<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    /// Create a builder without any transitions.
+    pub fn new() -> TransitionsBuilder<Q, S> {
+        TransitionsBuilder { order: Vec::new(), table: HashMap::new() }
+    }
+
+    /// Add a transition, failing when the key already has a transition.
+    pub fn with_transition(mut self, key: TransitionKey<Q, S>, value: TransitionValue<Q, S>) -> Result<TransitionsBuilder<Q, S>, TransitionError<Q, S>> {
+        match self.table.get(&key) {
+            Some(existing) if *existing == value => Err(TransitionError::Duplicate(key)),
+
+            Some(_) => Err(TransitionError::Conflict(key)),
+
+            None => {
+                self.order.push(key.clone());
+                self.table.insert(key, value);
+                Ok(self)
+            }
+        }
+    }
+
+    /// Add a transition, replacing any transition the key already has.
+    pub fn overwrite(mut self, key: TransitionKey<Q, S>, value: TransitionValue<Q, S>) -> TransitionsBuilder<Q, S> {
+        if !self.table.contains_key(&key) {
+            self.order.push(key.clone());
+        }
+        self.table.insert(key, value);
+        self
+    }
+
+    /// Build the described transitions.
+    pub fn build(mut self) -> Transitions<Q, S> {
+        let mut transitions = Transitions::new();
+        for key in self.order {
+            let value = self.table.remove(&key).expect("every ordered key to be in the table");
+            transitions = transitions.insert(key, value);
+        }
+        transitions
+    }
+}
+
+impl <Q, S> Default for TransitionsBuilder<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    fn default() -> TransitionsBuilder<Q, S> {
+        TransitionsBuilder::new()
+    }
+}
+
+/// Reasons why `TransitionsBuilder` refuses a transition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TransitionError<Q, S> {
+    /// The exact same transition was already added for this key.
+    Duplicate(TransitionKey<Q, S>),
+    /// A different transition was already added for this key.
+    Conflict(TransitionKey<Q, S>),
+}
+
+impl <Q, S> fmt::Display for TransitionError<Q, S> where Q: fmt::Debug, S: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Transitions::NoTransition => 0,
+            TransitionError::Duplicate(ref key) =>
+                write!(f, "duplicate transition for state {:?} reading {:?}", key.state, key.symbol),
+
+            TransitionError::Conflict(ref key) =>
+                write!(f, "conflicting transitions for state {:?} reading {:?}", key.state, key.symbol),
+        }
+    }
+}
+
+impl <Q, S> Error for TransitionError<Q, S> where Q: fmt::Debug, S: fmt::Debug {}
 
-            Transitions::Transition(_, _, ref next) => 1 + next.len()
+/// Iterator over the key/value pairs of `Transitions`.
+pub struct Iter<'a, Q, S> where Q: Clone + 'a, S: Clone + 'a {
+    transitions: &'a Transitions<Q, S>,
+}
+
+impl <'a, Q, S> Iterator for Iter<'a, Q, S> where Q: Clone, S: Clone {
+    type Item = (&'a TransitionKey<Q, S>, &'a TransitionValue<Q, S>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self.transitions {
+            Transitions::Transition(ref key, ref value, ref other_transitions) => {
+                self.transitions = other_transitions;
+                Some((key, value))
+            }
+
+            Transitions::NoTransition => None
         }
     }
 }
@@ -81,24 +303,171 @@ impl <Q, S> Transitions<Q, S> where Q: Clone, S: Clone {
 /// This trait specifies the contract any collection of Transitions should adhere to.
 pub trait Lookup<Q, S> where Q: Clone, S: Clone {
     /// Lookup a specific `TransitionKey` in self.
-    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<TransitionValue<Q, S>>;
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>>;
 }
 
 
 impl <Q, S> Lookup<Q,S> for Transitions<Q, S> where Q: Clone + Eq, S: Clone + Eq {
     /// Lookup a `TransitionKey`
-    fn lookup(&self, target: &TransitionKey<Q, S>) -> Option<TransitionValue<Q, S>> {
-        match *self {
-            Transitions::Transition(ref key, ref value, ref other_transitions) => {
-                if *key == *target {
-                    Some(value.clone())
-                } else {
-                    other_transitions.lookup(target)
+    fn lookup(&self, target: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        self.iter()
+            .find(|&(key, _)| *key == *target)
+            .map(|(_, value)| value)
+    }
+}
+
+impl <Q, S, L> Lookup<Q, S> for &L where Q: Clone, S: Clone, L: Lookup<Q, S> + ?Sized {
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        (**self).lookup(key)
+    }
+}
+
+impl <Q, S, L> Lookup<Q, S> for Rc<L> where Q: Clone, S: Clone, L: Lookup<Q, S> + ?Sized {
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        (**self).lookup(key)
+    }
+}
+
+impl <Q, S, L> Lookup<Q, S> for Arc<L> where Q: Clone, S: Clone, L: Lookup<Q, S> + ?Sized {
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        (**self).lookup(key)
+    }
+}
+
+/// Transitions stored in a hash map, making a lookup take constant time.
+///
+/// When a key is inserted twice, the last value wins, just as it does for
+/// `Transitions`.
+#[derive(Clone, Debug)]
+pub struct HashTransitions<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    table: HashMap<TransitionKey<Q, S>, TransitionValue<Q, S>>,
+}
+
+impl <Q, S> HashTransitions<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    /// Create transitions
+    pub fn new() -> HashTransitions<Q, S> {
+        HashTransitions { table: HashMap::new() }
+    }
+
+    /// Insert a transition and return the new transitions
+    pub fn insert(mut self, key: TransitionKey<Q, S>, value: TransitionValue<Q, S>) -> HashTransitions<Q, S> {
+        self.table.insert(key, value);
+        self
+    }
+
+    /// The number of transitions
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether there are no transitions at all
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+}
+
+impl <Q, S> Default for HashTransitions<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    fn default() -> HashTransitions<Q, S> {
+        HashTransitions::new()
+    }
+}
+
+impl <'a, Q, S> From<&'a Transitions<Q, S>> for HashTransitions<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    fn from(transitions: &'a Transitions<Q, S>) -> HashTransitions<Q, S> {
+        let mut table = HashMap::with_capacity(transitions.len());
+        for (key, value) in transitions.iter() {
+            table.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        HashTransitions { table }
+    }
+}
+
+impl <Q, S> Lookup<Q, S> for HashTransitions<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash {
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        self.table.get(key)
+    }
+}
+
+/// Types that are small non-negative integers in disguise, and can therefore
+/// be used to index a `DenseTransitions` table.
+pub trait Ordinal {
+    /// The position of self in a zero based enumeration of its type.
+    fn ordinal(&self) -> usize;
+}
+
+macro_rules! ordinal_for_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Ordinal for $t {
+                fn ordinal(&self) -> usize {
+                    *self as usize
                 }
             }
+        )*
+    }
+}
 
-            Transitions::NoTransition => None
+ordinal_for_unsigned!(u8, u16, u32, u64, usize);
+
+impl Ordinal for bool {
+    fn ordinal(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Transitions stored in a vector, indexed by the ordinals of state and
+/// symbol.
+///
+/// This is the fastest lookup when states are numbered densely from zero and
+/// the alphabet is small.
+#[derive(Clone, Debug)]
+pub struct DenseTransitions<Q, S> where Q: Clone + Ordinal, S: Clone + Ordinal {
+    symbols: usize,
+    table: Vec<Option<TransitionValue<Q, S>>>,
+}
+
+impl <Q, S> DenseTransitions<Q, S> where Q: Clone + Ordinal, S: Clone + Ordinal {
+    /// Create transitions for an alphabet of `symbols` symbols, i.e. symbols
+    /// with ordinals `0..symbols`.
+    pub fn new(symbols: usize) -> DenseTransitions<Q, S> {
+        DenseTransitions { symbols, table: Vec::new() }
+    }
+
+    /// Insert a transition and return the new transitions
+    ///
+    /// # Panics
+    /// When the ordinal of the symbol falls outside of the alphabet.
+    pub fn insert(mut self, key: TransitionKey<Q, S>, value: TransitionValue<Q, S>) -> DenseTransitions<Q, S> {
+        let symbol = key.symbol.ordinal();
+        assert!(symbol < self.symbols, "symbol {} outside of alphabet of {} symbols", symbol, self.symbols);
+        let index = key.state.ordinal() * self.symbols + symbol;
+        if index >= self.table.len() {
+            self.table.resize(index + 1, None);
+        }
+        self.table[index] = Some(value);
+        self
+    }
+
+    /// The number of transitions
+    pub fn len(&self) -> usize {
+        self.table.iter().filter(|value| value.is_some()).count()
+    }
+
+    /// Whether there are no transitions at all
+    pub fn is_empty(&self) -> bool {
+        self.table.iter().all(|value| value.is_none())
+    }
+}
+
+impl <Q, S> Lookup<Q, S> for DenseTransitions<Q, S> where Q: Clone + Ordinal, S: Clone + Ordinal {
+    fn lookup(&self, key: &TransitionKey<Q, S>) -> Option<&TransitionValue<Q, S>> {
+        let symbol = key.symbol.ordinal();
+        if symbol >= self.symbols {
+            return None;
         }
+        self.table
+            .get(key.state.ordinal() * self.symbols + symbol)
+            .and_then(|value| value.as_ref())
     }
 }
 
@@ -144,10 +513,167 @@ mod tests {
 
         let value = transitions.lookup(&TransitionKey::new(0, "I"));
 
-        match value {
-            Some(_) => assert!(true),
+        assert!(value.is_some(), "value not found");
+    }
 
-            None => assert!(false, "value not found"),
-        }
+    #[test]
+    fn should_lookup_halting_transitions() {
+        let transitions: Transitions<u32, &str> = Transitions::new()
+            .insert(
+                TransitionKey::new(0, "I"),
+                TransitionValue::halt("I", Movement::Right))
+            .insert(
+                TransitionKey::new(0, "_"),
+                TransitionValue::error("_", Movement::Left));
+
+        assert_eq!(transitions.lookup(&TransitionKey::new(0, "I")).map(|value| value.next), Some(Next::Halt));
+        assert_eq!(transitions.lookup(&TransitionKey::new(0, "_")).map(|value| value.next), Some(Next::Error));
+    }
+
+    #[test]
+    fn should_iterate_most_recent_first() {
+        let transitions: Transitions<u32, &str> = Transitions::new()
+            .insert(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .insert(
+                TransitionKey::new(0, "_"),
+                TransitionValue::new(1, "I", Movement::Left));
+
+        let keys: Vec<_> = transitions.iter().map(|(key, _)| key.clone()).collect();
+
+        assert_eq!(keys, vec![TransitionKey::new(0, "_"), TransitionKey::new(0, "I")]);
+    }
+
+    #[test]
+    fn should_handle_long_lists() {
+        let transitions = (0..1_000_000).fold(Transitions::new(), |transitions, state: u32| transitions.insert(
+            TransitionKey::new(state, "_"),
+            TransitionValue::new(state + 1, "I", Movement::Right)));
+
+        assert_eq!(transitions.len(), 1_000_000);
+    }
+
+    #[test]
+    fn should_clone_compare_and_debug_long_lists() {
+        let transitions = (0..1_000_000).fold(Transitions::new(), |transitions, state: u32| transitions.insert(
+            TransitionKey::new(state, "_"),
+            TransitionValue::new(state + 1, "I", Movement::Right)));
+
+        let copy = transitions.clone();
+
+        assert_eq!(copy.iter().next(), transitions.iter().next());
+        assert!(copy == transitions);
+        assert!(copy != copy.clone().insert(TransitionKey::new(0, "I"), TransitionValue::halt("I", Movement::Left)));
+        assert!(format!("{:?}", copy).len() > 1_000_000);
+    }
+
+    #[test]
+    fn builder_should_keep_insertion_order() {
+        let transitions: Transitions<u32, &str> = TransitionsBuilder::new()
+            .with_transition(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .and_then(|builder| builder.with_transition(
+                TransitionKey::new(0, "_"),
+                TransitionValue::new(1, "I", Movement::Left)))
+            .expect("no conflicts")
+            .build();
+
+        let keys: Vec<_> = transitions.iter().map(|(key, _)| key.clone()).collect();
+
+        assert_eq!(keys, vec![TransitionKey::new(0, "_"), TransitionKey::new(0, "I")]);
+    }
+
+    #[test]
+    fn builder_should_reject_duplicate_transitions() {
+        let result: Result<TransitionsBuilder<u32, &str>, _> = TransitionsBuilder::new()
+            .with_transition(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .and_then(|builder| builder.with_transition(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right)));
+
+        assert_eq!(result.err(), Some(TransitionError::Duplicate(TransitionKey::new(0, "I"))));
+    }
+
+    #[test]
+    fn builder_should_reject_conflicting_transitions() {
+        let result: Result<TransitionsBuilder<u32, &str>, _> = TransitionsBuilder::new()
+            .with_transition(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .and_then(|builder| builder.with_transition(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(1, "_", Movement::Right)));
+
+        assert_eq!(result.err(), Some(TransitionError::Conflict(TransitionKey::new(0, "I"))));
+    }
+
+    #[test]
+    fn builder_should_overwrite_on_request() {
+        let transitions: Transitions<u32, &str> = TransitionsBuilder::new()
+            .overwrite(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .overwrite(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(1, "_", Movement::Left))
+            .build();
+
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(
+            transitions.lookup(&TransitionKey::new(0, "I")),
+            Some(&TransitionValue::new(1, "_", Movement::Left)));
+    }
+
+    #[test]
+    fn should_lookup_transition_in_hash_table() {
+        let transitions: HashTransitions<u32, &str> = HashTransitions::new()
+            .insert(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .insert(
+                TransitionKey::new(0, "_"),
+                TransitionValue::new(1, "I", Movement::Left));
+
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions.lookup(&TransitionKey::new(0, "_")).map(|value| value.next), Some(Next::State(1)));
+        assert!(transitions.lookup(&TransitionKey::new(1, "_")).is_none());
+    }
+
+    #[test]
+    fn hash_table_should_agree_with_linked_list() {
+        let transitions: Transitions<u32, &str> = Transitions::new()
+            .insert(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(0, "I", Movement::Right))
+            .insert(
+                TransitionKey::new(0, "I"),
+                TransitionValue::new(2, "_", Movement::Left));
+
+        let table = HashTransitions::from(&transitions);
+
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table.lookup(&TransitionKey::new(0, "I")).map(|value| value.next),
+            transitions.lookup(&TransitionKey::new(0, "I")).map(|value| value.next));
+    }
+
+    #[test]
+    fn should_lookup_transition_in_dense_table() {
+        let transitions: DenseTransitions<usize, bool> = DenseTransitions::new(2)
+            .insert(
+                TransitionKey::new(0, false),
+                TransitionValue::new(1, true, Movement::Right))
+            .insert(
+                TransitionKey::new(3, true),
+                TransitionValue::new(0, false, Movement::Left));
+
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions.lookup(&TransitionKey::new(3, true)).map(|value| value.next), Some(Next::State(0)));
+        assert!(transitions.lookup(&TransitionKey::new(1, false)).is_none());
+        assert!(transitions.lookup(&TransitionKey::new(7, true)).is_none());
     }
 }
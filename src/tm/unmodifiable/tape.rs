@@ -2,6 +2,10 @@
 //!
 //! You can read from it, write to it and move it left and right.
 
+use std::ops::Range;
+use super::super::inspect::Inspect;
+use super::super::movement::Movement;
+
 /// The contract a concrete Tape should adhere to.
 pub trait Tape<S: Clone> {
     /// Reads the symbol at the location of the head of the Turing machine
@@ -18,6 +22,17 @@ pub trait Tape<S: Clone> {
 
     /// Move the head of the Turing machine to the right on this tape
     fn right(&self) -> Self;
+
+    /// Move the head of the Turing machine on this tape according to `movement`
+    fn move_head(&self, movement: Movement) -> Self where Self: Sized + Clone {
+        match movement {
+            Movement::Left => self.left(),
+
+            Movement::Right => self.right(),
+
+            Movement::Stay => self.clone(),
+        }
+    }
 }
 
 /// An type implementing the `Tape` trait.
@@ -27,6 +42,7 @@ pub struct ConcreteTape<S: Clone> {
     left: HalfTape<S>,
     current: S,
     right: HalfTape<S>,
+    position: i64,
 }
 
 #[derive(Clone, Debug)]
@@ -52,6 +68,16 @@ impl<S: Clone> HalfTape<S> {
     fn push(self, symbol: S) -> HalfTape<S> {
         HalfTape::Cell(symbol, Box::new(self))
     }
+
+    fn symbols(&self) -> Vec<S> {
+        let mut symbols = Vec::new();
+        let mut half_tape = self;
+        while let HalfTape::Cell(ref symbol, ref tail) = *half_tape {
+            symbols.push(symbol.clone());
+            half_tape = tail;
+        }
+        symbols
+    }
 }
 
 impl<S:Clone> ConcreteTape<S> {
@@ -64,7 +90,8 @@ impl<S:Clone> ConcreteTape<S> {
             blank: blank.clone(),
             left: HalfTape::empty(),
             current: blank.clone(),
-            right: HalfTape::empty()
+            right: HalfTape::empty(),
+            position: 0,
         }
     }
 }
@@ -91,12 +118,14 @@ impl<S: Clone> Tape<S> for ConcreteTape<S> {
                 left: left_tail,
                 current: symbol,
                 right: self.right.clone().push(self.current.clone()),
+                position: self.position - 1,
                 .. self.clone()
             },
             None => ConcreteTape {
                 left: left_tail,
                 current: self.blank(),
                 right: self.right.clone().push(self.current.clone()),
+                position: self.position - 1,
                 .. self.clone()
             }
         }
@@ -106,21 +135,47 @@ impl<S: Clone> Tape<S> for ConcreteTape<S> {
         let (option, right_tail) = self.right.pop();
         match option {
             Some(symbol) => ConcreteTape {
-                left: self.right.clone().push(self.current.clone()),
+                left: self.left.clone().push(self.current.clone()),
                 current: symbol,
                 right: right_tail,
+                position: self.position + 1,
                 .. self.clone()
             },
             None => ConcreteTape {
                 left: self.left.clone().push(self.current.clone()),
                 current: self.blank(),
                 right: right_tail,
+                position: self.position + 1,
                 .. self.clone()
             }
         }
     }
 }
 
+impl<S: Clone + PartialEq> Inspect<S> for ConcreteTape<S> {
+    fn head_position(&self) -> i64 {
+        self.position
+    }
+
+    fn extent(&self) -> Range<i64> {
+        let left = self.left.symbols().len() as i64;
+        let right = self.right.symbols().len() as i64;
+        (self.position - left)..(self.position + right + 1)
+    }
+
+    fn to_vec(&self) -> Vec<S> {
+        let mut symbols = self.left.symbols();
+        symbols.reverse();
+        symbols.push(self.current.clone());
+        symbols.extend(self.right.symbols());
+        symbols
+    }
+
+    fn non_blank_count(&self) -> usize {
+        self.to_vec().iter().filter(|symbol| **symbol != self.blank).count()
+    }
+}
+
 /// Builder for `ConcreteTape`.
 ///
 /// # Example
@@ -183,6 +238,16 @@ impl<S: Clone> TapeBuilder<S> {
         }
     }
 
+    /// Sets the position of the read/write head, which is 0 otherwise.
+    pub fn with_head_position(&self, position: i64) -> TapeBuilder<S> {
+        TapeBuilder {
+            tape: ConcreteTape {
+                position,
+                .. self.tape.clone()
+            }
+        }
+    }
+
     /// Build the described tape.
     pub fn build(&self) -> ConcreteTape<S> {
         self.tape.clone()
@@ -192,6 +257,11 @@ impl<S: Clone> TapeBuilder<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::VecDeque;
+    use proptest::prelude::*;
+    use super::super::turing::Machine;
+    use super::super::super::run::RunOutcome;
+    use super::super::super::transition::{Next, Transitions};
 
     #[test]
     fn empty_tape_should_read_head() {
@@ -222,6 +292,20 @@ mod tests {
         assert_eq!(symbol, t.blank());
     }
 
+    #[test]
+    fn tape_should_move_head_according_to_movement() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b"])
+            .build();
+
+        t = t.move_head(Movement::Stay);
+        assert_eq!(t.read(), "a");
+
+        t = t.move_head(Movement::Left);
+        assert_eq!(t.read(), "b");
+    }
+
     #[test]
     fn tape_should_be_loaded_with_initial_symbols_on_the_right() {
         let mut t = TapeBuilder::with_blank("_")
@@ -251,4 +335,150 @@ mod tests {
         symbol = t.read();
         assert_eq!(symbol, "b");
     }
+
+    #[test]
+    fn tape_should_report_head_position_and_extent() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b"])
+            .with_right_tape(vec!["c", "_"])
+            .build();
+
+        assert_eq!(t.head_position(), 0);
+        assert_eq!(t.extent(), -1..3);
+
+        t = t.left().left().write("d");
+        assert_eq!(t.head_position(), -2);
+        assert_eq!(t.extent(), -2..3);
+        assert_eq!(t.to_vec(), vec!["d", "b", "a", "c", "_"]);
+        assert_eq!(t.non_blank_count(), 4);
+    }
+
+    #[test]
+    fn tape_should_be_built_with_the_head_anywhere() {
+        let t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["b"])
+            .with_head_position(-5)
+            .build();
+
+        assert_eq!(t.head_position(), -5);
+        assert_eq!(t.extent(), -6..-4);
+    }
+
+    #[test]
+    fn tape_should_keep_the_left_side_when_moving_right_over_initial_symbols() {
+        let mut t = TapeBuilder::with_blank("_")
+            .with_current("a")
+            .with_left_tape(vec!["l"])
+            .with_right_tape(vec!["b", "c"])
+            .build();
+
+        t = t.right().right().left().left().left();
+
+        assert_eq!(t.read(), "l");
+    }
+
+    #[test]
+    fn busy_beaver_should_halt_when_moving_right_keeps_the_left_side() {
+        let transitions = Transitions::from_standard("1RB1LB_1LA0LC_1RZ1LD_1RD0RA").expect("a valid machine");
+
+        let (machine, outcome) = Machine::new(0, ConcreteTape::empty(0), transitions).run(1000);
+        assert_eq!(outcome, RunOutcome::Halted { steps: 107, state: Next::Halt });
+        assert_eq!(machine.tape().non_blank_count(), 13);
+    }
+
+    #[derive(Clone, Debug)]
+    enum Operation {
+        Read,
+        Write(u8),
+        Left,
+        Right,
+    }
+
+    fn operation() -> impl Strategy<Value = Operation> {
+        prop_oneof![
+            Just(Operation::Read),
+            (0u8..4).prop_map(Operation::Write),
+            Just(Operation::Left),
+            Just(Operation::Right),
+        ]
+    }
+
+    /// A straightforward model of a tape: the visited cells and the index of
+    /// the cell under the read/write head.
+    struct Model {
+        cells: VecDeque<u8>,
+        head: usize,
+    }
+
+    impl Model {
+        fn new(left: &[u8], current: u8, right: &[u8]) -> Model {
+            let mut cells: VecDeque<u8> = left.iter().rev().cloned().collect();
+            let head = cells.len();
+            cells.push_back(current);
+            cells.extend(right.iter().cloned());
+            Model { cells, head }
+        }
+
+        fn apply(&mut self, operation: &Operation) {
+            match *operation {
+                Operation::Read => (),
+
+                Operation::Write(symbol) => self.cells[self.head] = symbol,
+
+                Operation::Left => {
+                    if self.head == 0 {
+                        self.cells.push_front(0);
+                    } else {
+                        self.head -= 1;
+                    }
+                }
+
+                Operation::Right => {
+                    self.head += 1;
+                    if self.head == self.cells.len() {
+                        self.cells.push_back(0);
+                    }
+                }
+            }
+        }
+
+        fn read(&self) -> u8 {
+            self.cells[self.head]
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn tape_should_behave_like_a_model(
+            left in prop::collection::vec(0u8..4, 0..8),
+            current in 0u8..4,
+            right in prop::collection::vec(0u8..4, 0..8),
+            operations in prop::collection::vec(operation(), 0..64)) {
+            let mut tape = TapeBuilder::with_blank(0)
+                .with_current(current)
+                .with_left_tape(left.clone())
+                .with_right_tape(right.clone())
+                .build();
+            let mut model = Model::new(&left, current, &right);
+
+            for operation in &operations {
+                tape = match *operation {
+                    Operation::Read => tape,
+
+                    Operation::Write(symbol) => tape.write(symbol),
+
+                    Operation::Left => tape.left(),
+
+                    Operation::Right => tape.right(),
+                };
+                model.apply(operation);
+
+                prop_assert_eq!(tape.read(), model.read());
+            }
+            prop_assert_eq!(tape.to_vec(), model.cells.iter().cloned().collect::<Vec<_>>());
+            prop_assert_eq!(tape.head_position() - tape.extent().start, model.head as i64);
+        }
+    }
 }
@@ -1,48 +1,130 @@
 //! The actual Turing machine
 //!
 
+use std::collections::HashSet;
 use std::hash::Hash;
-use super::super::transition::{Lookup, Transitions, TransitionKey};
+use std::sync::Arc;
+use super::super::transition::{Lookup, Next, Transitions, TransitionKey};
+use super::super::run::RunOutcome;
 use super::tape::{Tape, ConcreteTape};
-use super::super::movement::Movement;
 
 /// The actual Turing machine
+///
+/// The transitions can be any `Lookup`. Because every step produces a new
+/// machine, the transitions are cloned on every step, so large tables are best
+/// shared behind an `Rc`, or behind an `Arc` to run the machine on another
+/// thread.
+///
+/// The machine halts when a transition leads to HALT or ERROR, or when it
+/// enters one of the states declared with `with_halting_states`.
 #[derive(Debug)]
-pub struct Machine<Q, S> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash  {
-    state: Q,
+pub struct Machine<Q, S, L = Transitions<Q, S>> where Q: Clone + Eq + Hash, S: Clone + Eq + Hash, L: Lookup<Q, S> + Clone {
+    state: Next<Q>,
     tape: ConcreteTape<S>,
-    transitions: Transitions<Q, S>,
+    transitions: L,
+    halting: Arc<HashSet<Q>>,
 }
 
-impl <Q, S> Machine<Q, S> where S: Clone + Eq + Hash, Q: Clone + Eq + Hash {
+impl <Q, S, L> Machine<Q, S, L> where S: Clone + Eq + Hash, Q: Clone + Eq + Hash, L: Lookup<Q, S> + Clone {
     /// Create a Turing machine from a initial state, a tape and transistions
-    pub fn new(q: Q, tape: ConcreteTape<S>, transitions: Transitions<Q, S>) -> Machine<Q, S> {
+    pub fn new(q: Q, tape: ConcreteTape<S>, transitions: L) -> Machine<Q, S, L> {
         Machine {
-            state: q,
-            tape: tape,
-            transitions: transitions,
+            state: Next::State(q),
+            tape,
+            transitions,
+            halting: Arc::new(HashSet::new()),
         }
     }
 
-    /// Step the machine through one transition.
-    pub fn step(&self) -> Option<Machine<Q, S>> {
+    /// Declare the states in which the machine halts when running.
+    pub fn with_halting_states<I>(self, states: I) -> Machine<Q, S, L> where I: IntoIterator<Item = Q> {
+        Machine {
+            halting: Arc::new(states.into_iter().collect()),
+            .. self
+        }
+    }
+
+    /// The state the machine is in.
+    pub fn state(&self) -> &Next<Q> {
+        &self.state
+    }
+
+    /// The transitions the machine follows.
+    pub fn transitions(&self) -> &L {
+        &self.transitions
+    }
+
+    /// The tape the machine is operating on.
+    pub fn tape(&self) -> &ConcreteTape<S> {
+        &self.tape
+    }
+
+    /// Whether the machine is in one of its halting states.
+    pub fn is_halted(&self) -> bool {
+        match self.state {
+            Next::State(ref q) => self.halting.contains(q),
+
+            Next::Halt | Next::Error => true,
+        }
+    }
+
+    /// Step the machine through one transition. The `state` of the resulting
+    /// machine tells where the transition led.
+    ///
+    /// Returns `None` when the machine is in HALT or ERROR, or when there is
+    /// no transition for the current state and symbol.
+    pub fn step(&self) -> Option<Machine<Q, S, L>> {
+        let q = match self.state {
+            Next::State(ref q) => q.clone(),
+
+            Next::Halt | Next::Error => return None,
+        };
         let symbol = self.tape.read();
-        let key = TransitionKey::new(self.state.clone(), symbol);
+        let key = TransitionKey::new(q, symbol);
         match self.transitions.lookup(&key) {
             Some(next) => {
                 Some(Machine {
-                    state : next.state.clone(),
-                    tape : match next.movement {
-                        Movement::Left => self.tape.write(next.symbol.clone()).left(),
-
-                        Movement::Right => self.tape.write(next.symbol.clone()).right(),
-                    },
+                    state : next.next.clone(),
+                    tape : self.tape.write(next.symbol.clone()).move_head(next.movement),
                     transitions: self.transitions.clone(),
+                    halting: self.halting.clone(),
                 })
             }
             None => None
         }
     }
+
+    /// Run the machine for at most `limit` steps.
+    ///
+    /// Returns the machine as it was when the run ended, together with the
+    /// reason it ended.
+    pub fn run(self, limit: u64) -> (Machine<Q, S, L>, RunOutcome<Q, S>) {
+        let mut machine = self;
+        let mut steps = 0;
+        loop {
+            if machine.is_halted() {
+                let state = machine.state.clone();
+                return (machine, RunOutcome::Halted { steps, state });
+            }
+            if steps == limit {
+                return (machine, RunOutcome::StepLimitReached);
+            }
+            match machine.step() {
+                Some(next) => machine = next,
+
+                None => {
+                    let state = match machine.state {
+                        Next::State(ref q) => q.clone(),
+
+                        Next::Halt | Next::Error => unreachable!("a machine that has not halted is in a state"),
+                    };
+                    let symbol = machine.tape.read();
+                    return (machine, RunOutcome::UndefinedTransition { state, symbol });
+                }
+            }
+            steps += 1;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -50,7 +132,8 @@ mod tests {
     use super::*;
     use super::super::super::movement::Movement;
     use super::super::tape::TapeBuilder;
-    use super::super::super::transition::{Transitions, TransitionKey, TransitionValue};
+    use std::rc::Rc;
+    use super::super::super::transition::{Next, Transitions, HashTransitions, TransitionKey, TransitionValue};
 
     #[test]
     fn should_step_through_a_program() {
@@ -75,9 +158,104 @@ mod tests {
                     TransitionValue::new(2, "_", Movement::Right))
         );
 
-        match m.step() {
-            Some(_) => assert!(true),
-            None => assert!(false),
-        }
+        assert!(m.step().is_some());
+    }
+
+    fn successor() -> Machine<i32, &'static str> {
+        Machine::new(
+            0,
+            TapeBuilder::with_blank("_")
+                .with_current("I")
+                .with_right_tape(vec!["I", "I"])
+                .build(),
+            Transitions::new()
+                .insert(
+                    TransitionKey::new(0, "I"),
+                    TransitionValue::new(0, "I", Movement::Right))
+                .insert(
+                    TransitionKey::new(0, "_"),
+                    TransitionValue::new(1, "I", Movement::Left))
+                .insert(
+                    TransitionKey::new(1, "I"),
+                    TransitionValue::new(1, "I", Movement::Left))
+                .insert(
+                    TransitionKey::new(1, "_"),
+                    TransitionValue::new(2, "_", Movement::Right))
+        )
+    }
+
+    #[test]
+    fn should_run_until_a_halting_state() {
+        let (m, outcome) = successor().with_halting_states(vec![2]).run(100);
+
+        assert_eq!(outcome, RunOutcome::Halted { steps: 8, state: Next::State(2) });
+        assert!(m.is_halted());
+    }
+
+    #[test]
+    fn should_report_an_undefined_transition() {
+        let (_, outcome) = successor().run(100);
+
+        assert_eq!(outcome, RunOutcome::UndefinedTransition { state: 2, symbol: "I" });
+    }
+
+    #[test]
+    fn should_stop_at_the_step_limit() {
+        let (m, outcome) = successor().with_halting_states(vec![2]).run(5);
+
+        assert_eq!(outcome, RunOutcome::StepLimitReached);
+        assert_eq!(*m.state(), Next::State(1));
+    }
+
+    #[test]
+    fn should_halt_on_a_halting_transition() {
+        let m = Machine::new(
+            "start",
+            TapeBuilder::with_blank("_").build(),
+            Transitions::new()
+                .insert(
+                    TransitionKey::new("start", "_"),
+                    TransitionValue::halt("I", Movement::Right))
+        );
+
+        let next = m.step().expect("a transition");
+        assert_eq!(*next.state(), Next::Halt);
+        assert!(next.step().is_none());
+
+        let (_, outcome) = m.run(10);
+        assert_eq!(outcome, RunOutcome::Halted { steps: 1, state: Next::Halt });
+    }
+
+    #[test]
+    fn should_share_a_hash_table_between_steps() {
+        let m = Machine::new(
+            0,
+            TapeBuilder::with_blank("_")
+                .with_current("I")
+                .build(),
+            Rc::new(HashTransitions::new()
+                .insert(
+                    TransitionKey::new(0, "I"),
+                    TransitionValue::new(0, "I", Movement::Right))
+                .insert(
+                    TransitionKey::new(0, "_"),
+                    TransitionValue::new(1, "I", Movement::Left)))
+        );
+
+        let next = m.step().and_then(|m| m.step());
+
+        assert!(next.is_some());
+        assert!(next.and_then(|m| m.step()).is_none());
+    }
+
+    #[test]
+    fn should_run_on_another_thread() {
+        let transitions = Arc::new(HashTransitions::from(&Transitions::new()
+            .insert(TransitionKey::new(0, '_'), TransitionValue::new(1, '1', Movement::Right))));
+        let m = Machine::new(0, ConcreteTape::empty('_'), transitions).with_halting_states(vec![1]);
+
+        let outcome = ::std::thread::spawn(move || m.run(10).1).join().expect("a finished run");
+
+        assert_eq!(outcome, RunOutcome::Halted { steps: 1, state: Next::State(1) });
     }
 }
@@ -0,0 +1,109 @@
+//! The abstract syntax tree of a TMD program.
+
+use std::fmt;
+use tm::movement::Movement;
+
+/// A region of a single line of source.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Span {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column of the first character, starting at 1.
+    pub start: usize,
+    /// The column just after the last character.
+    pub end: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.start)
+    }
+}
+
+/// A complete program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program {
+    /// The functions in order of definition.
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    /// Find a function by its name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+/// A function definition, from `func` to `end`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Function {
+    /// The name of the function.
+    pub name: String,
+    /// The tapes passed by the caller.
+    pub parameters: Vec<String>,
+    /// The tapes of the function itself, declared with `var`.
+    pub variables: Vec<String>,
+    /// The commands, including labels, in order.
+    pub commands: Vec<Command>,
+    /// The header of the function.
+    pub span: Span,
+}
+
+impl Function {
+    /// All tapes of the function: the parameters followed by the variables.
+    pub fn tapes(&self) -> Vec<&str> {
+        self.parameters.iter().chain(&self.variables).map(String::as_str).collect()
+    }
+}
+
+/// A command together with where it was written.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Command {
+    /// What kind of command this is.
+    pub kind: CommandKind,
+    /// The whole command.
+    pub span: Span,
+}
+
+/// The kinds of commands.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CommandKind {
+    /// `name:` marks the next command as a target of jumps.
+    Label(String),
+    /// `write x S` writes `S` under the head of tape `x`.
+    Write {
+        /// The tape written to.
+        tape: String,
+        /// The written symbol.
+        symbol: char,
+    },
+    /// `move x L` or `move x R` moves the head of tape `x`.
+    Move {
+        /// The tape of which the head moves.
+        tape: String,
+        /// The direction, either `Left` or `Right`.
+        movement: Movement,
+    },
+    /// `if x S goto name` jumps when the head of tape `x` reads `S`.
+    Branch {
+        /// The tape read from.
+        tape: String,
+        /// The symbol compared with.
+        symbol: char,
+        /// The label jumped to.
+        label: String,
+    },
+    /// `goto name` always jumps.
+    Goto(String),
+    /// `call f(x, y)` runs `f` on the given tapes.
+    Call {
+        /// The called function.
+        function: String,
+        /// The tapes passed, in order of the parameters.
+        arguments: Vec<String>,
+    },
+    /// `return` goes back to the caller; returning from `main` halts.
+    Return,
+    /// `error` stops the program with an error.
+    Error,
+}
@@ -0,0 +1,848 @@
+//! Compile turns a TMD program into a two-symbol, single-tape Turing machine.
+//!
+//! The tapes of the program are interleaved on the single tape: cell `i` of
+//! every tape is stored in block `i`, a fixed number of consecutive cells. A
+//! block starts with a bit marking the first block and a bit marking blocks
+//! that have been visited. A track for every tape follows: a bit telling
+//! whether the head of that tape is in this block, then the symbol in the cell
+//! as a binary number of a fixed width. The blank `_` is number 0, so blank
+//! tapes encode to a blank tape.
+//!
+//! Calls are inlined, which rules out recursion. The variables of a called
+//! function get tracks after those of the caller, shared with the variables of
+//! other calls from the same caller, and are cleared on every call.
+//!
+//! Between commands the machine scans the second cell of the tape. A command
+//! seeks the block with the head of its tape, does its work there and returns
+//! to the first block. Labels, jumps, calls without variables and returns do
+//! not cost a single state.
+//!
+//! Every state remembers its origin: the chain of inlined calls it was
+//! generated for and the stage of the compiler that generated it. States that
+//! return to the first block are shared by all commands that continue in the
+//! same place, and belong to the command that needed them first.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use tm::Bit;
+use tm::movement::Movement;
+use tm::transition::{Next, Transitions, TransitionKey, TransitionValue};
+use super::ast::{CommandKind, Function, Program, Span};
+use super::interp::BLANK;
+
+/// A program compiled to a two-symbol machine.
+#[derive(Clone, Debug)]
+pub struct Compilation {
+    /// The transitions of the machine, which starts in state 0 on a blank
+    /// tape. HALT and ERROR correspond to halting and stopping with an error.
+    pub transitions: Transitions<usize, Bit>,
+    /// The number of states of the machine, numbered densely from 0.
+    pub states: usize,
+    /// The symbols of the program, in the order of their numbers.
+    pub alphabet: Vec<char>,
+    /// The tapes of the program, i.e. the parameters of `main`, which are the
+    /// first tracks.
+    pub tapes: Vec<String>,
+    /// Where every state came from, by state.
+    pub origins: Vec<Origin>,
+    layout: Layout,
+}
+
+impl Compilation {
+    /// The number of tracks interleaved on the tape.
+    pub fn tracks(&self) -> usize {
+        self.layout.tracks
+    }
+
+    /// The number of cells in a block.
+    pub fn block_width(&self) -> usize {
+        self.layout.width()
+    }
+
+    /// Read the tapes of the program from the tape of the machine, given from
+    /// its first cell onwards. Trailing blanks are left out.
+    pub fn decode(&self, cells: &[Bit]) -> Vec<(String, Vec<char>)> {
+        let width = self.layout.width();
+        let bit = |cell: usize| cells.get(cell).cloned().unwrap_or(false);
+        let blocks = cells.len().div_ceil(width);
+        self.tapes.iter().enumerate().map(|(track, name)| {
+            let mut symbols: Vec<char> = (0..blocks).map(|block| {
+                let start = block * width + self.layout.head(track) + 1;
+                let code = (start..start + self.layout.bits).fold(0, |code, cell| 2 * code + bit(cell) as usize);
+                self.alphabet.get(code).cloned().unwrap_or(BLANK)
+            }).collect();
+            while symbols.last() == Some(&BLANK) {
+                symbols.pop();
+            }
+            (name.clone(), symbols)
+        }).collect()
+    }
+}
+
+/// Where a state of a compiled program came from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Origin {
+    /// The functions inlined into each other, from `main` to the function
+    /// with the command the state is for. Empty for states of the program as a
+    /// whole.
+    pub calls: Vec<String>,
+    /// The part of the compiler that generated the state.
+    pub stage: Stage,
+}
+
+/// The parts of the compiler that generate states.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Stage {
+    /// Marking the first block and the heads at the start of the program.
+    Initialize,
+    /// Looking for the head of a tape.
+    Seek,
+    /// Writing a symbol.
+    Write,
+    /// Moving a head.
+    Move,
+    /// Comparing a symbol for `if`.
+    Branch,
+    /// Clearing the variables of a called function.
+    Clear,
+    /// Going back to the first block.
+    Return,
+    /// Jumping in circles.
+    Spin,
+}
+
+impl Stage {
+    /// All stages, in the order of this enumeration.
+    pub const ALL: [Stage; 8] = [Stage::Initialize, Stage::Seek, Stage::Write, Stage::Move, Stage::Branch, Stage::Clear, Stage::Return, Stage::Spin];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Stage::Initialize => "initialize",
+            Stage::Seek => "seek",
+            Stage::Write => "write",
+            Stage::Move => "move",
+            Stage::Branch => "branch",
+            Stage::Clear => "clear",
+            Stage::Return => "return",
+            Stage::Spin => "spin",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Call that can not be inlined.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CompileError {
+    /// The function the call is in.
+    pub function: String,
+    /// Where the call is.
+    pub span: Span,
+    /// Why the call can not be inlined.
+    pub kind: CompileErrorKind,
+}
+
+/// The reasons a call can not be inlined.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CompileErrorKind {
+    /// The called function, directly or indirectly, calls the caller again.
+    Recursion(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in `{}`: ", self.span, self.function)?;
+        match self.kind {
+            CompileErrorKind::Recursion(ref callee) => write!(f, "recursive call of `{}`", callee),
+        }
+    }
+}
+
+impl Error for CompileError {}
+
+/// Compile `program` into a two-symbol, single-tape machine. The program
+/// should be the result of parsing, so all names refer to something.
+pub fn compile(program: &Program) -> Result<Compilation, CompileError> {
+    let main = program.function("main").expect("a function main");
+    let tracks = main.parameters.len() + variables(program, main, &mut Vec::new(), &mut HashMap::new())?;
+
+    let mut alphabet = vec![BLANK];
+    for function in &program.functions {
+        for command in &function.commands {
+            match command.kind {
+                CommandKind::Write { symbol, .. } | CommandKind::Branch { symbol, .. } if !alphabet.contains(&symbol) =>
+                    alphabet.push(symbol),
+
+                _ => (),
+            }
+        }
+    }
+    let mut bits = 0;
+    while 1 << bits < alphabet.len() {
+        bits += 1;
+    }
+
+    let layout = Layout { tracks, bits };
+    let mut assembler = Assembler {
+        program,
+        alphabet: &alphabet,
+        layout,
+        slots: Vec::new(),
+        returns: HashMap::new(),
+        origins: Vec::new(),
+        calls: Vec::new(),
+        stage: Stage::Initialize,
+    };
+    let start = assembler.fresh();
+    let own = main.parameters.len() + main.variables.len();
+    let entry = assembler.inline(main, (0..own).collect(), own, Next::Halt);
+    assembler.initialize(start, entry);
+    let (transitions, origins) = assembler.build();
+
+    Ok(Compilation { transitions, states: origins.len(), alphabet: alphabet.clone(), tapes: main.parameters.clone(), origins, layout })
+}
+
+/// The number of tracks needed for the variables of `function` and of the
+/// functions it calls, failing on recursion.
+fn variables<'a>(program: &'a Program, function: &'a Function, stack: &mut Vec<&'a str>, known: &mut HashMap<&'a str, usize>) -> Result<usize, CompileError> {
+    if let Some(&count) = known.get(function.name.as_str()) {
+        return Ok(count);
+    }
+    stack.push(&function.name);
+    let mut callees = 0;
+    for command in &function.commands {
+        if let CommandKind::Call { function: ref callee, .. } = command.kind {
+            if stack.contains(&callee.as_str()) {
+                return Err(CompileError {
+                    function: function.name.clone(),
+                    span: command.span,
+                    kind: CompileErrorKind::Recursion(callee.clone()),
+                });
+            }
+            let callee = program.function(callee).expect("a defined function");
+            callees = callees.max(variables(program, callee, stack, known)?);
+        }
+    }
+    stack.pop();
+    let count = function.variables.len() + callees;
+    known.insert(&function.name, count);
+    Ok(count)
+}
+
+/// Where things are in a block.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    tracks: usize,
+    bits: usize,
+}
+
+/// What a cell in a block holds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Cell {
+    First,
+    Visited,
+    Head(usize),
+    Symbol(usize),
+}
+
+impl Layout {
+    fn width(&self) -> usize {
+        2 + self.tracks * (1 + self.bits)
+    }
+
+    fn head(&self, track: usize) -> usize {
+        2 + track * (1 + self.bits)
+    }
+
+    fn cell(&self, offset: usize) -> Cell {
+        match offset % self.width() {
+            0 => Cell::First,
+
+            1 => Cell::Visited,
+
+            offset if (offset - 2) % (1 + self.bits) == 0 => Cell::Head((offset - 2) / (1 + self.bits)),
+
+            offset => Cell::Symbol((offset - 2) / (1 + self.bits)),
+        }
+    }
+}
+
+/// A state under construction: either its transitions for reading `false` and
+/// `true`, or another place it stands for.
+enum Slot {
+    Row([Option<TransitionValue<usize, Bit>>; 2]),
+    Alias(Next<usize>),
+}
+
+struct Assembler<'a> {
+    program: &'a Program,
+    alphabet: &'a [char],
+    layout: Layout,
+    slots: Vec<Slot>,
+    /// The states that return to the first block, by where they continue.
+    returns: HashMap<Next<usize>, usize>,
+    /// The origin of every slot.
+    origins: Vec<Origin>,
+    /// The functions being inlined, from `main` onwards.
+    calls: Vec<&'a str>,
+    /// The stage new states belong to.
+    stage: Stage,
+}
+
+impl <'a> Assembler<'a> {
+    fn fresh(&mut self) -> usize {
+        self.slots.push(Slot::Row([None, None]));
+        self.origins.push(self.origin());
+        self.slots.len() - 1
+    }
+
+    fn origin(&self) -> Origin {
+        Origin { calls: self.calls.iter().map(|call| call.to_string()).collect(), stage: self.stage }
+    }
+
+    fn set(&mut self, state: usize, read: Bit, symbol: Bit, movement: Movement, next: Next<usize>) {
+        match self.slots[state] {
+            Slot::Row(ref mut row) => row[read as usize] = Some(TransitionValue { next, symbol, movement }),
+
+            Slot::Alias(_) => unreachable!("transitions from an alias"),
+        }
+    }
+
+    /// Leave the scanned bit as it is.
+    fn pass(&mut self, state: usize, movement: Movement, next: Next<usize>) {
+        self.set(state, false, false, movement, next);
+        self.set(state, true, true, movement, next);
+    }
+
+    /// Overwrite the scanned bit.
+    fn put(&mut self, state: usize, symbol: Bit, movement: Movement, next: Next<usize>) {
+        self.set(state, false, symbol, movement, next);
+        self.set(state, true, symbol, movement, next);
+    }
+
+    fn alias(&mut self, state: usize, next: Next<usize>) {
+        self.slots[state] = Slot::Alias(next);
+    }
+
+    /// The state at `offset` in a block, on the way back to the first block
+    /// before continuing with `next`.
+    fn back(&mut self, next: Next<usize>, offset: usize) -> Next<usize> {
+        let width = self.layout.width();
+        let first = match self.returns.get(&next) {
+            Some(&first) => first,
+
+            None => {
+                let stage = mem::replace(&mut self.stage, Stage::Return);
+                let first = self.slots.len();
+                for _ in 0..width {
+                    self.fresh();
+                }
+                self.set(first, true, true, Movement::Right, next);
+                self.set(first, false, false, Movement::Left, Next::State(first + width - 1));
+                for offset in 1..width {
+                    self.pass(first + offset, Movement::Left, Next::State(first + offset - 1));
+                }
+                self.returns.insert(next, first);
+                self.stage = stage;
+                first
+            }
+        };
+        Next::State(first + offset % width)
+    }
+
+    /// Move right from the second cell of the tape, starting in `entry`, to
+    /// the head of `track`. Returns the state that finds it; its transition
+    /// for reading `true` is left to the caller.
+    fn seek(&mut self, entry: usize, track: usize) -> usize {
+        let stage = mem::replace(&mut self.stage, Stage::Seek);
+        self.origins[entry] = self.origin();
+        let width = self.layout.width();
+        let states: Vec<usize> = (0..width).map(|offset| if offset == 1 { entry } else { self.fresh() }).collect();
+        let head = self.layout.head(track);
+        for offset in 0..width {
+            let next = Next::State(states[(offset + 1) % width]);
+            if offset == head {
+                self.set(states[offset], false, false, Movement::Right, next);
+            } else {
+                self.pass(states[offset], Movement::Right, next);
+            }
+        }
+        self.stage = stage;
+        states[head]
+    }
+
+    /// The bits of the number of `symbol`, most significant first.
+    fn code(&self, symbol: char) -> Vec<Bit> {
+        let code = self.alphabet.iter().position(|&known| known == symbol).expect("a symbol of the alphabet");
+        (0..self.layout.bits).rev().map(|bit| code >> bit & 1 == 1).collect()
+    }
+
+    /// Generate `function` with its tapes on `tracks`, returning the state it
+    /// starts in. Returning from it continues with `exit`. The tracks from
+    /// `free` onwards are available to the functions it calls.
+    fn inline(&mut self, function: &'a Function, tracks: Vec<usize>, free: usize, exit: Next<usize>) -> Next<usize> {
+        self.calls.push(&function.name);
+        let entries: Vec<usize> = function.commands.iter().map(|_| self.fresh()).collect();
+        let after = |index: usize| entries.get(index + 1).map(|&entry| Next::State(entry)).unwrap_or(exit);
+        let tapes: HashMap<&str, usize> = function.tapes().into_iter().zip(tracks.iter().cloned()).collect();
+        let labels: HashMap<&str, usize> = function.commands.iter().zip(&entries).filter_map(|(command, &entry)| {
+            match command.kind {
+                CommandKind::Label(ref label) => Some((label.as_str(), entry)),
+
+                _ => None,
+            }
+        }).collect();
+        let width = self.layout.width();
+
+        for (index, command) in function.commands.iter().enumerate() {
+            let entry = entries[index];
+            let next = after(index);
+            self.stage = match command.kind {
+                CommandKind::Write { .. } => Stage::Write,
+
+                CommandKind::Branch { .. } => Stage::Branch,
+
+                _ => Stage::Move,
+            };
+            match command.kind {
+                CommandKind::Label(_) => self.alias(entry, next),
+
+                CommandKind::Write { ref tape, symbol } => {
+                    let head = self.layout.head(tapes[tape.as_str()]);
+                    let code = self.code(symbol);
+                    let found = self.seek(entry, tapes[tape.as_str()]);
+                    let mut following = self.back(next, head + 1 + code.len());
+                    for &bit in code.iter().rev() {
+                        let state = self.fresh();
+                        self.put(state, bit, Movement::Right, following);
+                        following = Next::State(state);
+                    }
+                    self.set(found, true, true, Movement::Right, following);
+                }
+
+                CommandKind::Move { ref tape, movement: Movement::Right } => {
+                    let head = self.layout.head(tapes[tape.as_str()]);
+                    let found = self.seek(entry, tapes[tape.as_str()]);
+                    let mut state = self.fresh();
+                    self.set(found, true, false, Movement::Right, Next::State(state));
+                    for offset in head + 1..head + width {
+                        let following = self.fresh();
+                        if offset == width + 1 {
+                            self.put(state, true, Movement::Right, Next::State(following));
+                        } else {
+                            self.pass(state, Movement::Right, Next::State(following));
+                        }
+                        state = following;
+                    }
+                    let back = self.back(next, head + 1);
+                    self.put(state, true, Movement::Right, back);
+                }
+
+                CommandKind::Move { ref tape, movement: Movement::Left } => {
+                    let head = self.layout.head(tapes[tape.as_str()]);
+                    let found = self.seek(entry, tapes[tape.as_str()]);
+                    let mut state = self.fresh();
+                    self.set(found, true, false, Movement::Left, Next::State(state));
+                    for _ in 1..head {
+                        let following = self.fresh();
+                        self.pass(state, Movement::Left, Next::State(following));
+                        state = following;
+                    }
+                    let mut following = self.fresh();
+                    self.set(state, true, true, Movement::Right, Next::Error);
+                    self.set(state, false, false, Movement::Left, Next::State(following));
+                    for _ in head + 1..width {
+                        state = following;
+                        following = self.fresh();
+                        self.pass(state, Movement::Left, Next::State(following));
+                    }
+                    let back = self.back(next, head + width - 1);
+                    self.put(following, true, Movement::Left, back);
+                }
+
+                CommandKind::Move { movement: Movement::Stay, .. } => self.alias(entry, next),
+
+                CommandKind::Branch { ref tape, symbol, ref label } => {
+                    let head = self.layout.head(tapes[tape.as_str()]);
+                    let code = self.code(symbol);
+                    let found = self.seek(entry, tapes[tape.as_str()]);
+                    let mut following = self.back(Next::State(labels[label.as_str()]), head + 1 + code.len());
+                    for (index, &bit) in code.iter().enumerate().rev() {
+                        let state = self.fresh();
+                        let differs = self.back(next, head + 2 + index);
+                        self.set(state, bit, bit, Movement::Right, following);
+                        self.set(state, !bit, !bit, Movement::Right, differs);
+                        following = Next::State(state);
+                    }
+                    self.set(found, true, true, Movement::Right, following);
+                }
+
+                CommandKind::Goto(ref label) => self.alias(entry, Next::State(labels[label.as_str()])),
+
+                CommandKind::Call { function: ref callee, ref arguments } => {
+                    let callee = self.program.function(callee).expect("a defined function");
+                    let variables: Vec<usize> = (free..free + callee.variables.len()).collect();
+                    let tracks = arguments.iter().map(|argument| tapes[argument.as_str()]).chain(variables.iter().cloned()).collect();
+                    let start = self.inline(callee, tracks, free + variables.len(), next);
+                    if variables.is_empty() {
+                        self.alias(entry, start);
+                    } else {
+                        self.clear(entry, &variables, start);
+                    }
+                }
+
+                CommandKind::Return => self.alias(entry, exit),
+
+                CommandKind::Error => self.alias(entry, Next::Error),
+            }
+        }
+
+        self.calls.pop();
+        entries.first().map(|&entry| Next::State(entry)).unwrap_or(exit)
+    }
+
+    /// Blank `tracks` and put their heads on the first block, starting in
+    /// `entry` and continuing with `next`.
+    fn clear(&mut self, entry: usize, tracks: &[usize], next: Next<usize>) {
+        self.stage = Stage::Clear;
+        self.origins[entry] = self.origin();
+        let width = self.layout.width();
+        let cleared = |cell: Cell| match cell {
+            Cell::Head(track) | Cell::Symbol(track) => tracks.contains(&track),
+
+            Cell::First | Cell::Visited => false,
+        };
+
+        let cycle: Vec<usize> = (0..width).map(|_| self.fresh()).collect();
+        let mut state = entry;
+        for offset in 1..width {
+            let following = if offset + 1 == width { cycle[0] } else { self.fresh() };
+            match self.layout.cell(offset) {
+                Cell::Head(track) if tracks.contains(&track) => self.put(state, true, Movement::Right, Next::State(following)),
+
+                cell if cleared(cell) => self.put(state, false, Movement::Right, Next::State(following)),
+
+                _ => self.pass(state, Movement::Right, Next::State(following)),
+            }
+            state = following;
+        }
+
+        let back = self.back(next, 0);
+        for offset in 0..width {
+            let following = Next::State(cycle[(offset + 1) % width]);
+            match self.layout.cell(offset) {
+                Cell::Visited => {
+                    self.set(cycle[offset], false, false, Movement::Left, back);
+                    self.set(cycle[offset], true, true, Movement::Right, following);
+                }
+
+                cell if cleared(cell) => self.put(cycle[offset], false, Movement::Right, following),
+
+                _ => self.pass(cycle[offset], Movement::Right, following),
+            }
+        }
+    }
+
+    /// Mark the first block, visit it and put all heads on it, starting in
+    /// `start` on a blank tape and continuing with `next`.
+    fn initialize(&mut self, start: usize, next: Next<usize>) {
+        self.stage = Stage::Initialize;
+        let width = self.layout.width();
+        let mut state = start;
+        for offset in 0..width {
+            let following = if offset + 1 == width { self.back(next, 0) } else { Next::State(self.fresh()) };
+            match self.layout.cell(offset) {
+                Cell::Symbol(_) => self.pass(state, Movement::Right, following),
+
+                _ => self.put(state, true, Movement::Right, following),
+            }
+            if let Next::State(following) = following {
+                state = following;
+            }
+        }
+    }
+
+    /// Follow aliases until reaching a state with transitions, or `None` when
+    /// the aliases go round in circles.
+    fn resolve(&self, mut next: Next<usize>) -> Option<Next<usize>> {
+        for _ in 0..=self.slots.len() {
+            match next {
+                Next::State(state) => match self.slots[state] {
+                    Slot::Alias(target) => next = target,
+
+                    Slot::Row(_) => return Some(next),
+                },
+
+                Next::Halt | Next::Error => return Some(next),
+            }
+        }
+        None
+    }
+
+    /// The transitions with aliases resolved and the states renumbered
+    /// densely, together with the origins of the states.
+    fn build(mut self) -> (Transitions<usize, Bit>, Vec<Origin>) {
+        let circles = self.slots.iter().any(|slot| match *slot {
+            Slot::Alias(next) => self.resolve(next).is_none(),
+
+            Slot::Row(_) => false,
+        });
+        // A jump that leads nowhere but to itself keeps the machine busy
+        // without changing the tape.
+        let spin = if circles {
+            self.stage = Stage::Spin;
+            let spin = self.fresh();
+            let other = self.fresh();
+            self.pass(spin, Movement::Right, Next::State(other));
+            self.pass(other, Movement::Left, Next::State(spin));
+            Some(spin)
+        } else {
+            None
+        };
+
+        let mut numbers = vec![None; self.slots.len()];
+        let mut origins = Vec::new();
+        for (state, slot) in self.slots.iter().enumerate() {
+            if let Slot::Row(_) = *slot {
+                numbers[state] = Some(origins.len());
+                origins.push(self.origins[state].clone());
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (state, slot) in self.slots.iter().enumerate() {
+            if let Slot::Row(ref row) = *slot {
+                for (read, value) in row.iter().enumerate() {
+                    if let Some(ref value) = *value {
+                        let next = self.resolve(value.next).unwrap_or_else(|| Next::State(spin.expect("a spinning state")));
+                        let next = next.map(|state| numbers[state].expect("a state with transitions"));
+                        let key = TransitionKey::new(numbers[state].expect("a state with transitions"), read == 1);
+                        rows.push((key, TransitionValue { next, symbol: value.symbol, movement: value.movement }));
+                    }
+                }
+            }
+        }
+
+        let transitions = rows.into_iter().rev().fold(Transitions::new(), |transitions, (key, value)| transitions.insert(key, value));
+        (transitions, origins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use laconic;
+    use laconic::codegen::{decode_output, OUTPUT};
+    use tm::inspect::Inspect;
+    use tm::modifiable::tape::ConcreteTape;
+    use tm::modifiable::turing::Machine;
+    use tm::run::RunOutcome;
+    use tm::transition::HashTransitions;
+    use super::super::interp::{self, Outcome};
+    use super::super::parser::parse;
+
+    type Tapes = Vec<(String, Vec<char>)>;
+
+    /// Run the compiled program on a blank tape, returning how it ended and
+    /// the tapes of the program.
+    fn execute(compilation: &Compilation, limit: u64) -> (RunOutcome<usize, Bit>, Tapes) {
+        let transitions = Rc::new(HashTransitions::from(&compilation.transitions));
+        let mut machine = Machine::new(0, ConcreteTape::empty(false), transitions);
+        let outcome = machine.run(limit);
+        assert_eq!(machine.tape().extent().start, 0);
+        (outcome, compilation.decode(&machine.tape().to_vec()))
+    }
+
+    /// Compile and run `source` and check that it ends like the interpreter
+    /// says, with the same tapes.
+    fn compare(source: &str) {
+        let program = parse(source).expect("a valid program");
+        let expected = interp::run(&program, 100_000);
+        let compilation = compile(&program).expect("a program without recursion");
+        let (outcome, tapes) = execute(&compilation, 100_000_000);
+
+        match (&expected.outcome, &outcome) {
+            (Outcome::Halted, RunOutcome::Halted { state: Next::Halt, .. }) => (),
+
+            (Outcome::Error(_), RunOutcome::Halted { state: Next::Error, .. }) => (),
+
+            (expected, found) => panic!("expected {:?}, found {:?}", expected, found),
+        }
+        let expected: Tapes = expected.tapes.iter().map(|(name, tape)| {
+            let mut symbols = tape.to_vec();
+            while symbols.last() == Some(&BLANK) {
+                symbols.pop();
+            }
+            (name.clone(), symbols)
+        }).collect();
+        assert_eq!(tapes, expected);
+    }
+
+    #[test]
+    fn compiled_programs_should_behave_like_the_interpreter() {
+        compare("
+func increment(x)
+seek:
+    move x R
+    if x _ goto done
+    goto seek
+done:
+    write x 1
+rewind:
+    move x L
+    if x S goto finished
+    goto rewind
+finished:
+end
+
+func twice(x)
+    var local
+    if local 1 goto reused
+    write local 1
+    call increment(x)
+    call increment(x)
+    return
+reused:
+    error
+end
+
+func main(x, y, z)
+    write x S
+    write y S
+    write z S
+again:
+    call twice(x)
+    call increment(y)
+    move y R
+    move y R
+    move y R
+    if y 1 goto skip
+    move y L
+    move y L
+    move y L
+    goto again
+skip:
+    write z a
+    move z R
+    write z b
+end
+");
+        compare("func main(x)\n    write x 1\n    move x R\n    write x 2\n    move x L\n    move x L\n    write x 3\nend\n");
+        compare("func main(x)\n    write x 1\n    if x 1 goto fail\n    write x 2\nfail:\n    error\nend\n");
+        compare("func nothing(x)\nend\n\nfunc main(x)\n    call nothing(x)\nend\n");
+    }
+
+    #[test]
+    fn jumps_in_circles_should_keep_running() {
+        let program = parse("func main(x)\n    write x 1\nloop:\n    goto loop\nend\n").expect("a valid program");
+        let compilation = compile(&program).expect("a program without recursion");
+
+        let (outcome, tapes) = execute(&compilation, 10_000);
+
+        assert_eq!(outcome, RunOutcome::StepLimitReached);
+        assert_eq!(tapes, vec![("x".to_string(), vec!['1'])]);
+    }
+
+    #[test]
+    fn should_report_the_layout_and_the_state_count() {
+        let program = parse("func main(x, y)\n    var z\n    write x 1\n    if y a goto done\n    move z R\ndone:\nend\n").expect("a valid program");
+        let compilation = compile(&program).expect("a program without recursion");
+
+        assert_eq!(compilation.alphabet, vec!['_', '1', 'a']);
+        assert_eq!(compilation.tracks(), 3);
+        assert_eq!(compilation.block_width(), 11);
+
+        let mut states: Vec<usize> = compilation.transitions.iter().map(|(key, _)| key.state).collect();
+        states.dedup();
+        assert_eq!(states, (0..compilation.states).collect::<Vec<_>>());
+        assert_eq!(compilation.transitions.len(), 2 * compilation.states);
+    }
+
+    #[test]
+    fn should_remember_where_states_came_from() {
+        let program = parse("func f(x)\n    var y\n    write y 1\nend\n\nfunc main(x)\n    call f(x)\nend\n").expect("a valid program");
+        let compilation = compile(&program).expect("a program without recursion");
+
+        let mut origins: Vec<(Vec<&str>, Stage)> = Vec::new();
+        for origin in &compilation.origins {
+            let origin = (origin.calls.iter().map(String::as_str).collect(), origin.stage);
+            if !origins.contains(&origin) {
+                origins.push(origin);
+            }
+        }
+
+        assert_eq!(compilation.origins.len(), compilation.states);
+        assert_eq!(compilation.origins[0], Origin { calls: vec![], stage: Stage::Initialize });
+        assert_eq!(origins, vec![
+            (vec![], Stage::Initialize),
+            (vec!["main"], Stage::Clear),
+            (vec!["main", "f"], Stage::Seek),
+            (vec!["main", "f"], Stage::Return),
+            (vec!["main", "f"], Stage::Write),
+            (vec!["main"], Stage::Return),
+            (vec![], Stage::Return),
+        ]);
+    }
+
+    #[test]
+    fn should_refuse_recursion() {
+        let program = parse("func f(x)\n    call g(x)\nend\n\nfunc g(x)\n    call f(x)\nend\n\nfunc main(x)\n    call f(x)\nend\n").expect("a valid program");
+
+        let error = compile(&program).expect_err("a recursive program");
+
+        assert_eq!(error.to_string(), "6:5 in `g`: recursive call of `f`");
+    }
+
+    #[test]
+    fn should_compile_generated_programs() {
+        let mut program = laconic::parser::parse("func main() { int a = 7; list l = [a, 2]; print(l[1] * a - 3); }").expect("a valid Laconic program");
+        assert_eq!(laconic::check::check(&mut program), vec![]);
+        let program = parse(&laconic::codegen::generate(&program)).expect("a valid TMD program");
+
+        let compilation = compile(&program).expect("a program without recursion");
+        let (outcome, tapes) = execute(&compilation, 100_000_000);
+
+        assert!(matches!(outcome, RunOutcome::Halted { state: Next::Halt, .. }));
+        assert_eq!(tapes[0].0, OUTPUT);
+        assert_eq!(decode_output(&tapes[0].1), Some(vec![11u32.into()]));
+    }
+
+    #[test]
+    fn should_clone_and_compare_large_compilations() {
+        let mut program = laconic::parser::parse("
+            func isPrime(int n) int {
+                int d = 2;
+                while (d * d <= n) {
+                    if (n % d == 0) {
+                        return 0;
+                    }
+                    d = d + 1;
+                }
+                return n >= 2;
+            }
+
+            func main() {
+                int n = 0;
+                while (n < 20) {
+                    if (isPrime(n)) {
+                        print(n);
+                    }
+                    n = n + 1;
+                }
+            }
+        ").expect("a valid Laconic program");
+        assert_eq!(laconic::check::check(&mut program), vec![]);
+        let program = parse(&laconic::codegen::generate(&program)).expect("a valid TMD program");
+        let compilation = compile(&program).expect("a program without recursion");
+
+        let copy = compilation.clone();
+
+        assert!(copy.transitions.len() > 100_000);
+        assert!(copy.transitions == compilation.transitions);
+        assert!(format!("{:?}", copy).len() > copy.transitions.len());
+    }
+}
@@ -0,0 +1,448 @@
+//! Interp runs a TMD program directly, with a tape per name.
+//!
+//! It is the reference for what a TMD program does, against which machines
+//! compiled from it can be compared. Every executed command counts as a step;
+//! labels are free.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use tm::inspect::Inspect;
+use tm::modifiable::tape::{ConcreteTape, Tape};
+use tm::movement::Movement;
+use super::ast::{CommandKind, Function, Program, Span};
+
+/// The blank symbol of TMD tapes.
+pub const BLANK: char = '_';
+
+/// Everything observed while running a program.
+#[derive(Clone, Debug)]
+pub struct Execution {
+    /// The number of steps taken.
+    pub steps: u64,
+    /// How the run ended.
+    pub outcome: Outcome,
+    /// The tapes of the program, i.e. the parameters of `main`, in order.
+    pub tapes: Vec<(String, ConcreteTape<char>)>,
+}
+
+impl Execution {
+    /// The tape of the program with the given name.
+    pub fn tape(&self, name: &str) -> Option<&ConcreteTape<char>> {
+        self.tapes.iter().find(|(tape, _)| tape == name).map(|(_, tape)| tape)
+    }
+}
+
+/// The ways a run can end.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// `main` returned.
+    Halted,
+    /// The program stopped with an error.
+    Error(RuntimeError),
+    /// The step limit was reached before the program halted.
+    StepLimitReached,
+}
+
+/// Command that stopped a program with an error.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuntimeError {
+    /// The function the command is in.
+    pub function: String,
+    /// Where the command is.
+    pub span: Span,
+    /// Why the program stopped.
+    pub kind: RuntimeErrorKind,
+}
+
+/// The reasons a program stops with an error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RuntimeErrorKind {
+    /// An `error` command was executed.
+    Error,
+    /// A head was moved left of the first cell.
+    LeftOfFirstCell,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in `{}`: ", self.span, self.function)?;
+        match self.kind {
+            RuntimeErrorKind::Error => write!(f, "error"),
+
+            RuntimeErrorKind::LeftOfFirstCell => write!(f, "moved left of the first cell"),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+/// Run `program` from `main` for at most `limit` steps. The program should be
+/// the result of parsing, so all names refer to something.
+pub fn run(program: &Program, limit: u64) -> Execution {
+    let functions: Vec<Compiled> = program.functions.iter().map(|function| Compiled::new(program, function)).collect();
+    let main = program.functions.iter().position(|function| function.name == "main").expect("a function main");
+
+    let parameters = functions[main].parameters;
+    let mut tapes: Vec<ConcreteTape<char>> = (0..parameters).map(|_| ConcreteTape::empty(BLANK)).collect();
+    let mut frames = vec![Frame::new(main, (0..parameters).collect(), &functions, &mut tapes)];
+    let mut steps = 0;
+    let outcome = loop {
+        let (function, pc) = match frames.last() {
+            Some(frame) => (frame.function, frame.pc),
+
+            None => break Outcome::Halted,
+        };
+        let compiled = &functions[function];
+        if pc == compiled.operations.len() {
+            let frame = frames.pop().expect("a frame");
+            tapes.truncate(frame.base);
+            continue;
+        }
+        if steps == limit {
+            break Outcome::StepLimitReached;
+        }
+        steps += 1;
+
+        let (ref operation, span) = compiled.operations[pc];
+        let frame = frames.last_mut().expect("a frame");
+        frame.pc += 1;
+        match *operation {
+            Operation::Write(tape, symbol) => tapes[frame.tapes[tape]].write(symbol),
+
+            Operation::Move(tape, movement) => {
+                let tape = &mut tapes[frame.tapes[tape]];
+                if movement == Movement::Left && tape.head_position() == 0 {
+                    break error(compiled, span, RuntimeErrorKind::LeftOfFirstCell);
+                }
+                tape.move_head(movement);
+            }
+
+            Operation::Branch(tape, symbol, target) => {
+                if tapes[frame.tapes[tape]].read() == symbol {
+                    frame.pc = target;
+                }
+            }
+
+            Operation::Goto(target) => frame.pc = target,
+
+            Operation::Call(callee, ref arguments) => {
+                let arguments = arguments.iter().map(|&argument| frame.tapes[argument]).collect();
+                let frame = Frame::new(callee, arguments, &functions, &mut tapes);
+                frames.push(frame);
+            }
+
+            Operation::Return => frame.pc = compiled.operations.len(),
+
+            Operation::Error => break error(compiled, span, RuntimeErrorKind::Error),
+        }
+    };
+
+    let tapes = program.functions[main].parameters.iter().cloned().zip(tapes).collect();
+    Execution { steps, outcome, tapes }
+}
+
+fn error(function: &Compiled, span: Span, kind: RuntimeErrorKind) -> Outcome {
+    Outcome::Error(RuntimeError { function: function.name.clone(), span, kind })
+}
+
+/// A command with its names replaced by indices: tapes by their position in
+/// the function, labels by the position of the command they mark and
+/// functions by their position in the program.
+enum Operation {
+    Write(usize, char),
+    Move(usize, Movement),
+    Branch(usize, char, usize),
+    Goto(usize),
+    Call(usize, Vec<usize>),
+    Return,
+    Error,
+}
+
+struct Compiled {
+    name: String,
+    parameters: usize,
+    variables: usize,
+    operations: Vec<(Operation, Span)>,
+}
+
+impl Compiled {
+    fn new(program: &Program, function: &Function) -> Compiled {
+        let tapes: HashMap<&str, usize> = function.tapes().into_iter().enumerate().map(|(index, tape)| (tape, index)).collect();
+        let mut labels = HashMap::new();
+        let mut position = 0;
+        for command in &function.commands {
+            match command.kind {
+                CommandKind::Label(ref label) => {
+                    labels.insert(label.as_str(), position);
+                }
+
+                _ => position += 1,
+            }
+        }
+
+        let tape = |name: &String| tapes[name.as_str()];
+        let label = |name: &String| labels[name.as_str()];
+        let operations = function.commands.iter().filter_map(|command| {
+            let operation = match command.kind {
+                CommandKind::Label(_) => return None,
+
+                CommandKind::Write { tape: ref name, symbol } => Operation::Write(tape(name), symbol),
+
+                CommandKind::Move { tape: ref name, movement } => Operation::Move(tape(name), movement),
+
+                CommandKind::Branch { tape: ref name, symbol, label: ref target } =>
+                    Operation::Branch(tape(name), symbol, label(target)),
+
+                CommandKind::Goto(ref target) => Operation::Goto(label(target)),
+
+                CommandKind::Call { function: ref callee, ref arguments } => {
+                    let callee = program.functions.iter().position(|function| function.name == *callee).expect("a defined function");
+                    Operation::Call(callee, arguments.iter().map(&tape).collect())
+                }
+
+                CommandKind::Return => Operation::Return,
+
+                CommandKind::Error => Operation::Error,
+            };
+            Some((operation, command.span))
+        }).collect();
+
+        Compiled { name: function.name.clone(), parameters: function.parameters.len(), variables: function.variables.len(), operations }
+    }
+}
+
+/// A function being executed.
+struct Frame {
+    function: usize,
+    pc: usize,
+    /// The tapes of the function, as positions in the tapes of the program.
+    tapes: Vec<usize>,
+    /// The number of tapes in use before the variables of this call.
+    base: usize,
+}
+
+impl Frame {
+    fn new(function: usize, mut arguments: Vec<usize>, functions: &[Compiled], tapes: &mut Vec<ConcreteTape<char>>) -> Frame {
+        let base = tapes.len();
+        let fresh = functions[function].variables + functions[function].parameters - arguments.len();
+        for _ in 0..fresh {
+            arguments.push(tapes.len());
+            tapes.push(ConcreteTape::empty(BLANK));
+        }
+        Frame { function, pc: 0, tapes: arguments, base }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use laconic;
+    use laconic::codegen::{decode_output, OUTPUT};
+    use super::super::parser::parse;
+
+    fn execute(source: &str, limit: u64) -> Execution {
+        run(&parse(source).expect("a valid program"), limit)
+    }
+
+    #[test]
+    fn should_run_functions_on_tapes() {
+        let execution = execute("
+func increment(x)
+seek:
+    move x R
+    if x _ goto done
+    goto seek
+done:
+    write x 1
+end
+
+func twice(x)
+    var local
+    write local 1
+    call increment(x)
+    call increment(x)
+end
+
+func main(x, y)
+    write x S
+    call twice(x)
+    call twice(y)
+    move y L
+end
+", 1_000);
+
+        assert_eq!(execution.outcome, Outcome::Halted);
+        assert_eq!(execution.tape("x").expect("a tape x").to_vec(), vec!['S', '1', '1']);
+        assert_eq!(execution.tape("y").expect("a tape y").to_vec(), vec!['_', '1', '1']);
+        assert_eq!(execution.tape("y").expect("a tape y").head_position(), 1);
+        assert_eq!(execution.steps, 22);
+    }
+
+    #[test]
+    fn should_stop_on_errors_and_at_the_step_limit() {
+        let execution = execute("func main(x)\n    move x R\n    move x L\n    move x L\nend\n", 10);
+        match execution.outcome {
+            Outcome::Error(ref error) => assert_eq!(error.to_string(), "4:5 in `main`: moved left of the first cell"),
+
+            ref outcome => panic!("not an error: {:?}", outcome),
+        }
+
+        let execution = execute("func main(x)\n    write x 1\n    error\nend\n", 10);
+        assert_eq!(execution.outcome, Outcome::Error(RuntimeError {
+            function: "main".to_string(),
+            span: Span { line: 3, start: 5, end: 10 },
+            kind: RuntimeErrorKind::Error,
+        }));
+        assert_eq!(execution.tape("x").expect("a tape x").read(), '1');
+
+        let execution = execute("func main(x)\nloop:\n    goto loop\nend\n", 10);
+        assert_eq!(execution.outcome, Outcome::StepLimitReached);
+        assert_eq!(execution.steps, 10);
+    }
+
+    /// Run a Laconic program both directly and compiled to TMD, and check
+    /// that both print the same and end in the same way.
+    fn compare(source: &str) {
+        let mut program = laconic::parser::parse(source).expect("a valid Laconic program");
+        assert_eq!(laconic::check::check(&mut program), vec![]);
+        let expected = laconic::interp::run(&program, 1_000_000);
+        assert_ne!(expected.outcome, laconic::interp::Outcome::FuelExhausted);
+
+        let tmd = laconic::codegen::generate(&program);
+        let execution = execute(&tmd, 100_000_000);
+        let output = decode_output(&execution.tape(OUTPUT).expect("an output tape").to_vec()).expect("printed values");
+
+        assert_eq!(output, expected.output);
+        match (&expected.outcome, &execution.outcome) {
+            (laconic::interp::Outcome::Halted, Outcome::Halted) => (),
+
+            (laconic::interp::Outcome::Error(_), Outcome::Error(error)) => assert_eq!(error.kind, RuntimeErrorKind::Error),
+
+            (expected, found) => panic!("expected {:?}, found {:?}", expected, found),
+        }
+    }
+
+    #[test]
+    fn compiled_laconic_should_behave_like_laconic() {
+        compare("
+            func isPrime(int n) int {
+                int d = 2;
+                while (d * d <= n) {
+                    if (n % d == 0) {
+                        return 0;
+                    }
+                    d = d + 1;
+                }
+                return n >= 2;
+            }
+
+            func main() {
+                int n = 0;
+                while (n < 20) {
+                    if (isPrime(n)) {
+                        print(n);
+                    }
+                    n = n + 1;
+                }
+            }
+        ");
+        compare("
+            func clear(list l) list {
+                l[0] = 0;
+                return l;
+            }
+
+            func main() {
+                list2 m = [[1, 2], [3]];
+                list l = m[0];
+                l[1] = 5;
+                m[1] = clear(m[1]) + l;
+                m[0][0] = 9;
+                print(m[0][0] + m[0][1]);
+                print(len(m[1]));
+                print(m[1][0] + m[1][1] + m[1][2]);
+                print(len(m) + len([]));
+                list2 e;
+                e = e + [[]];
+                print(len(e[0]) + len(e));
+            }
+        ");
+        compare("
+            func main() {
+                int x = 3;
+                x = x * x - 1;
+                print(x);
+                x = 0 && x;
+                print(x);
+                x = 1;
+                x = 5 && x || 0;
+                print(x);
+                print(!x + (x != 1) + (x == 1) + (x <= 1) + (x > 0) + (x >= 2));
+                print(21);
+                print(100 / 7 + 100 % 7);
+                print(7 - 100);
+            }
+        ");
+        compare("
+            func main() {
+                list l = [1, 2];
+                print(l[1]);
+                print(l[0] && l[2]);
+                print(1);
+            }
+        ");
+        compare("
+            func main() {
+                print(1 / (2 - 3));
+            }
+        ");
+    }
+
+    #[derive(Clone, Debug)]
+    enum Term {
+        Number(u8),
+        Variable(usize),
+        Binary(&'static str, Box<Term>, Box<Term>),
+        Not(Box<Term>),
+    }
+
+    impl Term {
+        fn show(&self) -> String {
+            match *self {
+                Term::Number(n) => n.to_string(),
+
+                Term::Variable(index) => ["a", "b", "c"][index].to_string(),
+
+                Term::Binary(operator, ref left, ref right) => format!("({} {} {})", left.show(), operator, right.show()),
+
+                Term::Not(ref operand) => format!("!{}", operand.show()),
+            }
+        }
+    }
+
+    fn term() -> impl Strategy<Value = Term> {
+        let leaf = prop_oneof![(0u8..6).prop_map(Term::Number), (0usize..3).prop_map(Term::Variable)];
+        leaf.prop_recursive(3, 12, 2, |inner| {
+            let operator = prop::sample::select(vec!["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"]);
+            prop_oneof![
+                (operator, inner.clone(), inner.clone()).prop_map(|(operator, left, right)| Term::Binary(operator, Box::new(left), Box::new(right))),
+                inner.prop_map(|operand| Term::Not(Box::new(operand))),
+            ]
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+        #[test]
+        fn compiled_expressions_should_evaluate_like_laconic(terms in prop::collection::vec(term(), 1..4), values in prop::collection::vec(0u8..4, 3)) {
+            let mut source = format!("func main() {{ int a = {}; int b = {}; int c = {};", values[0], values[1], values[2]);
+            for (index, term) in terms.iter().enumerate() {
+                source.push_str(&format!(" {} = {}; print({});", ["a", "b", "c"][index % 3], term.show(), ["a", "b", "c"][index % 3]));
+            }
+            source.push_str(" }");
+            compare(&source);
+        }
+    }
+}
@@ -1,3 +1,58 @@
+#![deny(missing_docs)]
+//! TMD, the Turing machine description language, is the intermediate
+//! language between Laconic and two-symbol Turing machines.
+//!
+//! A TMD program is a sequence of functions that work on tapes. Every tape is
+//! infinite to the right, has its own read/write head and starts out blank,
+//! with the head on the first cell. Execution starts in `main` and the program
+//! halts when `main` returns.
+//!
+//! ```text
+//! // x := x + 1, for x written in unary after a start marker.
+//! func increment(x)
+//! seek:
+//!     move x R
+//!     if x _ goto done
+//!     goto seek
+//! done:
+//!     write x 1
+//! rewind:
+//!     move x L
+//!     if x S goto finished
+//!     goto rewind
+//! finished:
+//! end
+//!
+//! func main(x)
+//!     write x S
+//!     call increment(x)
+//! end
+//! ```
+//!
+//! A function starts with a header naming its parameters and finishes with
+//! `end`. The parameters are the tapes of the caller, passed by reference, and
+//! the tapes of `main` are those of the program. Further tapes are declared
+//! with `var`; they are fresh for every call. In between are commands, one per
+//! line:
+//!
+//! * `name:` defines a label, which can be jumped to;
+//! * `write x S` writes the symbol `S` under the head of tape `x`;
+//! * `move x L` and `move x R` move the head of tape `x`;
+//! * `if x S goto name` jumps to a label when the head of `x` reads `S`;
+//! * `goto name` jumps to a label;
+//! * `call f(x, y)` runs function `f` on tapes `x` and `y`;
+//! * `return` goes back to the caller, as does reaching `end`;
+//! * `error` stops the program with an error.
+//!
+//! Symbols are single characters, where `_` is the blank. Names consist of
+//! letters, digits, `_` and `.`. Moving a head left of the first cell is an
+//! error. Everything after `//` is a comment.
+
+pub mod ast;
+pub mod compile;
+pub mod interp;
+pub mod parser;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,561 @@
+//! Parser reads TMD source into a `Program`.
+//!
+//! TMD is line oriented, so a problem only spoils the line it is on. The
+//! parser reports it, skips the line and carries on, so a single run reports
+//! all problems. After the functions are read, labels, tapes and calls are
+//! checked against their definitions.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use tm::movement::Movement;
+use super::ast::{Command, CommandKind, Function, Program, Span};
+
+/// Problem encountered while parsing TMD.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    /// Where the problem is.
+    pub span: Span,
+    /// What the problem is.
+    pub kind: ParseErrorKind,
+}
+
+/// The kinds of problems encountered while parsing TMD.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseErrorKind {
+    /// Something else was found than what was expected.
+    Expected(&'static str),
+    /// A character that can not start a token.
+    InvalidCharacter(char),
+    /// A line that is not a command.
+    UnknownCommand(String),
+    /// A function is defined twice.
+    DuplicateFunction(String),
+    /// A tape is declared twice in a function.
+    DuplicateTape(String),
+    /// A label is defined twice in a function.
+    DuplicateLabel(String),
+    /// A tape is used but not declared in the function.
+    UndeclaredTape(String),
+    /// A label is jumped to but not defined in the function.
+    UndefinedLabel(String),
+    /// A function is called but not defined.
+    UndefinedFunction(String),
+    /// A function is called with the wrong number of tapes.
+    Arity {
+        /// The called function.
+        function: String,
+        /// The number of parameters.
+        expected: usize,
+        /// The number of arguments.
+        found: usize,
+    },
+    /// There is no function `main`.
+    MissingMain,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.span)?;
+        match self.kind {
+            ParseErrorKind::Expected(what) => write!(f, "expected {}", what),
+
+            ParseErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+
+            ParseErrorKind::UnknownCommand(ref command) => write!(f, "unknown command `{}`", command),
+
+            ParseErrorKind::DuplicateFunction(ref name) => write!(f, "function `{}` is already defined", name),
+
+            ParseErrorKind::DuplicateTape(ref name) => write!(f, "tape `{}` is already declared", name),
+
+            ParseErrorKind::DuplicateLabel(ref name) => write!(f, "label `{}` is already defined", name),
+
+            ParseErrorKind::UndeclaredTape(ref name) => write!(f, "tape `{}` is not declared", name),
+
+            ParseErrorKind::UndefinedLabel(ref name) => write!(f, "label `{}` is not defined", name),
+
+            ParseErrorKind::UndefinedFunction(ref name) => write!(f, "there is no function `{}`", name),
+
+            ParseErrorKind::Arity { ref function, expected, found } =>
+                write!(f, "`{}` takes {} tape(s), found {}", function, expected, found),
+
+            ParseErrorKind::MissingMain => write!(f, "there is no function `main`"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a TMD program, reporting all problems found.
+pub fn parse(source: &str) -> Result<Program, Vec<ParseError>> {
+    let mut errors = Vec::new();
+    let mut functions: Vec<Function> = Vec::new();
+    let mut current: Option<Function> = None;
+    let mut last = Span { line: 1, start: 1, end: 1 };
+
+    for (index, text) in source.lines().enumerate() {
+        let line = match Line::tokenize(index + 1, text) {
+            Ok(line) => line,
+
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        if line.tokens.is_empty() {
+            continue;
+        }
+        last = line.span(0);
+
+        let result = match current.take() {
+            None => line.header().map(|function| current = Some(function)),
+
+            Some(function) if line.is_word(0, "func") => {
+                errors.push(line.error(0, ParseErrorKind::Expected("`end` before the next function")));
+                functions.push(function);
+                line.header().map(|function| current = Some(function))
+            }
+
+            Some(mut function) => if line.is_word(0, "end") {
+                line.end_of_line(1).map(|_| functions.push(function))
+            } else {
+                let result = line.body(&mut function);
+                current = Some(function);
+                result
+            },
+        };
+        if let Err(error) = result {
+            errors.push(error);
+        }
+    }
+    if let Some(function) = current {
+        errors.push(ParseError { span: last, kind: ParseErrorKind::Expected("`end`") });
+        functions.push(function);
+    }
+
+    let program = Program { functions };
+    resolve(&program, &mut errors);
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Check the names used in `program` against their definitions.
+fn resolve(program: &Program, errors: &mut Vec<ParseError>) {
+    let mut names = HashSet::new();
+    for function in &program.functions {
+        if !names.insert(&function.name) {
+            errors.push(ParseError { span: function.span, kind: ParseErrorKind::DuplicateFunction(function.name.clone()) });
+        }
+
+        let mut tapes = HashSet::new();
+        for tape in function.tapes() {
+            if !tapes.insert(tape) {
+                errors.push(ParseError { span: function.span, kind: ParseErrorKind::DuplicateTape(tape.to_string()) });
+            }
+        }
+
+        let mut labels = HashSet::new();
+        for command in &function.commands {
+            if let CommandKind::Label(ref label) = command.kind {
+                if !labels.insert(label) {
+                    errors.push(ParseError { span: command.span, kind: ParseErrorKind::DuplicateLabel(label.clone()) });
+                }
+            }
+        }
+
+        for command in &function.commands {
+            let error = |kind| ParseError { span: command.span, kind };
+            let used = match command.kind {
+                CommandKind::Write { ref tape, .. } | CommandKind::Move { ref tape, .. } => vec![tape],
+
+                CommandKind::Branch { ref tape, ref label, .. } => {
+                    if !labels.contains(label) {
+                        errors.push(error(ParseErrorKind::UndefinedLabel(label.clone())));
+                    }
+                    vec![tape]
+                }
+
+                CommandKind::Goto(ref label) => {
+                    if !labels.contains(label) {
+                        errors.push(error(ParseErrorKind::UndefinedLabel(label.clone())));
+                    }
+                    vec![]
+                }
+
+                CommandKind::Call { function: ref callee, ref arguments } => {
+                    match program.function(callee) {
+                        Some(callee) if callee.parameters.len() != arguments.len() => {
+                            errors.push(error(ParseErrorKind::Arity {
+                                function: callee.name.clone(),
+                                expected: callee.parameters.len(),
+                                found: arguments.len(),
+                            }));
+                        }
+
+                        Some(_) => (),
+
+                        None => errors.push(error(ParseErrorKind::UndefinedFunction(callee.clone()))),
+                    }
+                    arguments.iter().collect()
+                }
+
+                CommandKind::Label(_) | CommandKind::Return | CommandKind::Error => vec![],
+            };
+            for tape in used {
+                if !tapes.contains(tape.as_str()) {
+                    errors.push(error(ParseErrorKind::UndeclaredTape(tape.clone())));
+                }
+            }
+        }
+    }
+
+    if program.function("main").is_none() {
+        errors.push(ParseError { span: Span { line: 1, start: 1, end: 1 }, kind: ParseErrorKind::MissingMain });
+    }
+    errors.sort_by_key(|error| (error.span.line, error.span.start));
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Token<'a> {
+    Word(&'a str),
+    Colon,
+    Comma,
+    LeftParen,
+    RightParen,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+struct Line<'a> {
+    number: usize,
+    tokens: Vec<(usize, Token<'a>, usize)>,
+    end: usize,
+}
+
+impl<'a> Line<'a> {
+    fn tokenize(number: usize, text: &'a str) -> Result<Line<'a>, ParseError> {
+        let text = match text.find("//") {
+            Some(index) => &text[..index],
+
+            None => text,
+        };
+        let mut tokens = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            let mut end = index + c.len_utf8();
+            let token = match c {
+                ':' => Token::Colon,
+
+                ',' => Token::Comma,
+
+                '(' => Token::LeftParen,
+
+                ')' => Token::RightParen,
+
+                c if is_word(c) => {
+                    while let Some(&(next, c)) = chars.peek() {
+                        if !is_word(c) {
+                            break;
+                        }
+                        end = next + c.len_utf8();
+                        chars.next();
+                    }
+                    Token::Word(&text[index..end])
+                }
+
+                c if c.is_whitespace() => continue,
+
+                c => {
+                    let span = Span { line: number, start: index + 1, end: end + 1 };
+                    return Err(ParseError { span, kind: ParseErrorKind::InvalidCharacter(c) });
+                }
+            };
+            tokens.push((index + 1, token, end + 1));
+        }
+        Ok(Line { number, tokens, end: text.trim_end().len() + 1 })
+    }
+
+    fn span(&self, position: usize) -> Span {
+        match self.tokens.get(position) {
+            Some(&(start, _, end)) => Span { line: self.number, start, end },
+
+            None => Span { line: self.number, start: self.end, end: self.end },
+        }
+    }
+
+    /// The span from the token at `position` to the end of the line.
+    fn rest(&self, position: usize) -> Span {
+        Span { end: self.end, .. self.span(position) }
+    }
+
+    fn error(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { span: self.span(position), kind }
+    }
+
+    fn is_word(&self, position: usize, word: &str) -> bool {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word(found), _)) => found == word,
+
+            _ => false,
+        }
+    }
+
+    fn word(&self, position: usize, what: &'static str) -> Result<&'a str, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word(word), _)) => Ok(word),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected(what))),
+        }
+    }
+
+    fn punctuation(&self, position: usize, expected: Token, what: &'static str) -> Result<(), ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, token, _)) if token == expected => Ok(()),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected(what))),
+        }
+    }
+
+    fn symbol(&self, position: usize) -> Result<char, ParseError> {
+        let word = self.word(position, "a symbol")?;
+        let mut chars = word.chars();
+        match (chars.next(), chars.next()) {
+            (Some(symbol), None) if symbol != '.' => Ok(symbol),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected("a symbol"))),
+        }
+    }
+
+    fn movement(&self, position: usize) -> Result<Movement, ParseError> {
+        match self.tokens.get(position) {
+            Some(&(_, Token::Word("L"), _)) => Ok(Movement::Left),
+
+            Some(&(_, Token::Word("R"), _)) => Ok(Movement::Right),
+
+            _ => Err(self.error(position, ParseErrorKind::Expected("`L` or `R`"))),
+        }
+    }
+
+    fn end_of_line(&self, position: usize) -> Result<(), ParseError> {
+        if position < self.tokens.len() {
+            Err(self.error(position, ParseErrorKind::Expected("end of line")))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Parse `(a, b, c)` starting at `position`, returning the names and the
+    /// position after the closing parenthesis.
+    fn names(&self, mut position: usize, what: &'static str) -> Result<(Vec<String>, usize), ParseError> {
+        self.punctuation(position, Token::LeftParen, "`(`")?;
+        position += 1;
+        let mut names = Vec::new();
+        if self.punctuation(position, Token::RightParen, "`)`").is_ok() {
+            return Ok((names, position + 1));
+        }
+        loop {
+            names.push(self.word(position, what)?.to_string());
+            position += 1;
+            if self.punctuation(position, Token::RightParen, "`,` or `)`").is_ok() {
+                return Ok((names, position + 1));
+            }
+            self.punctuation(position, Token::Comma, "`,` or `)`")?;
+            position += 1;
+        }
+    }
+
+    fn header(&self) -> Result<Function, ParseError> {
+        if !self.is_word(0, "func") {
+            return Err(self.error(0, ParseErrorKind::Expected("`func`")));
+        }
+        let name = self.word(1, "function name")?.to_string();
+        let (parameters, position) = self.names(2, "parameter name")?;
+        self.end_of_line(position)?;
+        Ok(Function { name, parameters, variables: Vec::new(), commands: Vec::new(), span: self.rest(0) })
+    }
+
+    fn body(&self, function: &mut Function) -> Result<(), ParseError> {
+        if let Some(&(_, Token::Colon, _)) = self.tokens.get(1) {
+            let label = self.word(0, "label")?.to_string();
+            self.end_of_line(2)?;
+            function.commands.push(Command { kind: CommandKind::Label(label), span: self.span(0) });
+            return Ok(());
+        }
+
+        let command = self.word(0, "a command")?;
+        let (kind, end) = match command {
+            "var" => {
+                if self.tokens.len() == 1 {
+                    return Err(self.error(1, ParseErrorKind::Expected("tape name")));
+                }
+                for position in 1..self.tokens.len() {
+                    function.variables.push(self.word(position, "tape name")?.to_string());
+                }
+                return Ok(());
+            }
+
+            "write" => {
+                let tape = self.word(1, "tape name")?.to_string();
+                let symbol = self.symbol(2)?;
+                (CommandKind::Write { tape, symbol }, 3)
+            }
+
+            "move" => {
+                let tape = self.word(1, "tape name")?.to_string();
+                let movement = self.movement(2)?;
+                (CommandKind::Move { tape, movement }, 3)
+            }
+
+            "if" => {
+                let tape = self.word(1, "tape name")?.to_string();
+                let symbol = self.symbol(2)?;
+                if !self.is_word(3, "goto") {
+                    return Err(self.error(3, ParseErrorKind::Expected("`goto`")));
+                }
+                let label = self.word(4, "label")?.to_string();
+                (CommandKind::Branch { tape, symbol, label }, 5)
+            }
+
+            "goto" => (CommandKind::Goto(self.word(1, "label")?.to_string()), 2),
+
+            "call" => {
+                let function = self.word(1, "function name")?.to_string();
+                let (arguments, position) = self.names(2, "tape name")?;
+                (CommandKind::Call { function, arguments }, position)
+            }
+
+            "return" => (CommandKind::Return, 1),
+
+            "error" => (CommandKind::Error, 1),
+
+            command => return Err(self.error(0, ParseErrorKind::UnknownCommand(command.to_string()))),
+        };
+        self.end_of_line(end)?;
+        function.commands.push(Command { kind, span: self.rest(0) });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use laconic;
+
+    #[test]
+    fn should_parse_functions_and_commands() {
+        let program = parse("
+// Adds one to x.
+func increment(x)
+seek:
+    move x R        // find the end
+    if x _ goto done
+    goto seek
+done:
+    write x 1
+end
+
+func main(out)
+    var a b
+    write a S
+    call increment(a)
+    return
+    error
+end
+").expect("a valid program");
+
+        assert_eq!(program.functions.len(), 2);
+        let increment = program.function("increment").expect("a function increment");
+        assert_eq!(increment.parameters, vec!["x"]);
+        assert_eq!(increment.commands.len(), 6);
+        assert_eq!(increment.commands[1], Command {
+            kind: CommandKind::Move { tape: "x".to_string(), movement: Movement::Right },
+            span: Span { line: 5, start: 5, end: 13 },
+        });
+        assert_eq!(increment.commands[2].kind, CommandKind::Branch {
+            tape: "x".to_string(),
+            symbol: '_',
+            label: "done".to_string(),
+        });
+
+        let main = program.function("main").expect("a function main");
+        assert_eq!(main.tapes(), vec!["out", "a", "b"]);
+        assert_eq!(main.commands[1].kind, CommandKind::Call { function: "increment".to_string(), arguments: vec!["a".to_string()] });
+        assert_eq!(main.commands[2].kind, CommandKind::Return);
+        assert_eq!(main.commands[3].kind, CommandKind::Error);
+    }
+
+    #[test]
+    fn should_report_all_problems() {
+        let errors = parse("
+func f(x, y)
+    var x
+    write x SS
+    move y U
+    jump x
+top:
+top:
+    if z 1 goto bottom
+    call g(x)
+    call f(x)
+    goto $
+end
+").unwrap_err();
+
+        let reported: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(reported, vec![
+            "1:1: there is no function `main`",
+            "2:1: tape `x` is already declared",
+            "4:13: expected a symbol",
+            "5:12: expected `L` or `R`",
+            "6:5: unknown command `jump`",
+            "8:1: label `top` is already defined",
+            "9:5: label `bottom` is not defined",
+            "9:5: tape `z` is not declared",
+            "10:5: there is no function `g`",
+            "11:5: `f` takes 2 tape(s), found 1",
+            "12:10: invalid character '$'",
+        ]);
+    }
+
+    #[test]
+    fn should_report_a_missing_end() {
+        let errors = parse("func main()\n    return\nfunc other()\n").unwrap_err();
+
+        assert_eq!(errors[0].to_string(), "3:1: expected `end` before the next function");
+        assert_eq!(errors[1].to_string(), "3:1: expected `end`");
+    }
+
+    #[test]
+    fn should_load_generated_programs() {
+        let mut program = laconic::parser::parse("
+            func sum(list l) int {
+                int total;
+                int i = 0;
+                while (i < len(l)) {
+                    total = total + l[i];
+                    i = i + 1;
+                }
+                return total;
+            }
+
+            func main() {
+                list2 m = [[1, 2], [3]];
+                m[1][0] = 4;
+                print(sum(m[0]) + sum(m[1]) / 2);
+            }
+        ").expect("a valid Laconic program");
+        assert_eq!(laconic::check::check(&mut program), vec![]);
+
+        let program = parse(&laconic::codegen::generate(&program)).expect("a valid TMD program");
+
+        let main = program.function("main").expect("a function main");
+        assert_eq!(main.parameters, vec![laconic::codegen::OUTPUT]);
+        assert!(program.function("sum").is_some());
+        assert!(program.function("lib.set2").is_some());
+    }
+}